@@ -7,10 +7,17 @@ use crate::{dependencies::MAIL_SERVER_DEP, error::ClientError, handlers, APP_VER
 /// The type of the result returned by your client's entry points.
 pub type ClientResult<T = Response> = Result<T, ClientError>;
 
+/// Reply id of the messages and batches sent to the server, which returns the result of each message.
+pub const PROCESS_MESSAGES_REPLY_ID: u64 = 1;
+
 const APP: App = App::new(IBCMAIL_CLIENT_ID, APP_VERSION, None)
     .with_execute(handlers::execute_handler)
     .with_query(handlers::query_handler)
     .with_migrate(handlers::migrate_handler)
+    .with_replies(&[(
+        PROCESS_MESSAGES_REPLY_ID,
+        handlers::process_messages_reply_handler,
+    )])
     .with_dependencies(&[MAIL_SERVER_DEP]);
 
 // Export handlers
@@ -1,21 +1,25 @@
 use abstract_app::objects::TruncatedChainId;
 use abstract_app::{
-    sdk::ModuleRegistryInterface,
+    sdk::{ModuleInterface, ModuleRegistryInterface},
     traits::{AbstractResponse, AccountIdentification},
 };
 use base64::prelude::*;
-use cosmwasm_std::{ensure_eq, CosmosMsg, Deps, DepsMut, Env, MessageInfo};
+use cosmwasm_std::{
+    ensure, ensure_eq, to_json_binary, CosmosMsg, Deps, DepsMut, Env, MessageInfo, SubMsg,
+};
 use ibcmail::{
     client::{
-        state::{RECEIVED, SENT},
+        state::{reply_key, RECEIVED, RECEIVED_TRACES, REPLY_ROUTES, RETURN_ROUTES, SENT},
         ClientApp,
     },
+    is_account_address,
+    receiver::is_mail_server,
     server::api::{MailServer, ServerInterface},
-    IbcMailMessage, Message, Recipient, Route, Sender, IBCMAIL_SERVER_ID,
+    Header, IbcMailMessage, Message, Recipient, Route, Sender, EMAIL_VERSION, IBCMAIL_CLIENT_ID,
 };
 
 use crate::{
-    contract::{App, ClientResult},
+    contract::{App, ClientResult, PROCESS_MESSAGES_REPLY_ID},
     error::ClientError,
     msg::ClientExecuteMsg,
 };
@@ -32,7 +36,11 @@ pub fn execute_handler(
         ClientExecuteMsg::SendMessage { message, route } => {
             send_msg(deps, env, info, message, route, app)
         }
-        ClientExecuteMsg::ReceiveMessage(message) => receive_msg(deps, info, message, app),
+        ClientExecuteMsg::SendMessages(messages) => send_msgs(deps, env, info, messages, app),
+        ClientExecuteMsg::ReceiveMessage { msg, header } => {
+            receive_msg(deps, info, msg, header, app)
+        }
+        ClientExecuteMsg::ClaimMail { address } => claim_mail(deps, address, app),
     }
 }
 // # ANCHOR_END: execute_handler
@@ -41,55 +49,155 @@ pub fn execute_handler(
 fn send_msg(
     deps: DepsMut,
     env: Env,
-    _info: MessageInfo,
+    info: MessageInfo,
     msg: Message,
     route: Option<Route>,
     app: ClientApp,
 ) -> ClientResult {
+    let sender = message_sender(deps.as_ref(), &env, &info, &app)?;
+    let (to_send, route) = outgoing_msg(deps, &env, sender, msg, route, &app, None)?;
+
+    // Funds sent along prepay the relay fees of the route, the reply returns any excess to the caller
+    let server: MailServer<_> = app.mail_server(deps.as_ref());
+    let route_msg: CosmosMsg = server.process_msg_with_funds(to_send, route, info.funds)?;
+
+    Ok(app.response("send").add_submessage(
+        SubMsg::reply_on_success(route_msg, PROCESS_MESSAGES_REPLY_ID)
+            .with_payload(to_json_binary(&info.sender)?),
+    ))
+}
+
+/// Construct the message to send to the server, record it as sent by the account and pick its route.
+/// Messages in a batch include their index in the id so identical messages stay distinct.
+fn outgoing_msg(
+    deps: DepsMut,
+    env: &Env,
+    sender: Sender,
+    msg: Message,
+    route: Option<Route>,
+    app: &ClientApp,
+    batch_index: Option<usize>,
+) -> ClientResult<(IbcMailMessage, Option<Route>)> {
     // validate basic fields of message, construct message to send to server
-    let to_hash = format!("{:?}{:?}{:?}", env.block.time, msg.subject, msg.recipient);
+    let mut to_hash = format!("{:?}{:?}{:?}", env.block.time, msg.subject, msg.recipient);
+    if let Some(index) = batch_index {
+        to_hash.push_str(&index.to_string());
+    }
     let hash = <sha2::Sha256 as sha2::Digest>::digest(to_hash);
     let base_64_hash = BASE64_STANDARD.encode(hash);
     let to_send = IbcMailMessage {
         id: base_64_hash,
-        sender: Sender::account(
-            app.account_id(deps.as_ref()).unwrap(),
-            Some(TruncatedChainId::new(&env)),
-        ),
+        sender,
         message: Message {
             recipient: msg.recipient,
             subject: msg.subject,
             body: msg.body,
         },
         timestamp: env.block.time,
-        version: app.version().to_string(),
+        version: EMAIL_VERSION.to_string(),
     };
 
     SENT.save(deps.storage, to_send.id.clone(), &to_send)?;
 
-    let server: MailServer<_> = app.mail_server(deps.as_ref());
-    let route_msg: CosmosMsg = server.process_msg(to_send, route)?;
+    // Replies follow the route the sender's mail arrived over unless a route is given
+    let route = match route {
+        Some(route) => Some(route),
+        None => REPLY_ROUTES.may_load(deps.storage, reply_key(&to_send.message.recipient)?)?,
+    };
 
-    Ok(app.response("send").add_message(route_msg))
+    Ok((to_send, route))
 }
 // # ANCHOR_END: send_msg
 
+/// Send a batch of messages with a single request to the server
+fn send_msgs(
+    mut deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    msgs: Vec<Message>,
+    app: ClientApp,
+) -> ClientResult {
+    let sender = message_sender(deps.as_ref(), &env, &info, &app)?;
+    let to_send = msgs
+        .into_iter()
+        .enumerate()
+        .map(|(index, msg)| {
+            outgoing_msg(
+                deps.branch(),
+                &env,
+                sender.clone(),
+                msg,
+                None,
+                &app,
+                Some(index),
+            )
+        })
+        .collect::<ClientResult<Vec<_>>>()?;
+
+    let server: MailServer<_> = app.mail_server(deps.as_ref());
+    let route_msg: CosmosMsg = server.process_msgs(to_send, info.funds)?;
+
+    // Messages the server rejects are removed from the sent messages in the reply,
+    // which returns their funds to the caller
+    Ok(app.response("send_batch").add_submessage(
+        SubMsg::reply_on_success(route_msg, PROCESS_MESSAGES_REPLY_ID)
+            .with_payload(to_json_binary(&info.sender)?),
+    ))
+}
+
+/// Determine the sender of an outgoing message.
+/// Requests coming from another module installed on this account are marked as sent by that module.
+/// Only the account, its owner and its modules may send mail through the client.
+fn message_sender(
+    deps: Deps,
+    env: &Env,
+    info: &MessageInfo,
+    app: &ClientApp,
+) -> ClientResult<Sender> {
+    let account = app.account(deps)?;
+    let account_id = app.account_id(deps)?;
+    let chain = Some(TruncatedChainId::new(env));
+
+    if is_account_address(&deps.querier, account.addr(), &info.sender)? {
+        return Ok(Sender::account(account_id, chain));
+    }
+
+    if let Ok(module) = app.module_registry(deps)?.module_info(info.sender.clone()) {
+        let module_id = module.info.id();
+        let installed_address = app.modules(deps).module_address(&module_id).ok();
+        if installed_address.as_ref() == Some(&info.sender) {
+            return Ok(Sender::module(account_id, module_id, chain));
+        }
+    }
+
+    Err(ClientError::Unauthorized(info.sender.to_string()))
+}
+
 /// Receive a message from the server
 // # ANCHOR: receive_msg
-fn receive_msg(deps: DepsMut, info: MessageInfo, msg: IbcMailMessage, app: App) -> ClientResult {
-    let sender_module = app
-        .module_registry(deps.as_ref())?
-        .module_info(info.sender)
-        .map_err(|_| ClientError::NotMailServer {})?;
-    ensure_eq!(
-        sender_module.info.id(),
-        IBCMAIL_SERVER_ID,
+fn receive_msg(
+    deps: DepsMut,
+    info: MessageInfo,
+    msg: IbcMailMessage,
+    header: Header,
+    app: App,
+) -> ClientResult {
+    ensure!(
+        is_mail_server(&app, deps.as_ref(), info.sender),
         ClientError::NotMailServer {}
     );
 
     ensure_correct_recipient(deps.as_ref(), &msg.message.recipient, &app)?;
 
     RECEIVED.save(deps.storage, msg.id.clone(), &msg)?;
+    RECEIVED_TRACES.save(deps.storage, msg.id.clone(), &header.trace)?;
+
+    // Remember the way back so replies don't need an explicit route
+    let return_route = header.return_route();
+    RETURN_ROUTES.save(deps.storage, msg.id.clone(), &return_route)?;
+    if let Some(reply_recipient) = msg.sender.reply_recipient() {
+        REPLY_ROUTES.save(deps.storage, reply_key(&reply_recipient)?, &return_route)?;
+    }
 
     Ok(app
         .response("received")
@@ -97,6 +205,19 @@ fn receive_msg(deps: DepsMut, info: MessageInfo, msg: IbcMailMessage, app: App)
 }
 // # ANCHOR_END: receive_msg
 
+/// Pull the mail held by the server into this client
+fn claim_mail(deps: DepsMut, address: Option<String>, app: App) -> ClientResult {
+    let address = match address {
+        Some(address) => address,
+        None => app.account(deps.as_ref())?.addr().to_string(),
+    };
+
+    let server: MailServer<_> = app.mail_server(deps.as_ref());
+    let claim_msg: CosmosMsg = server.claim_mail(address)?;
+
+    Ok(app.response("claim_mail").add_message(claim_msg))
+}
+
 fn ensure_correct_recipient(
     deps: Deps,
     recipient: &Recipient,
@@ -119,6 +240,25 @@ fn ensure_correct_recipient(
                 .module_registry(deps)?
                 .query_namespace(namespace.to_owned())?;
         }
+        Recipient::Module {
+            account, module_id, ..
+        } => {
+            let our_id = app.account_id(deps)?;
+
+            // check that the message is addressed to this client on the current account
+            ensure_eq!(module_id, IBCMAIL_CLIENT_ID, ClientError::NotRecipient {});
+            ensure_eq!(account, &our_id, ClientError::NotRecipient {});
+        }
+        Recipient::Address { address, .. } => {
+            let address = deps.api.addr_validate(address)?;
+            let our_account = app.account(deps)?;
+
+            // mail for an address is accepted by the account at that address or owned by it
+            ensure!(
+                is_account_address(&deps.querier, our_account.addr(), &address)?,
+                ClientError::NotRecipient {}
+            );
+        }
         _ => Err(ClientError::NotImplemented("recipients".to_string()))?,
     }
     Ok(())
@@ -1,7 +1,9 @@
 pub mod execute;
 pub mod migrate;
 pub mod query;
+pub mod reply;
 
 pub use crate::handlers::{
     execute::execute_handler, migrate::migrate_handler, query::query_handler,
+    reply::process_messages_reply_handler,
 };
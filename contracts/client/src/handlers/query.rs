@@ -5,8 +5,8 @@ use cw_storage_plus::{Bound, Map, PrimaryKey};
 use ibcmail::{
     client::{
         error::ClientError,
-        msg::{MessageFilter, MessagesResponse},
-        state::{RECEIVED, SENT},
+        msg::{MessageFilter, MessagesResponse, ReturnRouteResponse, TraceResponse},
+        state::{RECEIVED, RECEIVED_TRACES, RETURN_ROUTES, SENT},
     },
     MessageHash, MessageStatus,
 };
@@ -38,6 +38,8 @@ pub fn query_handler(
             start_after,
             limit,
         )?),
+        ClientQueryMsg::Trace { id } => to_json_binary(&query_trace(deps, id)?),
+        ClientQueryMsg::ReturnRoute { id } => to_json_binary(&query_return_route(deps, id)?),
     }
     .map_err(Into::into)
 }
@@ -82,7 +84,7 @@ fn query_messages(
 fn query_messages_list(
     deps: Deps,
     status: MessageStatus,
-    _filter: Option<MessageFilter>,
+    filter: Option<MessageFilter>,
     start: Option<MessageHash>,
     limit: Option<u32>,
 ) -> ClientResult<MessagesResponse> {
@@ -92,13 +94,34 @@ fn query_messages_list(
         _ => return Err(ClientError::NotImplemented("message type".to_string())),
     };
 
+    // The filter applies to the messages of the page
     let messages = cw_paginate::paginate_map(
         &map,
         deps.storage,
         start.as_ref().map(Bound::exclusive),
         limit,
-        |_id, message| Ok::<_, ClientError>(message),
-    )?;
+        |_id, message| {
+            let matches = filter
+                .as_ref()
+                .map_or(true, |filter| filter.matches(&message));
+            Ok::<_, ClientError>(matches.then_some(message))
+        },
+    )?
+    .into_iter()
+    .flatten()
+    .collect();
 
     Ok(MessagesResponse { messages })
 }
+
+fn query_trace(deps: Deps, id: MessageHash) -> ClientResult<TraceResponse> {
+    let trace = RECEIVED_TRACES.load(deps.storage, id)?;
+
+    Ok(TraceResponse { trace })
+}
+
+fn query_return_route(deps: Deps, id: MessageHash) -> ClientResult<ReturnRouteResponse> {
+    let route = RETURN_ROUTES.load(deps.storage, id)?;
+
+    Ok(ReturnRouteResponse { route })
+}
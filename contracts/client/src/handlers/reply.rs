@@ -0,0 +1,41 @@
+use abstract_app::traits::AbstractResponse;
+use cosmwasm_std::{from_json, Addr, BankMsg, DepsMut, Env, Reply, StdError};
+use cw_utils::parse_execute_response_data;
+use ibcmail::{client::state::SENT, server::msg::ProcessMessagesResponse};
+
+use crate::contract::{App, ClientResult};
+
+/// Forget the messages of a batch that the server could not send, so only sent mail is listed as sent,
+/// and return the funds that the server refunded to the sender of the messages.
+pub fn process_messages_reply_handler(
+    deps: DepsMut,
+    _env: Env,
+    app: App,
+    reply: Reply,
+) -> ClientResult {
+    let response = reply.result.into_result().map_err(StdError::generic_err)?;
+    let data = response
+        .msg_responses
+        .first()
+        .and_then(|response| parse_execute_response_data(&response.value).ok())
+        .and_then(|response| response.data)
+        .ok_or_else(|| StdError::generic_err("server did not return the batch results"))?;
+    let ProcessMessagesResponse { results, refund } = from_json(data)?;
+
+    let mut response = app.response("send_batch_result");
+    for result in results {
+        if let Some(error) = result.error {
+            SENT.remove(deps.storage, result.id.clone());
+            response = response.add_attribute("failed", format!("{}: {error}", result.id));
+        }
+    }
+    if !refund.is_empty() {
+        let sender: Addr = from_json(reply.payload)?;
+        response = response.add_message(BankMsg::Send {
+            to_address: sender.to_string(),
+            amount: refund,
+        });
+    }
+
+    Ok(response)
+}
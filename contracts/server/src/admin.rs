@@ -0,0 +1,411 @@
+use abstract_adapter::objects::TruncatedChainId;
+use abstract_adapter::sdk::{
+    features::{AccountIdentification, ModuleIdentification},
+    ModuleRegistryInterface,
+};
+use abstract_adapter::std::{
+    adapter,
+    ibc::{Callback, ModuleQuery},
+    ibc_client::{self, InstalledModuleIdentification},
+    objects::{module::ModuleInfo, namespace::Namespace},
+    registry::NamespaceResponse,
+};
+use abstract_adapter::traits::AbstractResponse;
+use cosmwasm_std::{
+    ensure, ensure_eq, to_json_binary, wasm_execute, Deps, DepsMut, Empty, Env, QueryRequest,
+    StdResult,
+};
+use ibcmail::{
+    server::{
+        msg::{ServerCallbackMsg, ServerQueryMsg},
+        state::{
+            Ics20Route, PauseMode, PolytoneRoute, RateLimit, RelayFee, RemoteRelayFee,
+            BLOCKED_LINKS, CHAIN_BREAKERS, CHAIN_RATE_LIMITS, CONFIG, IBC_CHANNELS,
+            IBC_RELAY_ACCOUNT, ICS20_ROUTES, LINK_COSTS, OPEN_CHANNELS, PAUSED, PEER_WIRE_VERSIONS,
+            POLYTONE_ROUTES, PREFERRED_HOPS, REMOTE_RELAY_FEES, ROUTE_TABLE, TRUSTED_HOOK_SENDERS,
+            TRUSTED_PORTS, TRUSTED_PROXIES,
+        },
+        transport::{ibc_hooks_sender, AbstractIbc},
+        wire::SUPPORTED_WIRE_VERSIONS,
+        ServerAdapter,
+    },
+    IBCMAIL_NAMESPACE,
+};
+
+use crate::{
+    contract::{Adapter, ServerResult},
+    error::ServerError,
+};
+
+/// Ask the server on `chain` which wire versions it supports.
+/// The version to send in is stored from the IBC callback.
+pub(crate) fn sync_wire_version(deps: Deps, app: Adapter, chain: TruncatedChainId) -> ServerResult {
+    ensure_admin(deps, &app)?;
+
+    let query = ModuleQuery {
+        target_module: InstalledModuleIdentification {
+            module_info: ModuleInfo::from_id(app.module_id(), app.version().into())?,
+            account_id: None,
+        },
+        msg: to_json_binary(&adapter::QueryMsg::Module(ServerQueryMsg::WireVersions {}))?,
+    };
+    let ibc_client_msg = ibc_client::ExecuteMsg::IbcQuery {
+        host_chain: chain.clone(),
+        queries: vec![QueryRequest::Custom(query)],
+        callback: Callback::new(&ServerCallbackMsg::WireVersions { chain })?,
+    };
+
+    let ibc_client_addr = AbstractIbc { app: &app }.ibc_client_addr(deps)?;
+
+    Ok(app.response("sync_wire_version").add_message(wasm_execute(
+        ibc_client_addr,
+        &ibc_client_msg,
+        vec![],
+    )?))
+}
+
+pub(crate) fn set_peer_wire_version(
+    deps: DepsMut,
+    app: Adapter,
+    chain: TruncatedChainId,
+    version: Option<u32>,
+) -> ServerResult {
+    ensure_admin(deps.as_ref(), &app)?;
+
+    match version {
+        Some(version) => {
+            ensure!(
+                SUPPORTED_WIRE_VERSIONS.contains(&version),
+                ServerError::UnsupportedWireVersion(version)
+            );
+            PEER_WIRE_VERSIONS.save(deps.storage, chain.as_str(), &version)?;
+        }
+        None => PEER_WIRE_VERSIONS.remove(deps.storage, chain.as_str()),
+    }
+
+    Ok(app.response("set_peer_wire_version"))
+}
+
+pub(crate) fn set_rate_limit(
+    deps: DepsMut,
+    app: Adapter,
+    chain: Option<TruncatedChainId>,
+    limit: Option<RateLimit>,
+) -> ServerResult {
+    ensure_admin(deps.as_ref(), &app)?;
+
+    match (chain, limit) {
+        (Some(chain), Some(limit)) => {
+            CHAIN_RATE_LIMITS.save(deps.storage, chain.as_str(), &limit)?
+        }
+        (Some(chain), None) => CHAIN_RATE_LIMITS.remove(deps.storage, chain.as_str()),
+        (None, limit) => {
+            CONFIG.update(deps.storage, |mut config| -> StdResult<_> {
+                config.rate_limit = limit;
+                Ok(config)
+            })?;
+        }
+    }
+
+    Ok(app.response("set_rate_limit"))
+}
+
+pub(crate) fn set_relay_fee(deps: DepsMut, app: Adapter, fee: Option<RelayFee>) -> ServerResult {
+    ensure_admin(deps.as_ref(), &app)?;
+
+    if let Some(fee) = &fee {
+        deps.api.addr_validate(fee.collector.as_str())?;
+    }
+    CONFIG.update(deps.storage, |mut config| -> StdResult<_> {
+        config.relay_fee = fee;
+        Ok(config)
+    })?;
+
+    Ok(app.response("set_relay_fee"))
+}
+
+pub(crate) fn set_pause(deps: DepsMut, app: Adapter, mode: Option<PauseMode>) -> ServerResult {
+    ensure_admin(deps.as_ref(), &app)?;
+
+    match mode {
+        Some(mode) => PAUSED.save(deps.storage, &mode)?,
+        None => PAUSED.remove(deps.storage),
+    }
+
+    Ok(app.response("set_pause"))
+}
+
+pub(crate) fn set_chain_breaker(
+    deps: DepsMut,
+    app: Adapter,
+    chain: TruncatedChainId,
+    mode: Option<PauseMode>,
+) -> ServerResult {
+    ensure_admin(deps.as_ref(), &app)?;
+
+    match mode {
+        Some(mode) => CHAIN_BREAKERS.save(deps.storage, chain.as_str(), &mode)?,
+        None => CHAIN_BREAKERS.remove(deps.storage, chain.as_str()),
+    }
+
+    Ok(app.response("set_chain_breaker"))
+}
+
+pub(crate) fn set_remote_relay_fee(
+    deps: DepsMut,
+    app: Adapter,
+    chain: TruncatedChainId,
+    fee: Option<RemoteRelayFee>,
+) -> ServerResult {
+    ensure_admin(deps.as_ref(), &app)?;
+
+    match fee {
+        Some(fee) => REMOTE_RELAY_FEES.save(deps.storage, chain.as_str(), &fee)?,
+        None => REMOTE_RELAY_FEES.remove(deps.storage, chain.as_str()),
+    }
+
+    Ok(app.response("set_remote_relay_fee"))
+}
+
+pub(crate) fn update_config(
+    deps: DepsMut,
+    app: Adapter,
+    accepted_clients: Option<Vec<String>>,
+    held_mail_expiry: Option<u64>,
+    max_hops: Option<u32>,
+    message_ttl: Option<u64>,
+    remote_preflight: Option<bool>,
+) -> ServerResult {
+    ensure_admin(deps.as_ref(), &app)?;
+
+    let mut config = CONFIG.load(deps.storage)?;
+    if let Some(accepted_clients) = accepted_clients {
+        for module_id in &accepted_clients {
+            ModuleInfo::from_id_latest(module_id)?;
+        }
+        config.accepted_clients = accepted_clients;
+    }
+    if let Some(held_mail_expiry) = held_mail_expiry {
+        config.held_mail_expiry = held_mail_expiry;
+    }
+    if let Some(max_hops) = max_hops {
+        config.max_hops = max_hops;
+    }
+    if let Some(message_ttl) = message_ttl {
+        config.message_ttl = message_ttl;
+    }
+    if let Some(remote_preflight) = remote_preflight {
+        config.remote_preflight = remote_preflight;
+    }
+    CONFIG.save(deps.storage, &config)?;
+
+    Ok(app.response("update_config"))
+}
+
+pub(crate) fn update_route_table(
+    deps: DepsMut,
+    app: Adapter,
+    to_add: Vec<(TruncatedChainId, Vec<TruncatedChainId>)>,
+    to_remove: Vec<TruncatedChainId>,
+) -> ServerResult {
+    ensure_admin(deps.as_ref(), &app)?;
+
+    for chain in to_remove {
+        ROUTE_TABLE.remove(deps.storage, chain.as_str());
+    }
+    for (chain, remote_hosts) in to_add {
+        ROUTE_TABLE.save(deps.storage, chain.as_str(), &remote_hosts)?;
+    }
+
+    Ok(app.response("update_route_table"))
+}
+
+pub(crate) fn set_preferred_hop(
+    deps: DepsMut,
+    app: Adapter,
+    destination: TruncatedChainId,
+    next_hop: Option<TruncatedChainId>,
+) -> ServerResult {
+    ensure_admin(deps.as_ref(), &app)?;
+
+    match next_hop {
+        Some(next_hop) => PREFERRED_HOPS.save(deps.storage, destination.as_str(), &next_hop)?,
+        None => PREFERRED_HOPS.remove(deps.storage, destination.as_str()),
+    }
+
+    Ok(app.response("set_preferred_hop"))
+}
+
+pub(crate) fn set_link_cost(
+    deps: DepsMut,
+    app: Adapter,
+    from: TruncatedChainId,
+    to: TruncatedChainId,
+    cost: Option<u64>,
+) -> ServerResult {
+    ensure_admin(deps.as_ref(), &app)?;
+
+    let link = (from.as_str(), to.as_str());
+    match cost {
+        Some(cost) => LINK_COSTS.save(deps.storage, link, &cost)?,
+        None => LINK_COSTS.remove(deps.storage, link),
+    }
+
+    Ok(app.response("set_link_cost"))
+}
+
+pub(crate) fn set_link_blocked(
+    deps: DepsMut,
+    app: Adapter,
+    from: TruncatedChainId,
+    to: TruncatedChainId,
+    blocked: bool,
+) -> ServerResult {
+    ensure_admin(deps.as_ref(), &app)?;
+
+    let link = (from.as_str(), to.as_str());
+    if blocked {
+        BLOCKED_LINKS.save(deps.storage, link, &Empty {})?;
+    } else {
+        BLOCKED_LINKS.remove(deps.storage, link);
+    }
+
+    Ok(app.response("set_link_blocked"))
+}
+
+pub(crate) fn set_polytone_route(
+    deps: DepsMut,
+    app: Adapter,
+    chain: TruncatedChainId,
+    route: Option<PolytoneRoute>,
+) -> ServerResult {
+    ensure_admin(deps.as_ref(), &app)?;
+
+    match route {
+        Some(route) => {
+            deps.api.addr_validate(route.note.as_str())?;
+            POLYTONE_ROUTES.save(deps.storage, chain.as_str(), &route)?;
+        }
+        None => POLYTONE_ROUTES.remove(deps.storage, chain.as_str()),
+    }
+
+    Ok(app.response("set_polytone_route"))
+}
+
+pub(crate) fn set_ibc_channel(
+    deps: DepsMut,
+    env: Env,
+    app: Adapter,
+    chain: TruncatedChainId,
+    channel_id: Option<String>,
+) -> ServerResult {
+    ensure_admin(deps.as_ref(), &app)?;
+
+    match channel_id {
+        Some(channel_id) => {
+            ensure!(
+                OPEN_CHANNELS.may_load(deps.storage, &channel_id)?.as_ref() == Some(&chain),
+                ServerError::ChannelNotOpen {
+                    channel: channel_id,
+                    chain,
+                }
+            );
+            // Received packets are handled by the server executing itself for the account
+            let relay_account = app.account(deps.as_ref())?.into_addr();
+            let authorized = app
+                .authorized_addresses
+                .may_load(deps.storage, relay_account.clone())?
+                .unwrap_or_default();
+            ensure!(
+                authorized.contains(&env.contract.address),
+                ServerError::ServerNotAuthorized(relay_account)
+            );
+            IBC_CHANNELS.save(deps.storage, chain.as_str(), &channel_id)?;
+            IBC_RELAY_ACCOUNT.save(deps.storage, &relay_account)?;
+        }
+        None => IBC_CHANNELS.remove(deps.storage, chain.as_str()),
+    }
+
+    Ok(app.response("set_ibc_channel"))
+}
+
+pub(crate) fn set_trusted_port(
+    deps: DepsMut,
+    app: Adapter,
+    port_id: String,
+    chain: Option<TruncatedChainId>,
+) -> ServerResult {
+    ensure_admin(deps.as_ref(), &app)?;
+
+    match chain {
+        Some(chain) => TRUSTED_PORTS.save(deps.storage, &port_id, &chain)?,
+        None => TRUSTED_PORTS.remove(deps.storage, &port_id),
+    }
+
+    Ok(app.response("set_trusted_port"))
+}
+
+pub(crate) fn set_trusted_proxy(
+    deps: DepsMut,
+    app: Adapter,
+    proxy: String,
+    chain: Option<TruncatedChainId>,
+) -> ServerResult {
+    ensure_admin(deps.as_ref(), &app)?;
+
+    let proxy = deps.api.addr_validate(&proxy)?;
+    match chain {
+        Some(chain) => TRUSTED_PROXIES.save(deps.storage, &proxy, &chain)?,
+        None => TRUSTED_PROXIES.remove(deps.storage, &proxy),
+    }
+
+    Ok(app.response("set_trusted_proxy"))
+}
+
+pub(crate) fn set_ics20_route(
+    deps: DepsMut,
+    app: Adapter,
+    chain: TruncatedChainId,
+    route: Option<Ics20Route>,
+) -> ServerResult {
+    ensure_admin(deps.as_ref(), &app)?;
+
+    match route {
+        Some(route) => ICS20_ROUTES.save(deps.storage, chain.as_str(), &route)?,
+        None => ICS20_ROUTES.remove(deps.storage, chain.as_str()),
+    }
+
+    Ok(app.response("set_ics20_route"))
+}
+
+pub(crate) fn set_trusted_hook_sender(
+    deps: DepsMut,
+    app: Adapter,
+    channel: String,
+    remote_server: String,
+    chain: Option<TruncatedChainId>,
+) -> ServerResult {
+    ensure_admin(deps.as_ref(), &app)?;
+
+    let sender = ibc_hooks_sender(deps.api, &channel, &remote_server)?;
+    match chain {
+        Some(chain) => TRUSTED_HOOK_SENDERS.save(deps.storage, &sender, &chain)?,
+        None => TRUSTED_HOOK_SENDERS.remove(deps.storage, &sender),
+    }
+
+    Ok(app
+        .response("set_trusted_hook_sender")
+        .add_attribute("hook_sender", sender))
+}
+
+/// Ensure that the target account owns the ibcmail namespace, which administers the server.
+pub(crate) fn ensure_admin(deps: Deps, app: &ServerAdapter) -> ServerResult<()> {
+    let namespace = Namespace::new(IBCMAIL_NAMESPACE)?;
+    let admin = match app.module_registry(deps)?.query_namespace(namespace)? {
+        NamespaceResponse::Claimed(info) => info.account_id,
+        NamespaceResponse::Unclaimed {} => return Err(ServerError::Unauthorized {}),
+    };
+    ensure_eq!(app.account_id(deps)?, admin, ServerError::Unauthorized {});
+
+    Ok(())
+}
@@ -1,7 +1,9 @@
 use cosmwasm_std::Response;
 pub use ibcmail::server::ServerAdapter as Adapter;
-use ibcmail::{server::error::ServerError, IBCMAIL_SERVER_ID};
-use ibcmail::{server::msg::ServerInstantiateMsg, IBCMAIL_CLIENT_ID};
+use ibcmail::{
+    server::{error::ServerError, transport::ICS20_TRANSFER_REPLY_ID},
+    IBCMAIL_CLIENT_ID, IBCMAIL_SERVER_ID,
+};
 
 use crate::{handlers, APP_VERSION};
 
@@ -12,14 +14,119 @@ pub const MAIL_CLIENT: StaticDependency = StaticDependency::new(IBCMAIL_CLIENT_I
 /// The type of the result returned by your client's entry points.
 pub type ServerResult<T = Response> = Result<T, ServerError>;
 
-const ADAPTER: Adapter = Adapter::new(IBCMAIL_SERVER_ID, APP_VERSION, None)
+/// Reply id of message deliveries to mail clients.
+pub const DELIVERY_REPLY_ID: u64 = 1;
+
+/// Reply id of packets received on the server's own IBC port.
+pub const IBC_RECEIVE_REPLY_ID: u64 = 2;
+
+pub(crate) const ADAPTER: Adapter = Adapter::new(IBCMAIL_SERVER_ID, APP_VERSION, None)
+    .with_instantiate(handlers::instantiate_handler)
     .with_execute(handlers::execute_handler)
+    .with_query(handlers::query_handler)
     .with_module_ibc(handlers::module_ibc_handler)
+    .with_ibc_callback(handlers::ibc_callback_handler)
+    .with_sudo(handlers::sudo_handler)
+    .with_replies(&[
+        (DELIVERY_REPLY_ID, handlers::delivery_reply_handler),
+        (IBC_RECEIVE_REPLY_ID, handlers::ibc_receive_reply_handler),
+        (
+            ICS20_TRANSFER_REPLY_ID,
+            handlers::ics20_transfer_reply_handler,
+        ),
+    ])
     .with_dependencies(&[]);
 
 // Export handlers
 #[cfg(feature = "export")]
 abstract_adapter::export_endpoints!(ADAPTER, Adapter);
 
+/// Interface of the server, written out instead of generated by `cw_orch_interface!`
+/// so its mock also has the entry points of the server's own IBC port.
 #[cfg(feature = "interface")]
-abstract_adapter::cw_orch_interface!(ADAPTER, Adapter, ServerInstantiateMsg, ServerInterface);
+pub mod interface {
+    use abstract_adapter::{
+        abstract_interface::{
+            AbstractInterfaceError, AdapterDeployer, DependencyCreation, RegisteredModule,
+        },
+        objects::dependency::StaticDependency,
+        std::{
+            account::ModuleInstallConfig,
+            adapter::{ExecuteMsg, InstantiateMsg, QueryMsg},
+        },
+    };
+    use cosmwasm_std::Empty;
+    use cw_orch::{contract::Contract, prelude::*};
+    use ibcmail::{
+        server::msg::{ServerExecuteMsg, ServerInstantiateMsg, ServerQueryMsg},
+        IBCMAIL_SERVER_ID,
+    };
+
+    use crate::{ibc, APP_VERSION};
+
+    #[cw_orch::interface(
+        InstantiateMsg<ServerInstantiateMsg>,
+        ExecuteMsg<ServerExecuteMsg>,
+        QueryMsg<ServerQueryMsg>,
+        Empty
+    )]
+    pub struct ServerInterface;
+
+    impl<Chain: CwEnv> AdapterDeployer<Chain, ServerInstantiateMsg> for ServerInterface<Chain> {}
+
+    impl<Chain: CwEnv> Uploadable for ServerInterface<Chain> {
+        fn wasm(_chain: &ChainInfoOwned) -> WasmPath {
+            ArtifactsDir::auto(Some(env!("CARGO_MANIFEST_DIR").to_string()))
+                .find_wasm_path("ibcmail_server")
+                .unwrap()
+        }
+
+        fn wrapper() -> Box<dyn MockContract<Empty>> {
+            Box::new(
+                ContractWrapper::new_with_empty(super::execute, super::instantiate, super::query)
+                    .with_reply(super::reply)
+                    .with_sudo(super::sudo)
+                    .with_ibc(
+                        ibc::ibc_channel_open,
+                        ibc::ibc_channel_connect,
+                        ibc::ibc_channel_close,
+                        ibc::ibc_packet_receive,
+                        ibc::ibc_packet_ack,
+                        ibc::ibc_packet_timeout,
+                    ),
+            )
+        }
+    }
+
+    impl<Chain: CwEnv> RegisteredModule for ServerInterface<Chain> {
+        type InitMsg = ServerInstantiateMsg;
+
+        fn module_id<'a>() -> &'a str {
+            IBCMAIL_SERVER_ID
+        }
+
+        fn module_version<'a>() -> &'a str {
+            APP_VERSION
+        }
+
+        fn dependencies<'a>() -> &'a [StaticDependency] {
+            &[]
+        }
+    }
+
+    impl<Chain: CwEnv> From<Contract<Chain>> for ServerInterface<Chain> {
+        fn from(contract: Contract<Chain>) -> Self {
+            Self(contract)
+        }
+    }
+
+    impl<Chain: CwEnv> DependencyCreation for ServerInterface<Chain> {
+        type DependenciesConfig = Empty;
+
+        fn dependency_install_configs(
+            _configuration: Self::DependenciesConfig,
+        ) -> Result<Vec<ModuleInstallConfig>, AbstractInterfaceError> {
+            Ok(vec![])
+        }
+    }
+}
@@ -0,0 +1,108 @@
+use abstract_adapter::sdk::{features::AccountIdentification, AccountVerification};
+use abstract_adapter::traits::AbstractResponse;
+use cosmwasm_std::{ensure, Addr, DepsMut, Env};
+use ibcmail::{
+    is_account_address,
+    server::state::{DeadLetter, DeliveryStatus, DEAD_LETTERS},
+    MessageHash,
+};
+
+use crate::{
+    admin::ensure_admin,
+    contract::{Adapter, ServerResult},
+    error::ServerError,
+    handlers::execute::{bounce_msg, deliver_msg, log_delivery, receiver_module},
+};
+
+/// Remove a dead letter of the account at `address`.
+/// Only that account and the admin may handle its dead letters.
+pub(crate) fn take_dead_letter(
+    deps: DepsMut,
+    app: &Adapter,
+    address: &Addr,
+    id: &str,
+) -> ServerResult<DeadLetter> {
+    if ensure_admin(deps.as_ref(), app).is_err() {
+        let account = app.account(deps.as_ref())?;
+        ensure!(
+            is_account_address(&deps.querier, account.addr(), address)?,
+            ServerError::CannotClaim(address.to_string())
+        );
+    }
+
+    let dead_letter = DEAD_LETTERS.load(deps.storage, (address, id))?;
+    DEAD_LETTERS.remove(deps.storage, (address, id));
+
+    Ok(dead_letter)
+}
+
+pub(crate) fn retry_dead_letter(
+    mut deps: DepsMut,
+    mut app: Adapter,
+    address: String,
+    id: MessageHash,
+) -> ServerResult {
+    let address = deps.api.addr_validate(&address)?;
+    let dead_letter = take_dead_letter(deps.branch(), &app, &address, &id)?;
+
+    let (account_id, account) = {
+        let registry = app.account_registry(deps.as_ref())?;
+        let account_id = registry.account_id(&address)?;
+        let account = registry.account(&account_id)?;
+        (account_id, account)
+    };
+    app.target_account = Some(account.clone());
+
+    let recipient = dead_letter.msg.message.recipient.clone();
+    let receiver_id = receiver_module(deps.as_ref(), &account, &recipient)?
+        .ok_or(ServerError::NoMailClient(account_id))?;
+    let msg = deliver_msg(
+        deps.as_ref(),
+        dead_letter.msg,
+        dead_letter.header,
+        &app,
+        &receiver_id,
+    )?;
+
+    Ok(app
+        .response("retry_dead_letter")
+        .add_attribute("message_id", id)
+        .add_submessage(msg))
+}
+
+pub(crate) fn discard_dead_letter(
+    mut deps: DepsMut,
+    env: Env,
+    mut app: Adapter,
+    address: String,
+    id: MessageHash,
+    bounce: bool,
+) -> ServerResult {
+    let address = deps.api.addr_validate(&address)?;
+    let dead_letter = take_dead_letter(deps.branch(), &app, &address, &id)?;
+
+    let msg = if bounce {
+        let reason = format!("delivery failed: {}", dead_letter.error);
+        bounce_msg(
+            deps.branch(),
+            &env,
+            &mut app,
+            dead_letter.msg,
+            dead_letter.header,
+            &reason,
+        )?
+    } else {
+        log_delivery(
+            deps.storage,
+            &env,
+            &dead_letter.msg,
+            DeliveryStatus::Discarded {},
+        )?;
+        None
+    };
+
+    Ok(app
+        .response("discard_dead_letter")
+        .add_attribute("message_id", id)
+        .add_submessages(msg))
+}
@@ -1,31 +1,64 @@
 use abstract_adapter::objects::TruncatedChainId;
 use abstract_adapter::sdk::{
-    features::ModuleIdentification, AccountVerification, ModuleRegistryInterface,
+    features::{AccountIdentification, ModuleIdentification},
+    AccountVerification, ModuleRegistryInterface,
 };
 use abstract_adapter::std::registry::Account;
 use abstract_adapter::std::{
-    ibc_client,
-    objects::{account::AccountTrace, module::ModuleInfo},
+    account::state::ACCOUNT_MODULES,
+    adapter,
+    ibc::{Callback, ModuleQuery},
+    ibc_client::{self, InstalledModuleIdentification},
+    objects::{account::AccountTrace, module::ModuleInfo, AccountId},
     registry::NamespaceResponse,
-    IBC_CLIENT,
 };
 use abstract_adapter::traits::AbstractResponse;
 use cosmwasm_std::{
-    to_json_binary, wasm_execute, Addr, CosmosMsg, Deps, DepsMut, Env, MessageInfo,
+    ensure, to_json_binary, wasm_execute, Addr, BankMsg, Coin, CosmosMsg, Deps, DepsMut, Env,
+    MessageInfo, QueryRequest, Storage, SubMsg,
 };
-use ibcmail::client::api::MailClient;
+use ibcmail::receiver::{MailReceiver, MailReceiverInterface};
 use ibcmail::{
-    client::api::ClientInterface,
     server::{
-        msg::{ServerExecuteMsg, ServerIbcMessage},
+        msg::{
+            MessageResult, ProcessMessagesResponse, ServerCallbackMsg, ServerExecuteMsg,
+            ServerIbcMessage, ServerQueryMsg,
+        },
+        state::{
+            delivery_log, sender_key, DeliveryRecord, DeliveryStatus, RemoteRelayFee, CONFIG,
+            DELIVERY_LOG_NEXT_ID, DELIVERY_LOG_SIZE,
+        },
+        transport::AbstractIbc,
+        wire::LEGACY_WIRE_VERSION,
         ServerAdapter,
     },
-    Header, IbcMailMessage, Recipient, Route,
+    Header, HopTrace, IbcMailMessage, Message, MessageHash, Recipient, Route, Sender,
+    EMAIL_VERSION,
 };
 
 use crate::{
-    contract::{Adapter, ServerResult},
+    admin::{
+        set_chain_breaker, set_ibc_channel, set_ics20_route, set_link_blocked, set_link_cost,
+        set_pause, set_peer_wire_version, set_polytone_route, set_preferred_hop, set_rate_limit,
+        set_relay_fee, set_remote_relay_fee, set_trusted_hook_sender, set_trusted_port,
+        set_trusted_proxy, sync_wire_version, update_config, update_route_table,
+    },
+    contract::{Adapter, ServerResult, DELIVERY_REPLY_ID},
+    dead_letter::{discard_dead_letter, retry_dead_letter},
     error::ServerError,
+    held_mail::{bounce_expired_mail, claim_mail, hold_msg},
+    pause::ensure_not_paused,
+    rate_limit::{check_rate_limit, ensure_within_rate_limit, release_rate_limit},
+    receive::{handle_ibc_packet, receive_ics20, receive_polytone},
+    relay_fee::{
+        ensure_relay_fees_paid, excess_funds, prepaid_relay_fees, relay_fee_funds,
+        relay_fee_payment, route_relay_fees, total_relay_fees,
+    },
+    routing::{
+        find_route, is_blocked, peer_wire_version, remote_hosts, send_to_server, transfer_funds,
+        transport_to,
+    },
+    stats::{count_message, Counter},
 };
 
 // ANCHOR: execute_handler
@@ -40,6 +73,85 @@ pub fn execute_handler(
         ServerExecuteMsg::ProcessMessage { msg, route } => {
             process_message(deps, env, info, msg, route, app)
         }
+        ServerExecuteMsg::ProcessMessages { msgs } => process_messages(deps, env, info, msgs, app),
+        ServerExecuteMsg::ClaimMail { address } => claim_mail(deps, env, app, address),
+        ServerExecuteMsg::BounceExpiredMail { address } => {
+            bounce_expired_mail(deps, env, app, address)
+        }
+        ServerExecuteMsg::SetPolytoneRoute { chain, route } => {
+            set_polytone_route(deps, app, chain, route)
+        }
+        ServerExecuteMsg::SetTrustedPort { port_id, chain } => {
+            set_trusted_port(deps, app, port_id, chain)
+        }
+        ServerExecuteMsg::SetIbcChannel { chain, channel_id } => {
+            set_ibc_channel(deps, env, app, chain, channel_id)
+        }
+        ServerExecuteMsg::SetTrustedProxy { proxy, chain } => {
+            set_trusted_proxy(deps, app, proxy, chain)
+        }
+        ServerExecuteMsg::SetIcs20Route { chain, route } => {
+            set_ics20_route(deps, app, chain, route)
+        }
+        ServerExecuteMsg::SetTrustedHookSender {
+            channel,
+            remote_server,
+            chain,
+        } => set_trusted_hook_sender(deps, app, channel, remote_server, chain),
+        ServerExecuteMsg::ReceivePolytone { msg } => receive_polytone(deps, env, info, app, msg),
+        ServerExecuteMsg::ReceiveIcs20 { msg } => receive_ics20(deps, env, info, app, msg),
+        ServerExecuteMsg::HandleIbcPacket { channel_id, msg } => {
+            handle_ibc_packet(deps, env, info, app, channel_id, msg)
+        }
+        ServerExecuteMsg::RetryDeadLetter { address, id } => {
+            retry_dead_letter(deps, app, address, id)
+        }
+        ServerExecuteMsg::DiscardDeadLetter {
+            address,
+            id,
+            bounce,
+        } => discard_dead_letter(deps, env, app, address, id, bounce),
+        ServerExecuteMsg::SyncWireVersion { chain } => sync_wire_version(deps.as_ref(), app, chain),
+        ServerExecuteMsg::SetPeerWireVersion { chain, version } => {
+            set_peer_wire_version(deps, app, chain, version)
+        }
+        ServerExecuteMsg::UpdateRouteTable { to_add, to_remove } => {
+            update_route_table(deps, app, to_add, to_remove)
+        }
+        ServerExecuteMsg::SetPreferredHop {
+            destination,
+            next_hop,
+        } => set_preferred_hop(deps, app, destination, next_hop),
+        ServerExecuteMsg::SetLinkCost { from, to, cost } => {
+            set_link_cost(deps, app, from, to, cost)
+        }
+        ServerExecuteMsg::SetLinkBlocked { from, to, blocked } => {
+            set_link_blocked(deps, app, from, to, blocked)
+        }
+        ServerExecuteMsg::SetRateLimit { chain, limit } => set_rate_limit(deps, app, chain, limit),
+        ServerExecuteMsg::SetRelayFee { fee } => set_relay_fee(deps, app, fee),
+        ServerExecuteMsg::SetPause { mode } => set_pause(deps, app, mode),
+        ServerExecuteMsg::SetChainBreaker { chain, mode } => {
+            set_chain_breaker(deps, app, chain, mode)
+        }
+        ServerExecuteMsg::SetRemoteRelayFee { chain, fee } => {
+            set_remote_relay_fee(deps, app, chain, fee)
+        }
+        ServerExecuteMsg::UpdateConfig {
+            accepted_clients,
+            held_mail_expiry,
+            max_hops,
+            message_ttl,
+            remote_preflight,
+        } => update_config(
+            deps,
+            app,
+            accepted_clients,
+            held_mail_expiry,
+            max_hops,
+            message_ttl,
+            remote_preflight,
+        ),
     }
 }
 // ANCHOR_END: execute_handler
@@ -47,17 +159,290 @@ pub fn execute_handler(
 fn process_message(
     deps: DepsMut,
     env: Env,
-    _info: MessageInfo,
-    msg: IbcMailMessage,
+    info: MessageInfo,
+    mut msg: IbcMailMessage,
     route: Option<Route>,
     mut app: Adapter,
 ) -> ServerResult {
     println!("processing message: {:?} with route {:?}", msg, route);
 
     let current_chain = TruncatedChainId::new(&env);
+    msg.sender = msg.sender.with_chain(&current_chain);
+    ensure_valid_sender(deps.as_ref(), &env, &info, &app, &msg.sender)?;
+
+    let route = resolve_route(deps.as_ref(), &env, &app, &msg.message.recipient, route)?;
+    let mut metadata = new_header(deps.as_ref(), &env, route)?;
+    ensure_sendable(deps.as_ref(), &env, &msg, &metadata)?;
+    check_rate_limit(deps.storage, &env, &current_chain, &msg.sender)?;
+
+    // The sender pays the relay fees and the tokens of an ICS-20 transfer to the next hop
+    let relay_fees = route_relay_fees(deps.as_ref(), &metadata.route)?;
+    let transfer = transfer_funds(deps.as_ref(), &app, &metadata)?;
+    let payments: Vec<Coin> = relay_fees
+        .iter()
+        .map(|(_, fee)| relay_fee_payment(fee))
+        .collect();
+    let required = total_relay_fees(
+        payments
+            .iter()
+            .chain(transfer.iter().map(|(_, funds)| funds)),
+    )?;
+    ensure_relay_fees_paid(&info.funds, &required)?;
+    metadata.relay_fees = prepaid_relay_fees(&relay_fees);
+
+    // Funds in excess of the fees are returned like those of a batch
+    let refund = excess_funds(&info.funds, &required);
+    let data = to_json_binary(&ProcessMessagesResponse {
+        results: vec![MessageResult {
+            id: msg.id.clone(),
+            error: None,
+        }],
+        refund: refund.clone(),
+    })?;
+    let refund = refund_msg(&info.sender, refund);
+
+    // Only directly connected servers reached over Abstract IBC can be queried before sending
+    if CONFIG.load(deps.storage)?.remote_preflight && metadata.hop_count() == 1 {
+        let dest_chain = next_hop(deps.as_ref(), &env, &app, &metadata)?;
+        if transport_to(deps.as_ref(), &app, &dest_chain).can_query() {
+            let msg = preflight_msg(deps.as_ref(), &app, dest_chain, msg, metadata)?;
+            return Ok(app
+                .response("preflight")
+                .add_message(msg)
+                .add_messages(refund)
+                .set_data(data));
+        }
+    }
+
+    let msg = route_msg(deps, &env, msg, metadata, &mut app)?;
+
+    Ok(app
+        .response("route")
+        .add_submessages(msg)
+        .add_messages(refund)
+        .set_data(data))
+}
+
+fn process_messages(
+    mut deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    msgs: Vec<(IbcMailMessage, Option<Route>)>,
+    mut app: Adapter,
+) -> ServerResult {
+    let mut results = Vec::with_capacity(msgs.len());
+    let mut routable = vec![];
+    let mut costs = vec![];
+    let current_chain = TruncatedChainId::new(&env);
+    for (mut msg, route) in msgs {
+        msg.sender = msg.sender.with_chain(&current_chain);
+        let checked = ensure_valid_sender(deps.as_ref(), &env, &info, &app, &msg.sender)
+            .and_then(|_| resolve_route(deps.as_ref(), &env, &app, &msg.message.recipient, route))
+            .and_then(|route| new_header(deps.as_ref(), &env, route))
+            .and_then(|header| ensure_sendable(deps.as_ref(), &env, &msg, &header).map(|_| header))
+            .and_then(|header| {
+                let fees = route_relay_fees(deps.as_ref(), &header.route)?;
+                let transfer = transfer_funds(deps.as_ref(), &app, &header)?;
+                Ok((header, fees, transfer))
+            })
+            .and_then(|checked| {
+                check_rate_limit(deps.storage, &env, &current_chain, &msg.sender)?;
+                Ok(checked)
+            });
+        match checked {
+            Ok((header, fees, transfer)) => {
+                let header = Header {
+                    relay_fees: prepaid_relay_fees(&fees),
+                    ..header
+                };
+                costs.push(MessageCosts {
+                    id: msg.id.clone(),
+                    fees,
+                    transfer,
+                });
+                routable.push((msg, header));
+            }
+            Err(error) => {
+                log_delivery(
+                    deps.storage,
+                    &env,
+                    &msg,
+                    DeliveryStatus::Failed {
+                        reason: error.to_string(),
+                    },
+                )?;
+                results.push(MessageResult {
+                    id: msg.id,
+                    error: Some(error.to_string()),
+                });
+            }
+        }
+    }
+
+    // The relay fees and ICS-20 transfers of the whole batch are paid at once
+    let required = batch_costs(deps.as_ref(), &costs)?;
+    ensure_relay_fees_paid(&info.funds, &required)?;
+
+    let routed_ids: Vec<MessageHash> = routable.iter().map(|(msg, _)| msg.id.clone()).collect();
+    let (msgs, failures) = route_msgs(deps.branch(), &env, routable, &mut app)?;
+    for (msg, _, _) in &failures {
+        release_rate_limit(deps.storage, &current_chain, &msg.sender)?;
+    }
+    // Only the messages that are routed pay the relay fees and transfers of their route,
+    // the funds of the others are returned
+    costs.retain(|costs| !failures.iter().any(|(failed, _, _)| failed.id == costs.id));
+    let spent = batch_costs(deps.as_ref(), &costs)?;
+    let refund = excess_funds(&info.funds, &spent);
+    for id in routed_ids {
+        let error = failures
+            .iter()
+            .find(|(failed, _, _)| failed.id == id)
+            .map(|(_, _, reason)| reason.clone());
+        results.push(MessageResult { id, error });
+    }
+
+    let failed: Vec<_> = results
+        .iter()
+        .filter_map(|result| {
+            let error = result.error.as_ref()?;
+            Some(("failed", format!("{}: {error}", result.id)))
+        })
+        .collect();
+
+    let response = app
+        .response("route_batch")
+        .add_attribute("sent", (results.len() - failed.len()).to_string())
+        .add_attributes(failed)
+        .add_submessages(msgs)
+        .add_messages(refund_msg(&info.sender, refund.clone()));
+
+    Ok(response.set_data(to_json_binary(&ProcessMessagesResponse {
+        results,
+        refund,
+    })?))
+}
+
+/// Message that returns the funds that `sender` attached in excess of the fees.
+fn refund_msg(sender: &Addr, refund: Vec<Coin>) -> Option<BankMsg> {
+    (!refund.is_empty()).then(|| BankMsg::Send {
+        to_address: sender.to_string(),
+        amount: refund,
+    })
+}
+
+/// Relay fees and ICS-20 transfer of a message in a batch.
+struct MessageCosts {
+    id: MessageHash,
+    fees: Vec<(TruncatedChainId, RemoteRelayFee)>,
+    transfer: Option<(TruncatedChainId, Coin)>,
+}
+
+/// Funds that senders attach for the relay fees and ICS-20 transfers of a batch.
+/// Messages for the same next hop share one transfer, unless its server only understands single messages.
+fn batch_costs(deps: Deps, costs: &[MessageCosts]) -> ServerResult<Vec<Coin>> {
+    let mut transfers: Vec<&(TruncatedChainId, Coin)> = vec![];
+    for transfer in costs.iter().filter_map(|costs| costs.transfer.as_ref()) {
+        let shared = transfers.iter().any(|(chain, _)| chain == &transfer.0)
+            && peer_wire_version(deps, &transfer.0)? != LEGACY_WIRE_VERSION;
+        if !shared {
+            transfers.push(transfer);
+        }
+    }
+    let payments: Vec<Coin> = costs
+        .iter()
+        .flat_map(|costs| costs.fees.iter().map(|(_, fee)| relay_fee_payment(fee)))
+        .collect();
+
+    Ok(total_relay_fees(
+        payments
+            .iter()
+            .chain(transfers.iter().map(|(_, funds)| funds)),
+    )?)
+}
+
+/// Ensure that mail sent by the calling account is sent in the name of that account or one of its modules.
+/// Address senders are vouched for by the mail client of the account that they sent through.
+/// Only servers create mail in their own name, like bounces, so it can't be sent by accounts.
+fn ensure_valid_sender(
+    deps: Deps,
+    env: &Env,
+    info: &MessageInfo,
+    app: &ServerAdapter,
+    sender: &Sender,
+) -> ServerResult<()> {
+    let account = app.account(deps)?;
+    let account_id = app.account_id(deps)?;
+    let current_chain = TruncatedChainId::new(env);
+    let is_local = |chain: &Option<TruncatedChainId>| {
+        chain.as_ref().map_or(true, |chain| chain == &current_chain)
+    };
+
+    let valid = match sender {
+        Sender::Account { id, chain } => id == &account_id && is_local(chain),
+        Sender::Module {
+            account: id,
+            module_id,
+            chain,
+        } => {
+            id == &account_id
+                && is_local(chain)
+                && ACCOUNT_MODULES
+                    .query(&deps.querier, account.addr().clone(), module_id)?
+                    .is_some()
+        }
+        Sender::Address { chain, .. } => {
+            let client = match installed_mail_client(deps, &account)? {
+                Some(client_id) => {
+                    ACCOUNT_MODULES.query(&deps.querier, account.addr().clone(), &client_id)?
+                }
+                None => None,
+            };
+            is_local(chain) && client.as_ref() == Some(&info.sender)
+        }
+        Sender::Server { .. } => false,
+    };
+    ensure!(valid, ServerError::InvalidSender(format!("{sender:?}")));
+
+    Ok(())
+}
 
-    let route: Route = if let Some(route) = route {
-        Ok::<_, ServerError>(match route {
+/// Ensure that `msg` is in a message format that this server understands.
+pub(crate) fn ensure_supported_version(msg: &IbcMailMessage) -> ServerResult<()> {
+    ensure!(
+        msg.is_supported_version(),
+        ServerError::UnsupportedMessageVersion(msg.version.clone())
+    );
+
+    Ok(())
+}
+
+/// Ensure that `msg` may be sent from this chain with `header`, the checks a sender can't pass on to other servers.
+/// The rate limit is only checked, the message is counted once it is sent.
+pub(crate) fn ensure_sendable(
+    deps: Deps,
+    env: &Env,
+    msg: &IbcMailMessage,
+    header: &Header,
+) -> ServerResult<()> {
+    ensure_supported_version(msg)?;
+    ensure_within_max_hops(header)?;
+    ensure_not_paused(deps.storage, header)?;
+    ensure_within_rate_limit(deps.storage, env, &TruncatedChainId::new(env), &msg.sender)
+}
+
+/// The full route from the current chain for a message to `recipient`.
+/// Explicit routes are prefixed with the current chain, otherwise a route is looked up for remote recipients.
+pub(crate) fn resolve_route(
+    deps: Deps,
+    env: &Env,
+    app: &ServerAdapter,
+    recipient: &Recipient,
+    route: Option<Route>,
+) -> ServerResult<Route> {
+    let current_chain = TruncatedChainId::new(env);
+
+    if let Some(route) = route {
+        Ok(match route {
             Route::Local => Route::Local,
             Route::Remote(mut chains) => {
                 println!("processing remote route: {:?}", chains);
@@ -75,108 +460,375 @@ fn process_message(
             }
         })
     } else {
-        println!("processing message recipient: {:?}", msg.message.recipient);
-        match msg.message.recipient.clone() {
-            // TODO: add smarter routing
-            Recipient::Account { id: _, chain } => Ok(chain.map_or(AccountTrace::Local, |chain| {
-                if chain == current_chain {
-                    AccountTrace::Local
-                } else {
-                    AccountTrace::Remote(vec![current_chain, chain.clone()])
-                }
-            })),
-            Recipient::Namespace {
-                chain,
-                namespace: _,
-            } => Ok(chain.map_or(AccountTrace::Local, |chain| {
-                if chain == current_chain {
-                    AccountTrace::Local
-                } else {
-                    AccountTrace::Remote(vec![current_chain, chain.clone()])
-                }
-            })),
-            _ => {
-                return Err(ServerError::NotImplemented(
-                    "Non-account recipients not supported".to_string(),
-                ))
-            }
+        println!("processing message recipient: {:?}", recipient);
+        match recipient.chain() {
+            Some(chain) if chain != &current_chain => find_route(deps, app, current_chain, chain),
+            _ => Ok(AccountTrace::Local),
         }
-    }?;
+    }
+}
+
+pub(crate) fn ensure_no_loop(header: &Header) -> ServerResult<()> {
+    if let Some(chain) = header.repeated_chain() {
+        return Err(ServerError::InvalidRoute {
+            route: header.route.clone(),
+            hop: header.current_hop,
+            reason: format!("{chain} is visited more than once"),
+        });
+    }
+
+    Ok(())
+}
+
+pub(crate) fn ensure_within_max_hops(header: &Header) -> ServerResult<()> {
+    if header.exceeds_max_hops() {
+        return Err(ServerError::InvalidRoute {
+            route: header.route.clone(),
+            hop: header.current_hop,
+            reason: format!(
+                "{} hops exceed the limit of {}",
+                header.hop_count(),
+                header.max_hops.unwrap_or_default()
+            ),
+        });
+    }
+
+    Ok(())
+}
+
+/// The next chain on the route, which must be reachable over an unblocked link.
+pub(crate) fn next_hop(
+    deps: Deps,
+    env: &Env,
+    app: &ServerAdapter,
+    header: &Header,
+) -> ServerResult<TruncatedChainId> {
+    let invalid_route = |reason: String| ServerError::InvalidRoute {
+        route: header.route.clone(),
+        hop: header.current_hop,
+        reason,
+    };
+    let AccountTrace::Remote(chains) = &header.route else {
+        return Err(invalid_route("missing next hop".to_string()));
+    };
+    let dest_chain = chains
+        .get(header.current_hop as usize + 1)
+        .ok_or_else(|| invalid_route("missing next hop".to_string()))?;
+
+    // Fail early with the unreachable hop instead of inside the IBC client
+    let current_chain = TruncatedChainId::new(env);
+    if !remote_hosts(deps, app)?.contains(dest_chain) {
+        return Err(invalid_route(format!(
+            "{dest_chain} is not reachable from {current_chain}"
+        )));
+    }
+    if is_blocked(deps, current_chain.as_str(), dest_chain.as_str()) {
+        return Err(invalid_route(format!(
+            "link from {current_chain} to {dest_chain} is blocked"
+        )));
+    }
+
+    Ok(dest_chain.clone())
+}
 
-    let metadata = Header {
-        current_hop: 0,
-        route,
+/// Ask the server on `dest_chain`, the next hop, whether `msg` can be delivered.
+/// The message is sent from the IBC callback once the recipient is confirmed.
+fn preflight_msg(
+    deps: Deps,
+    app: &ServerAdapter,
+    dest_chain: TruncatedChainId,
+    msg: IbcMailMessage,
+    header: Header,
+) -> ServerResult<CosmosMsg> {
+    let dry_run = ServerQueryMsg::DryRun {
+        message: msg.message.clone(),
+        route: None,
+        sender: msg.sender.clone(),
+    };
+    let query = ModuleQuery {
+        target_module: InstalledModuleIdentification {
+            module_info: ModuleInfo::from_id(app.module_id(), app.version().into())?,
+            account_id: None,
+        },
+        msg: to_json_binary(&adapter::QueryMsg::Module(dry_run))?,
+    };
+    let ibc_client_msg = ibc_client::ExecuteMsg::IbcQuery {
+        host_chain: dest_chain,
+        queries: vec![QueryRequest::Custom(query)],
+        callback: Callback::new(&ServerCallbackMsg::Preflight { msg, header })?,
     };
 
-    let msg = route_msg(deps, msg, metadata, &mut app)?;
+    let ibc_client_addr = AbstractIbc { app }.ibc_client_addr(deps)?;
 
-    Ok(app.response("route").add_message(msg))
+    Ok(wasm_execute(ibc_client_addr, &ibc_client_msg, vec![])?.into())
+}
+
+/// Outcome of handling a message on the current hop.
+pub(crate) enum RouteStep {
+    /// The message was delivered, held or bounced on this chain
+    Done(Option<SubMsg>),
+    /// The message continues to the server on the next chain of its route
+    Forward {
+        next_hop: TruncatedChainId,
+        msg: IbcMailMessage,
+        header: Header,
+    },
 }
 
 pub(crate) fn route_msg(
-    deps: DepsMut,
+    mut deps: DepsMut,
+    env: &Env,
     msg: IbcMailMessage,
     header: Header,
     app: &mut ServerAdapter,
-) -> ServerResult<CosmosMsg> {
+) -> ServerResult<Option<SubMsg>> {
+    let (sender, original_header) = (msg.sender.clone(), header.clone());
+    match route_step(deps.branch(), env, msg, header, app)? {
+        RouteStep::Done(msg) => {
+            count_routed(deps.storage, env, &sender, &original_header, false)?;
+            Ok(msg)
+        }
+        RouteStep::Forward {
+            next_hop,
+            msg,
+            header,
+        } => {
+            let funds = relay_fee_funds(deps.as_ref(), &header, &next_hop)?;
+            let server_msg = ServerIbcMessage::RouteMessage { msg, header };
+            let msg = send_to_server(deps.as_ref(), env, app, next_hop, &server_msg, funds)?;
+            count_routed(deps.storage, env, &sender, &original_header, true)?;
+            Ok(Some(msg))
+        }
+    }
+}
+
+/// Count a message that was routed on this hop.
+/// Bounces are counted when the server creates them and deliveries once the receiver accepted them.
+fn count_routed(
+    storage: &mut dyn Storage,
+    env: &Env,
+    sender: &Sender,
+    header: &Header,
+    forwarded: bool,
+) -> ServerResult<()> {
+    if header.current_hop == 0 && !matches!(sender, Sender::Server { .. }) {
+        count_message(storage, env, &header.route, Counter::Originated)?;
+    }
+    if forwarded && header.current_hop > 0 {
+        count_message(storage, env, &header.route, Counter::Forwarded)?;
+    }
+
+    Ok(())
+}
+
+/// Messages that could not be routed, with the reason.
+pub(crate) type RouteFailures = Vec<(IbcMailMessage, Header, String)>;
+
+/// Route a batch of messages, sending the messages for the same next hop in a single IBC message.
+/// Messages that fail to route are returned and don't affect the rest of the batch.
+pub(crate) fn route_msgs(
+    mut deps: DepsMut,
+    env: &Env,
+    msgs: Vec<(IbcMailMessage, Header)>,
+    app: &mut ServerAdapter,
+) -> ServerResult<(Vec<SubMsg>, RouteFailures)> {
+    let mut sub_msgs = vec![];
+    let mut failures = vec![];
+    let mut batches: Vec<(TruncatedChainId, Vec<(IbcMailMessage, Header)>)> = vec![];
+
+    for (msg, header) in msgs {
+        let original = (msg.clone(), header.clone());
+        match route_step(deps.branch(), env, msg, header, app) {
+            Ok(RouteStep::Done(msg)) => {
+                count_routed(deps.storage, env, &original.0.sender, &original.1, false)?;
+                sub_msgs.extend(msg);
+            }
+            Ok(RouteStep::Forward {
+                next_hop,
+                msg,
+                header,
+            }) => {
+                count_routed(deps.storage, env, &original.0.sender, &original.1, true)?;
+                match batches.iter_mut().find(|(chain, _)| chain == &next_hop) {
+                    Some((_, batch)) => batch.push((msg, header)),
+                    None => batches.push((next_hop, vec![(msg, header)])),
+                }
+            }
+            Err(error) => {
+                let reason = error.to_string();
+                log_delivery(
+                    deps.storage,
+                    env,
+                    &original.0,
+                    DeliveryStatus::Failed {
+                        reason: reason.clone(),
+                    },
+                )?;
+                failures.push((original.0, original.1, reason));
+            }
+        }
+    }
+
+    for (next_hop, msgs) in batches {
+        if peer_wire_version(deps.as_ref(), &next_hop)? != LEGACY_WIRE_VERSION {
+            let mut funds = vec![];
+            for (_, header) in &msgs {
+                funds.extend(relay_fee_funds(deps.as_ref(), header, &next_hop)?);
+            }
+            let funds = total_relay_fees(&funds)?;
+            let server_msg = ServerIbcMessage::RouteMessages { msgs };
+            sub_msgs.push(send_to_server(
+                deps.as_ref(),
+                env,
+                app,
+                next_hop,
+                &server_msg,
+                funds,
+            )?);
+            continue;
+        }
+
+        // Servers of the legacy wire version only understand single messages,
+        // the messages they can't represent fail on their own
+        for (msg, header) in msgs {
+            let server_msg = ServerIbcMessage::RouteMessage {
+                msg: msg.clone(),
+                header: header.clone(),
+            };
+            let sent = relay_fee_funds(deps.as_ref(), &header, &next_hop).and_then(|funds| {
+                send_to_server(
+                    deps.as_ref(),
+                    env,
+                    app,
+                    next_hop.clone(),
+                    &server_msg,
+                    funds,
+                )
+            });
+            match sent {
+                Ok(sub_msg) => sub_msgs.push(sub_msg),
+                Err(error) => {
+                    let reason = error.to_string();
+                    log_delivery(
+                        deps.storage,
+                        env,
+                        &msg,
+                        DeliveryStatus::Failed {
+                            reason: reason.clone(),
+                        },
+                    )?;
+                    failures.push((msg, header, reason));
+                }
+            }
+        }
+    }
+
+    Ok((sub_msgs, failures))
+}
+
+/// Handle a message on the current hop: deliver it locally, bounce it or pick the next hop.
+fn route_step(
+    deps: DepsMut,
+    env: &Env,
+    msg: IbcMailMessage,
+    mut header: Header,
+    app: &mut ServerAdapter,
+) -> ServerResult<RouteStep> {
     println!("routing message: {:?}, metadata: {:?}", msg, header);
 
-    match header.route {
-        AccountTrace::Local => route_to_local_account(deps.as_ref(), msg, header, app),
+    header.trace.push(HopTrace {
+        chain: TruncatedChainId::new(env),
+        height: env.block.height,
+        time: env.block.time,
+    });
+
+    ensure_no_loop(&header)?;
+
+    let done = match header.route {
+        AccountTrace::Local => route_to_local_account(deps, env, msg, header, app)?,
         AccountTrace::Remote(ref chains) => {
             println!("routing to chains: {:?}", chains);
             // check index of hop. If we are on the final hop, route to local account
             if header.current_hop == (chains.len() - 1) as u32 {
                 println!("routing to local account: {:?}", chains);
-                return route_to_local_account(deps.as_ref(), msg.clone(), header, app);
+                route_to_local_account(deps, env, msg, header, app)?
+            } else if header.exceeds_max_hops() {
+                // Drop messages that are not allowed to travel further and notify the sender
+                bounce_msg(deps, env, app, msg, header, "hop limit exceeded")?
+            } else if header.is_expired(env.block.time) {
+                bounce_msg(deps, env, app, msg, header, "message expired in transit")?
+            } else {
+                let next_hop = next_hop(deps.as_ref(), env, app, &header)?;
+                log_delivery(
+                    deps.storage,
+                    env,
+                    &msg,
+                    DeliveryStatus::Dispatched {
+                        next_hop: next_hop.clone(),
+                    },
+                )?;
+                return Ok(RouteStep::Forward {
+                    next_hop,
+                    msg,
+                    header,
+                });
             }
-            // TODO verify that the chain is a valid chain
-
-            let current_module_info = ModuleInfo::from_id(app.module_id(), app.version().into())?;
-
-            let dest_chain =
-                chains
-                    .get(header.current_hop as usize + 1)
-                    .ok_or(ServerError::InvalidRoute {
-                        route: header.route.clone(),
-                        hop: header.current_hop,
-                    })?;
-
-            // ANCHOR: ibc_client
-            // Call IBC client
-            let ibc_client_msg = ibc_client::ExecuteMsg::ModuleIbcAction {
-                host_chain: dest_chain.clone(),
-                target_module: current_module_info,
-                msg: to_json_binary(&ServerIbcMessage::RouteMessage { msg, header })?,
-                callback: None,
-            };
-
-            let ibc_client_addr: Addr = app
-                .module_registry(deps.as_ref())?
-                .query_module(ModuleInfo::from_id_latest(IBC_CLIENT)?)?
-                .reference
-                .unwrap_native()?;
-
-            let msg: CosmosMsg = wasm_execute(ibc_client_addr, &ibc_client_msg, vec![])?.into();
-            // ANCHOR_END: ibc_client
-            Ok::<CosmosMsg, ServerError>(msg)
         }
-    }
+    };
+
+    Ok(RouteStep::Done(done))
 }
 
 fn route_to_local_account(
-    deps: Deps,
+    deps: DepsMut,
+    env: &Env,
     msg: IbcMailMessage,
     header: Header,
     app: &mut ServerAdapter,
-) -> ServerResult<CosmosMsg> {
+) -> ServerResult<Option<SubMsg>> {
     println!("routing to local account: {:?}", msg.message.recipient);
     // This is a local message
 
     let recipient = msg.message.recipient.clone();
 
+    let Some(account_id) = resolve_account_id(deps.as_ref(), app, &recipient)? else {
+        // Not an account, keep the message until the owner of the address claims it
+        if let Recipient::Address { address, .. } = &recipient {
+            let address = deps.api.addr_validate(address)?;
+            hold_msg(deps, env, &address, msg, header)?;
+        }
+        return Ok(None);
+    };
+
+    // ANCHOR: set_acc_and_send
+    // Set target account for actions, is used by APIs to retrieve mail client address.
+    let recipient_acc: Account = app.account_registry(deps.as_ref())?.account(&account_id)?;
+    app.target_account = Some(recipient_acc.clone());
+
+    let Some(receiver_id) = receiver_module(deps.as_ref(), &recipient_acc, &recipient)? else {
+        // Keep the message until the account installs a mail client or the receiving module
+        hold_msg(deps, env, recipient_acc.addr(), msg, header)?;
+        return Ok(None);
+    };
+    let msg: SubMsg = deliver_msg(deps.as_ref(), msg, header, app, &receiver_id)?;
+    // ANCHOR_END: set_acc_and_send
+
+    Ok(Some(msg))
+}
+
+/// The local account that `recipient` refers to.
+/// Returns `None` for address recipients that are not an account.
+pub(crate) fn resolve_account_id(
+    deps: Deps,
+    app: &ServerAdapter,
+    recipient: &Recipient,
+) -> ServerResult<Option<AccountId>> {
     let account_id = match recipient {
-        Recipient::Account { id: account_id, .. } => Ok(account_id),
+        Recipient::Account { id: account_id, .. }
+        | Recipient::Module {
+            account: account_id,
+            ..
+        } => account_id.clone(),
         Recipient::Namespace { namespace, .. } => {
             // TODO: this only allows for addressing recipients via namespace of their email account directly.
             // If they have the email application installed on a sub-account, this will not be able to identify the sub-account.
@@ -184,25 +836,182 @@ fn route_to_local_account(
                 .module_registry(deps)?
                 .query_namespace(namespace.clone())?;
             match namespace_status {
-                NamespaceResponse::Claimed(info) => Ok(info.account_id),
+                NamespaceResponse::Claimed(info) => info.account_id,
                 NamespaceResponse::Unclaimed {} => {
-                    return Err(ServerError::UnclaimedNamespace(namespace));
+                    return Err(ServerError::UnclaimedNamespace(namespace.clone()));
                 }
             }
         }
-        _ => Err(ServerError::NotImplemented(
-            "Non-account recipients not supported".to_string(),
-        )),
-    }?;
+        Recipient::Address { address, .. } => {
+            let address = deps.api.addr_validate(address)?;
+            match app.account_registry(deps)?.account_id(&address) {
+                Ok(account_id) => account_id,
+                Err(_) => return Ok(None),
+            }
+        }
+        _ => {
+            return Err(ServerError::NotImplemented(
+                "Non-account recipients not supported".to_string(),
+            ))
+        }
+    };
 
-    // ANCHOR: set_acc_and_send
-    // Set target account for actions, is used by APIs to retrieve mail client address.
-    let recipient_acc: Account = app.account_registry(deps)?.account(&account_id)?;
-    app.target_account = Some(recipient_acc);
+    Ok(Some(account_id))
+}
 
-    let mail_client: MailClient<_> = app.mail_client(deps);
-    let msg: CosmosMsg = mail_client.receive_msg(msg, header)?;
-    // ANCHOR_END: set_acc_and_send
+/// The module on `account` that receives mail for `recipient`, if installed.
+pub(crate) fn receiver_module(
+    deps: Deps,
+    account: &Account,
+    recipient: &Recipient,
+) -> ServerResult<Option<String>> {
+    match recipient {
+        // Modules implementing the receiver interface get the message directly
+        Recipient::Module { module_id, .. } => {
+            let installed =
+                ACCOUNT_MODULES.query(&deps.querier, account.addr().clone(), module_id)?;
+            Ok(installed.map(|_| module_id.clone()))
+        }
+        _ => installed_mail_client(deps, account),
+    }
+}
+
+/// Message that is being delivered, passed to the delivery reply.
+#[cosmwasm_schema::cw_serde]
+pub(crate) struct DeliveryPayload {
+    pub account: Addr,
+    pub msg: IbcMailMessage,
+    pub header: Header,
+}
+
+/// Deliver a message to the receiving module on the target account.
+/// The delivery is logged by the reply once the receiver accepted the message.
+/// A failed delivery doesn't fail the transaction, the message is kept as a dead letter instead.
+pub(crate) fn deliver_msg(
+    deps: Deps,
+    msg: IbcMailMessage,
+    header: Header,
+    app: &ServerAdapter,
+    receiver_id: &str,
+) -> ServerResult<SubMsg> {
+    let account = app.account(deps)?.into_addr();
+    let payload = to_json_binary(&DeliveryPayload {
+        account,
+        msg: msg.clone(),
+        header: header.clone(),
+    })?;
+
+    let receiver: MailReceiver<_> = app.mail_receiver(deps, receiver_id);
+    Ok(
+        SubMsg::reply_always(receiver.receive_msg(msg, header)?, DELIVERY_REPLY_ID)
+            .with_payload(payload),
+    )
+}
+
+/// Send a notification that `msg` could not be delivered back to its sender.
+/// Messages from servers are not bounced to prevent bounce loops.
+/// A bounce that can't be routed is logged as failed instead of failing the caller,
+/// which would lose the other messages it handles.
+pub(crate) fn bounce_msg(
+    mut deps: DepsMut,
+    env: &Env,
+    app: &mut ServerAdapter,
+    msg: IbcMailMessage,
+    header: Header,
+    reason: &str,
+) -> ServerResult<Option<SubMsg>> {
+    let Some(recipient) = msg.sender.reply_recipient() else {
+        let reason = reason.to_string();
+        log_delivery(deps.storage, env, &msg, DeliveryStatus::Failed { reason })?;
+        return Ok(None);
+    };
+    let status = DeliveryStatus::Bounced {
+        reason: reason.to_string(),
+    };
+    log_delivery(deps.storage, env, &msg, status)?;
+    count_message(deps.storage, env, &header.route, Counter::Bounced)?;
+
+    let bounce = IbcMailMessage {
+        id: format!("bounce-{}", msg.id),
+        sender: Sender::Server {
+            chain: TruncatedChainId::new(env),
+        },
+        version: EMAIL_VERSION.to_string(),
+        timestamp: env.block.time,
+        message: Message::new(
+            recipient,
+            format!("Undeliverable: {}", msg.message.subject),
+            format!("Message {} could not be delivered: {reason}", msg.id),
+        ),
+    };
+    let header = new_header(deps.as_ref(), env, header.return_route())?;
+
+    match route_msg(deps.branch(), env, bounce.clone(), header, app) {
+        Ok(msg) => Ok(msg),
+        Err(error) => {
+            let reason = error.to_string();
+            log_delivery(
+                deps.storage,
+                env,
+                &bounce,
+                DeliveryStatus::Failed { reason },
+            )?;
+            Ok(None)
+        }
+    }
+}
+
+/// Record what happened to `msg` in the delivery log, removing the oldest entry once it is full.
+pub(crate) fn log_delivery(
+    storage: &mut dyn Storage,
+    env: &Env,
+    msg: &IbcMailMessage,
+    status: DeliveryStatus,
+) -> ServerResult<()> {
+    let id = DELIVERY_LOG_NEXT_ID.may_load(storage)?.unwrap_or_default();
+    DELIVERY_LOG_NEXT_ID.save(storage, &(id + 1))?;
+
+    let log = delivery_log();
+    log.save(
+        storage,
+        id,
+        &DeliveryRecord {
+            message_id: msg.id.clone(),
+            sender: msg.sender.clone(),
+            sender_key: sender_key(&msg.sender)?,
+            status,
+            height: env.block.height,
+            time: env.block.time,
+        },
+    )?;
+    if let Some(oldest) = id.checked_sub(DELIVERY_LOG_SIZE) {
+        log.remove(storage, oldest)?;
+    }
+
+    Ok(())
+}
+
+/// Header of a message sent from the current chain, limited by the server config.
+pub(crate) fn new_header(deps: Deps, env: &Env, route: Route) -> ServerResult<Header> {
+    let config = CONFIG.load(deps.storage)?;
+
+    Ok(Header {
+        max_hops: Some(config.max_hops),
+        expires_at: Some(env.block.time.plus_seconds(config.message_ttl)),
+        ..Header::new(route)
+    })
+}
+
+/// Find the first accepted mail client that is installed on `account`.
+fn installed_mail_client(deps: Deps, account: &Account) -> ServerResult<Option<String>> {
+    let config = CONFIG.load(deps.storage)?;
+
+    for module_id in config.accepted_clients {
+        let installed = ACCOUNT_MODULES.query(&deps.querier, account.addr().clone(), &module_id)?;
+        if installed.is_some() {
+            return Ok(Some(module_id));
+        }
+    }
 
-    Ok(msg)
+    Ok(None)
 }
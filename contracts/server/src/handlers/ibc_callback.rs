@@ -0,0 +1,57 @@
+use abstract_adapter::sdk::AbstractResponse;
+use abstract_adapter::std::ibc::{Callback, IbcResult};
+use cosmwasm_std::{from_json, DepsMut, Env};
+use ibcmail::server::{
+    msg::{DryRunResponse, ServerCallbackMsg, WireVersionsResponse},
+    state::PEER_WIRE_VERSIONS,
+    wire, ServerAdapter,
+};
+
+use crate::{
+    contract::ServerResult,
+    handlers::execute::{bounce_msg, route_msg},
+};
+
+pub fn ibc_callback_handler(
+    deps: DepsMut,
+    env: Env,
+    mut app: ServerAdapter,
+    callback: Callback,
+    result: IbcResult,
+) -> ServerResult {
+    match from_json::<ServerCallbackMsg>(callback.msg)? {
+        ServerCallbackMsg::Preflight { msg, header } => {
+            let rejection = match result.get_query_result(0) {
+                Ok((_, response)) => {
+                    let dry_run: DryRunResponse = from_json(response)?;
+                    // Mail for recipients without a mail client is held until they install one
+                    (!dry_run.errors.is_empty()).then(|| dry_run.errors.join(", "))
+                }
+                Err(error) => Some(error.to_string()),
+            };
+
+            let msg = match rejection {
+                None => route_msg(deps, &env, msg, header, &mut app)?,
+                Some(reason) => bounce_msg(deps, &env, &mut app, msg, header, &reason)?,
+            };
+
+            Ok(app.response("preflight_callback").add_submessages(msg))
+        }
+        ServerCallbackMsg::WireVersions { chain } => {
+            let response = app
+                .response("wire_versions_callback")
+                .add_attribute("chain", chain.as_str());
+            // A failed query says nothing about the server, the known version is kept
+            let version = match result.get_query_result(0) {
+                Ok((_, response)) => {
+                    let response: WireVersionsResponse = from_json(response)?;
+                    wire::highest_common_version(&response.supported)
+                }
+                Err(error) => return Ok(response.add_attribute("error", error.to_string())),
+            };
+            PEER_WIRE_VERSIONS.save(deps.storage, chain.as_str(), &version)?;
+
+            Ok(response.add_attribute("version", version.to_string()))
+        }
+    }
+}
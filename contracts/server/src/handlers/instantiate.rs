@@ -0,0 +1,20 @@
+use abstract_adapter::sdk::AbstractResponse;
+use cosmwasm_std::{DepsMut, Env, MessageInfo};
+use ibcmail::server::{
+    msg::ServerInstantiateMsg,
+    state::{ServerConfig, CONFIG},
+};
+
+use crate::contract::{Adapter, ServerResult};
+
+pub fn instantiate_handler(
+    deps: DepsMut,
+    _env: Env,
+    _info: MessageInfo,
+    app: Adapter,
+    _msg: ServerInstantiateMsg,
+) -> ServerResult {
+    CONFIG.save(deps.storage, &ServerConfig::default())?;
+
+    Ok(app.response("instantiate"))
+}
@@ -1,4 +1,13 @@
 pub mod execute;
+pub mod ibc_callback;
+pub mod instantiate;
 pub mod module_ibc;
+pub mod query;
+pub mod reply;
+pub mod sudo;
 
-pub use crate::handlers::{execute::execute_handler, module_ibc::module_ibc_handler};
+pub use crate::handlers::{
+    execute::execute_handler, ibc_callback::ibc_callback_handler, instantiate::instantiate_handler,
+    module_ibc::module_ibc_handler, query::query_handler, reply::delivery_reply_handler,
+    reply::ibc_receive_reply_handler, reply::ics20_transfer_reply_handler, sudo::sudo_handler,
+};
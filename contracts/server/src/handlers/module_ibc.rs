@@ -1,19 +1,32 @@
+use abstract_adapter::objects::TruncatedChainId;
 use abstract_adapter::sdk::AbstractResponse;
-use abstract_adapter::std::ibc::ModuleIbcInfo;
-use cosmwasm_std::{from_json, Binary, DepsMut, Env};
+use abstract_adapter::std::{ibc::ModuleIbcInfo, objects::account::AccountTrace};
+use cosmwasm_std::{ensure, Binary, Coin, DepsMut, Env};
 
 use ibcmail::{
-    server::{error::ServerError, msg::ServerIbcMessage, ServerAdapter},
-    IBCMAIL_SERVER_ID,
+    server::{
+        error::ServerError,
+        msg::ServerIbcMessage,
+        state::PEER_WIRE_VERSIONS,
+        wire::{self, Decoded, LEGACY_WIRE_VERSION},
+        ServerAdapter,
+    },
+    Header, IbcMailMessage, IBCMAIL_SERVER_ID,
 };
 
-use crate::{contract::ServerResult, handlers::execute::route_msg};
+use crate::{
+    contract::ServerResult,
+    handlers::execute::{bounce_msg, ensure_supported_version, route_msg, route_msgs},
+    pause::ensure_not_paused,
+    rate_limit::{check_rate_limit, origin_chain},
+    relay_fee::{pay_out_funds, take_relay_fee},
+};
 
 // ANCHOR: module_ibc_handler
 pub fn module_ibc_handler(
     deps: DepsMut,
-    _env: Env,
-    mut app: ServerAdapter,
+    env: Env,
+    app: ServerAdapter,
     module_info: ModuleIbcInfo,
     msg: Binary,
 ) -> ServerResult {
@@ -22,17 +35,146 @@ pub fn module_ibc_handler(
         return Err(ServerError::UnauthorizedIbcModule(module_info.clone()));
     };
 
-    let server_msg: ServerIbcMessage = from_json(msg)?;
+    handle_server_msg(deps, env, app, module_info.source_chain, msg, vec![])
+}
+// ANCHOR_END: module_ibc_handler
+
+/// Handle a message from the server on `source_chain`, whichever transport delivered and authenticated it.
+/// Servers are answered in the highest wire version they advertise and that this server supports,
+/// servers that send bare legacy messages in the legacy version.
+/// Messages forwarded by this server must arrive with its relay fee in `funds` if it requires one,
+/// which it pays to its collector. The rest of the funds goes to the account that the message is handled for.
+/// Messages that may not pass while the server or a chain on their route is paused are bounced,
+/// a failed Abstract IBC action would drop them without notifying anyone.
+pub(crate) fn handle_server_msg(
+    mut deps: DepsMut,
+    env: Env,
+    mut app: ServerAdapter,
+    source_chain: TruncatedChainId,
+    msg: Binary,
+    funds: Vec<Coin>,
+) -> ServerResult {
+    let mut available = funds.clone();
+    let Decoded { msg, peer_versions } = wire::decode(&msg)?;
+    let version = match peer_versions {
+        Some(versions) => wire::highest_common_version(&versions),
+        None => LEGACY_WIRE_VERSION,
+    };
+    PEER_WIRE_VERSIONS.save(deps.storage, source_chain.as_str(), &version)?;
 
-    match server_msg {
+    match msg {
         ServerIbcMessage::RouteMessage { msg, mut header } => {
             header.current_hop += 1;
+            ensure_from_previous_hop(&header, &source_chain)?;
 
-            let msg = route_msg(deps, msg, header, &mut app)?;
+            let origin = origin_chain(&env, &header.route);
+            let msg = IbcMailMessage {
+                sender: msg.sender.with_chain(&origin),
+                ..msg
+            };
+            let checked = ensure_not_paused(deps.storage, &header)
+                .and_then(|_| ensure_supported_version(&msg))
+                .and_then(|_| check_rate_limit(deps.storage, &env, &origin, &msg.sender))
+                .and_then(|_| {
+                    take_relay_fee(deps.as_ref(), &env, &msg.sender, &header, &mut available)
+                });
+            let (msg, relay_fee) = match checked {
+                Ok(relay_fee) => (
+                    route_msg(deps.branch(), &env, msg, header, &mut app)?,
+                    relay_fee,
+                ),
+                Err(error) => (
+                    bounce_msg(
+                        deps.branch(),
+                        &env,
+                        &mut app,
+                        msg,
+                        header,
+                        &error.to_string(),
+                    )?,
+                    None,
+                ),
+            };
+            let payouts = pay_out_funds(deps.as_ref(), &app, &funds, relay_fee.as_slice())?;
 
-            Ok(app.response("module_ibc").add_message(msg))
+            Ok(app
+                .response("module_ibc")
+                .add_submessages(msg)
+                .add_messages(payouts))
+        }
+        ServerIbcMessage::RouteMessages { msgs } => {
+            let mut routable = vec![];
+            let mut rejected = vec![];
+            let mut relay_fees = vec![];
+            for (msg, mut header) in msgs {
+                header.current_hop += 1;
+                let origin = origin_chain(&env, &header.route);
+                let msg = IbcMailMessage {
+                    sender: msg.sender.with_chain(&origin),
+                    ..msg
+                };
+                let checked = ensure_from_previous_hop(&header, &source_chain)
+                    .and_then(|_| ensure_not_paused(deps.storage, &header))
+                    .and_then(|_| ensure_supported_version(&msg))
+                    .and_then(|_| check_rate_limit(deps.storage, &env, &origin, &msg.sender))
+                    .and_then(|_| {
+                        take_relay_fee(deps.as_ref(), &env, &msg.sender, &header, &mut available)
+                    });
+                match checked {
+                    Ok(relay_fee) => {
+                        relay_fees.push((msg.id.clone(), relay_fee));
+                        routable.push((msg, header));
+                    }
+                    Err(error) => rejected.push((msg, header, error.to_string())),
+                }
+            }
+
+            // One failing message must not fail the packet for the rest of the batch
+            let (mut msgs, failures) = route_msgs(deps.branch(), &env, routable, &mut app)?;
+            // Fees are only collected for the messages that are forwarded
+            let relay_fees: Vec<_> = relay_fees
+                .into_iter()
+                .filter(|(id, _)| !failures.iter().any(|(msg, _, _)| &msg.id == id))
+                .filter_map(|(_, relay_fee)| relay_fee)
+                .collect();
+            let payouts = pay_out_funds(deps.as_ref(), &app, &funds, &relay_fees)?;
+            for (msg, header, reason) in failures.into_iter().chain(rejected) {
+                msgs.extend(bounce_msg(
+                    deps.branch(),
+                    &env,
+                    &mut app,
+                    msg,
+                    header,
+                    &reason,
+                )?);
+            }
+
+            Ok(app
+                .response("module_ibc")
+                .add_submessages(msgs)
+                .add_messages(payouts))
         }
         _ => Err(ServerError::UnauthorizedIbcMessage {}),
     }
 }
-// ANCHOR_END: module_ibc_handler
+
+/// Ensure that a message at its current hop was sent by the server on `source_chain`,
+/// the previous chain on its route. Transports only authenticate the server they received it from.
+fn ensure_from_previous_hop(header: &Header, source_chain: &TruncatedChainId) -> ServerResult<()> {
+    let previous_hop = match &header.route {
+        AccountTrace::Remote(chains) => header
+            .current_hop
+            .checked_sub(1)
+            .and_then(|hop| chains.get(hop as usize)),
+        AccountTrace::Local => None,
+    };
+    ensure!(
+        previous_hop == Some(source_chain),
+        ServerError::WrongSourceChain {
+            chain: source_chain.clone(),
+            route: header.route.clone(),
+        }
+    );
+
+    Ok(())
+}
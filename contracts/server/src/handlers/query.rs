@@ -0,0 +1,402 @@
+use abstract_adapter::objects::{account::AccountTrace, TruncatedChainId};
+use abstract_adapter::sdk::AccountVerification;
+use cosmwasm_std::{to_json_binary, Binary, Coin, Deps, Env, Order, StdResult};
+use cw_storage_plus::Bound;
+use ibcmail::{
+    server::{
+        msg::{
+            ChainStatistics, ChainStatisticsResponse, ConfigResponse, DeadLettersResponse,
+            DeliveryLogResponse, DryRunResponse, HeldMailResponse, LinkCost, PauseStatusResponse,
+            RouteTableResponse, ServerQueryMsg, StatisticsResponse, WireVersionsResponse,
+        },
+        state::{
+            delivery_log, sender_key, BLOCKED_LINKS, CHAIN_BREAKERS, CHAIN_RATE_LIMITS,
+            CHAIN_STATS, CONFIG, DEAD_LETTERS, HELD_MAIL, IBC_CHANNELS, ICS20_ROUTES, LINK_COSTS,
+            PAUSED, PEER_WIRE_VERSIONS, POLYTONE_ROUTES, PREFERRED_HOPS, REMOTE_RELAY_FEES,
+            ROUTE_TABLE, TOTAL_STATS,
+        },
+        wire::SUPPORTED_WIRE_VERSIONS,
+    },
+    IbcMailMessage, Message, MessageHash, Route, Sender, EMAIL_VERSION,
+};
+
+use crate::{
+    contract::{Adapter, ServerResult},
+    handlers::execute::{
+        ensure_no_loop, ensure_sendable, new_header, next_hop, receiver_module, resolve_account_id,
+        resolve_route,
+    },
+    relay_fee::{relay_fee_payment, route_relay_fees, total_relay_fees},
+    routing::transfer_funds,
+};
+
+const DEFAULT_LIMIT: u32 = 10;
+const MAX_LIMIT: u32 = 30;
+
+pub fn query_handler(
+    deps: Deps,
+    env: Env,
+    app: &Adapter,
+    msg: ServerQueryMsg,
+) -> ServerResult<Binary> {
+    match msg {
+        ServerQueryMsg::Config {} => to_json_binary(&query_config(deps)?),
+        ServerQueryMsg::HeldMail {
+            address,
+            start_after,
+            limit,
+        } => to_json_binary(&query_held_mail(deps, address, start_after, limit)?),
+        ServerQueryMsg::DeadLetters {
+            address,
+            start_after,
+            limit,
+        } => to_json_binary(&query_dead_letters(deps, address, start_after, limit)?),
+        ServerQueryMsg::RouteTable {} => to_json_binary(&query_route_table(deps)?),
+        ServerQueryMsg::DryRun {
+            message,
+            route,
+            sender,
+        } => to_json_binary(&query_dry_run(deps, &env, app, message, route, sender)?),
+        ServerQueryMsg::MessageHistory { id } => to_json_binary(&query_message_history(deps, id)?),
+        ServerQueryMsg::SenderHistory {
+            sender,
+            start_after,
+            limit,
+        } => to_json_binary(&query_sender_history(
+            deps,
+            &env,
+            sender,
+            start_after,
+            limit,
+        )?),
+        ServerQueryMsg::WireVersions {} => to_json_binary(&query_wire_versions(deps)?),
+        ServerQueryMsg::PauseStatus {} => to_json_binary(&query_pause_status(deps)?),
+        ServerQueryMsg::Statistics {} => to_json_binary(&query_statistics(deps)?),
+        ServerQueryMsg::ChainStatistics { start_after, limit } => {
+            to_json_binary(&query_chain_statistics(deps, start_after, limit)?)
+        }
+    }
+    .map_err(Into::into)
+}
+
+fn query_config(deps: Deps) -> ServerResult<ConfigResponse> {
+    let config = CONFIG.load(deps.storage)?;
+    let chain_rate_limits = CHAIN_RATE_LIMITS
+        .range(deps.storage, None, None, Order::Ascending)
+        .map(|item| {
+            let (chain, limit) = item?;
+            Ok((TruncatedChainId::from_string(chain)?, limit))
+        })
+        .collect::<ServerResult<_>>()?;
+
+    let remote_relay_fees = REMOTE_RELAY_FEES
+        .range(deps.storage, None, None, Order::Ascending)
+        .map(|item| {
+            let (chain, fee) = item?;
+            Ok((TruncatedChainId::from_string(chain)?, fee))
+        })
+        .collect::<ServerResult<_>>()?;
+
+    Ok(ConfigResponse {
+        config,
+        chain_rate_limits,
+        remote_relay_fees,
+    })
+}
+
+fn query_held_mail(
+    deps: Deps,
+    address: String,
+    start_after: Option<MessageHash>,
+    limit: Option<u32>,
+) -> ServerResult<HeldMailResponse> {
+    let address = deps.api.addr_validate(&address)?;
+    let limit = limit.unwrap_or(DEFAULT_LIMIT).min(MAX_LIMIT) as usize;
+
+    let messages = HELD_MAIL
+        .prefix(&address)
+        .range(
+            deps.storage,
+            start_after.as_deref().map(Bound::exclusive),
+            None,
+            Order::Ascending,
+        )
+        .take(limit)
+        .map(|item| item.map(|(_, held_msg)| held_msg))
+        .collect::<StdResult<_>>()?;
+
+    Ok(HeldMailResponse { messages })
+}
+
+fn query_dead_letters(
+    deps: Deps,
+    address: String,
+    start_after: Option<MessageHash>,
+    limit: Option<u32>,
+) -> ServerResult<DeadLettersResponse> {
+    let address = deps.api.addr_validate(&address)?;
+    let limit = limit.unwrap_or(DEFAULT_LIMIT).min(MAX_LIMIT) as usize;
+
+    let messages = DEAD_LETTERS
+        .prefix(&address)
+        .range(
+            deps.storage,
+            start_after.as_deref().map(Bound::exclusive),
+            None,
+            Order::Ascending,
+        )
+        .take(limit)
+        .map(|item| item.map(|(_, dead_letter)| dead_letter))
+        .collect::<StdResult<_>>()?;
+
+    Ok(DeadLettersResponse { messages })
+}
+
+fn query_message_history(deps: Deps, id: MessageHash) -> ServerResult<DeliveryLogResponse> {
+    let entries = delivery_log()
+        .idx
+        .message_id
+        .prefix(id)
+        .range(deps.storage, None, None, Order::Ascending)
+        .collect::<StdResult<_>>()?;
+
+    Ok(DeliveryLogResponse { entries })
+}
+
+/// Senders without a chain are senders on this chain.
+fn query_sender_history(
+    deps: Deps,
+    env: &Env,
+    sender: Sender,
+    start_after: Option<u64>,
+    limit: Option<u32>,
+) -> ServerResult<DeliveryLogResponse> {
+    let sender = sender.with_chain(&TruncatedChainId::new(env));
+    let limit = limit.unwrap_or(DEFAULT_LIMIT).min(MAX_LIMIT) as usize;
+
+    let entries = delivery_log()
+        .idx
+        .sender
+        .prefix(sender_key(&sender)?)
+        .range(
+            deps.storage,
+            start_after.map(Bound::exclusive),
+            None,
+            Order::Ascending,
+        )
+        .take(limit)
+        .collect::<StdResult<_>>()?;
+
+    Ok(DeliveryLogResponse { entries })
+}
+
+fn query_route_table(deps: Deps) -> ServerResult<RouteTableResponse> {
+    let chains = ROUTE_TABLE
+        .range(deps.storage, None, None, Order::Ascending)
+        .map(|item| {
+            let (chain, remote_hosts) = item?;
+            Ok((TruncatedChainId::from_string(chain)?, remote_hosts))
+        })
+        .collect::<ServerResult<_>>()?;
+
+    let preferred_hops = PREFERRED_HOPS
+        .range(deps.storage, None, None, Order::Ascending)
+        .map(|item| {
+            let (destination, next_hop) = item?;
+            Ok((TruncatedChainId::from_string(destination)?, next_hop))
+        })
+        .collect::<ServerResult<_>>()?;
+
+    let link_costs = LINK_COSTS
+        .range(deps.storage, None, None, Order::Ascending)
+        .map(|item| {
+            let ((from, to), cost) = item?;
+            Ok(LinkCost {
+                from: TruncatedChainId::from_string(from)?,
+                to: TruncatedChainId::from_string(to)?,
+                cost,
+            })
+        })
+        .collect::<ServerResult<_>>()?;
+
+    let blocked_links = BLOCKED_LINKS
+        .keys(deps.storage, None, None, Order::Ascending)
+        .map(|item| {
+            let (from, to) = item?;
+            Ok((
+                TruncatedChainId::from_string(from)?,
+                TruncatedChainId::from_string(to)?,
+            ))
+        })
+        .collect::<ServerResult<_>>()?;
+
+    let polytone_routes = POLYTONE_ROUTES
+        .range(deps.storage, None, None, Order::Ascending)
+        .map(|item| {
+            let (chain, route) = item?;
+            Ok((TruncatedChainId::from_string(chain)?, route))
+        })
+        .collect::<ServerResult<_>>()?;
+
+    let ibc_channels = IBC_CHANNELS
+        .range(deps.storage, None, None, Order::Ascending)
+        .map(|item| {
+            let (chain, channel_id) = item?;
+            Ok((TruncatedChainId::from_string(chain)?, channel_id))
+        })
+        .collect::<ServerResult<_>>()?;
+
+    let ics20_routes = ICS20_ROUTES
+        .range(deps.storage, None, None, Order::Ascending)
+        .map(|item| {
+            let (chain, route) = item?;
+            Ok((TruncatedChainId::from_string(chain)?, route))
+        })
+        .collect::<ServerResult<_>>()?;
+
+    Ok(RouteTableResponse {
+        chains,
+        preferred_hops,
+        link_costs,
+        blocked_links,
+        polytone_routes,
+        ibc_channels,
+        ics20_routes,
+    })
+}
+
+fn query_dry_run(
+    deps: Deps,
+    env: &Env,
+    app: &Adapter,
+    message: Message,
+    route: Option<Route>,
+    sender: Sender,
+) -> ServerResult<DryRunResponse> {
+    let mut errors = vec![];
+    let mut check = |result: ServerResult<()>| {
+        if let Err(error) = result {
+            errors.push(error.to_string());
+        }
+    };
+
+    let route = match resolve_route(deps, env, app, &message.recipient, route) {
+        Ok(route) => Some(route),
+        Err(error) => {
+            check(Err(error));
+            None
+        }
+    };
+    let mut recipient_account = None;
+    let mut fees = vec![];
+    if let Some(route) = &route {
+        let header = new_header(deps, env, route.clone())?;
+        let relay_fees = route_relay_fees(deps, route)?;
+        let transfer = transfer_funds(deps, app, &header)?;
+        let payments: Vec<Coin> = relay_fees
+            .iter()
+            .map(|(_, fee)| relay_fee_payment(fee))
+            .collect();
+        fees = total_relay_fees(
+            payments
+                .iter()
+                .chain(transfer.iter().map(|(_, funds)| funds)),
+        )?;
+
+        // The same checks as for mail that is sent
+        let msg = IbcMailMessage {
+            id: String::new(),
+            sender: sender.with_chain(&TruncatedChainId::new(env)),
+            version: EMAIL_VERSION.to_string(),
+            timestamp: env.block.time,
+            message: message.clone(),
+        };
+        check(ensure_no_loop(&header));
+        check(ensure_sendable(deps, env, &msg, &header));
+
+        match route {
+            AccountTrace::Local => match resolve_account_id(deps, app, &message.recipient) {
+                Ok(account_id) => recipient_account = account_id,
+                Err(error) => check(Err(error)),
+            },
+            AccountTrace::Remote(_) => check(next_hop(deps, env, app, &header).map(|_| ())),
+        }
+    }
+    let mut mail_client = None;
+    if let Some(account_id) = &recipient_account {
+        match app.account_registry(deps)?.account(account_id) {
+            Ok(account) => mail_client = receiver_module(deps, &account, &message.recipient)?,
+            Err(error) => check(Err(error.into())),
+        }
+    }
+
+    Ok(DryRunResponse {
+        route,
+        recipient_account,
+        mail_client,
+        fees,
+        errors,
+    })
+}
+
+fn query_wire_versions(deps: Deps) -> ServerResult<WireVersionsResponse> {
+    let peers = PEER_WIRE_VERSIONS
+        .range(deps.storage, None, None, Order::Ascending)
+        .map(|item| {
+            let (chain, version) = item?;
+            Ok((TruncatedChainId::from_string(chain)?, version))
+        })
+        .collect::<ServerResult<_>>()?;
+
+    Ok(WireVersionsResponse {
+        supported: SUPPORTED_WIRE_VERSIONS.to_vec(),
+        peers,
+    })
+}
+
+fn query_pause_status(deps: Deps) -> ServerResult<PauseStatusResponse> {
+    let chain_breakers = CHAIN_BREAKERS
+        .range(deps.storage, None, None, Order::Ascending)
+        .map(|item| {
+            let (chain, mode) = item?;
+            Ok((TruncatedChainId::from_string(chain)?, mode))
+        })
+        .collect::<ServerResult<_>>()?;
+
+    Ok(PauseStatusResponse {
+        paused: PAUSED.may_load(deps.storage)?,
+        chain_breakers,
+    })
+}
+
+fn query_statistics(deps: Deps) -> ServerResult<StatisticsResponse> {
+    Ok(StatisticsResponse {
+        total: TOTAL_STATS.may_load(deps.storage)?.unwrap_or_default(),
+    })
+}
+
+fn query_chain_statistics(
+    deps: Deps,
+    start_after: Option<(TruncatedChainId, TruncatedChainId)>,
+    limit: Option<u32>,
+) -> ServerResult<ChainStatisticsResponse> {
+    let limit = limit.unwrap_or(DEFAULT_LIMIT).min(MAX_LIMIT) as usize;
+    let start_after = start_after
+        .as_ref()
+        .map(|(source, destination)| Bound::exclusive((source.as_str(), destination.as_str())));
+
+    let chains = CHAIN_STATS
+        .range(deps.storage, start_after, None, Order::Ascending)
+        .take(limit)
+        .map(|item| {
+            let ((source, destination), stats) = item?;
+            Ok(ChainStatistics {
+                source: TruncatedChainId::from_string(source)?,
+                destination: TruncatedChainId::from_string(destination)?,
+                stats,
+            })
+        })
+        .collect::<ServerResult<_>>()?;
+
+    Ok(ChainStatisticsResponse { chains })
+}
@@ -0,0 +1,169 @@
+use abstract_adapter::sdk::AbstractResponse;
+use cosmwasm_std::{from_json, to_json_binary, DepsMut, Env, Reply, StdError, SubMsgResult};
+use ibcmail::server::{
+    error::ServerError,
+    msg::IbcMailAck,
+    state::{DeadLetter, DeliveryStatus, DEAD_LETTERS, PENDING_TRANSFERS},
+    transport::Ics20TransferPayload,
+    ServerAdapter,
+};
+
+use crate::{
+    contract::ServerResult,
+    handlers::execute::{log_delivery, DeliveryPayload},
+    stats::{count_message, Counter},
+};
+
+/// Log and count the messages that the receiver accepted as delivered
+/// and keep the messages that it failed to receive as dead letters.
+pub fn delivery_reply_handler(
+    deps: DepsMut,
+    env: Env,
+    app: ServerAdapter,
+    reply: Reply,
+) -> ServerResult {
+    let DeliveryPayload {
+        account,
+        msg,
+        header,
+    } = from_json(reply.payload)?;
+    let SubMsgResult::Err(error) = reply.result else {
+        log_delivery(deps.storage, &env, &msg, DeliveryStatus::Delivered {})?;
+        count_message(deps.storage, &env, &header.route, Counter::Delivered)?;
+        return Ok(app.response("delivery").add_attribute("message_id", msg.id));
+    };
+
+    let status = DeliveryStatus::DeadLettered {
+        reason: error.clone(),
+    };
+    log_delivery(deps.storage, &env, &msg, status)?;
+
+    let id = msg.id.clone();
+    DEAD_LETTERS.save(
+        deps.storage,
+        (&account, id.as_str()),
+        &DeadLetter {
+            msg,
+            header,
+            error,
+            failed_at: env.block.time,
+        },
+    )?;
+
+    Ok(app.response("dead_letter").add_attribute("message_id", id))
+}
+
+/// Acknowledge a packet received on the server's own IBC port with the result of handling it.
+/// The state changes of a failed packet are reverted, the sending server bounces its messages.
+pub fn ibc_receive_reply_handler(
+    _deps: DepsMut,
+    _env: Env,
+    app: ServerAdapter,
+    reply: Reply,
+) -> ServerResult {
+    let response = app.response("ibc_packet_receive");
+    let response = match reply.result {
+        SubMsgResult::Ok(_) => response.set_data(to_json_binary(&IbcMailAck::Success {})?),
+        SubMsgResult::Err(error) => response
+            .set_data(to_json_binary(&IbcMailAck::Error(error.clone()))?)
+            .add_attribute("error", error),
+    };
+
+    Ok(response)
+}
+
+/// Remember the message of an ICS-20 transfer by its packet sequence,
+/// so it can be bounced once ibc-hooks reports that the transfer failed.
+pub fn ics20_transfer_reply_handler(
+    deps: DepsMut,
+    _env: Env,
+    app: ServerAdapter,
+    reply: Reply,
+) -> ServerResult {
+    let Ics20TransferPayload { channel, msg } = from_json(reply.payload)?;
+    let response = reply.result.into_result().map_err(StdError::generic_err)?;
+    let sequence = response
+        .msg_responses
+        .first()
+        .and_then(|response| transfer_sequence(&response.value))
+        .ok_or(ServerError::MissingTransferSequence {})?;
+
+    PENDING_TRANSFERS.save(deps.storage, (&channel, sequence), &msg)?;
+
+    Ok(app
+        .response("ics20_transfer")
+        .add_attribute("channel", channel)
+        .add_attribute("sequence", sequence.to_string()))
+}
+
+/// Sequence of the packet in an encoded `MsgTransferResponse`, its only field.
+/// Decoded by hand, a protobuf dependency is not worth it for a single varint.
+fn transfer_sequence(response: &[u8]) -> Option<u64> {
+    let (&tag, varint) = response.split_first()?;
+    // Field 1 with varint wire type
+    if tag != 0x08 {
+        return None;
+    }
+
+    let mut sequence = 0u64;
+    for (i, byte) in varint.iter().take(10).enumerate() {
+        // The tenth byte only holds the highest bit of a u64
+        if i == 9 && byte & 0x7f > 1 {
+            return None;
+        }
+        sequence |= u64::from(byte & 0x7f) << (7 * i);
+        if byte & 0x80 == 0 {
+            return Some(sequence);
+        }
+    }
+
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn reads_sequence_of_transfer_response() {
+        assert_eq!(transfer_sequence(&[0x08, 0x01]), Some(1));
+        assert_eq!(transfer_sequence(&[0x08, 0xac, 0x02]), Some(300));
+        assert_eq!(transfer_sequence(&[0x08, 0xac]), None);
+        assert_eq!(transfer_sequence(&[0x10, 0x01]), None);
+        assert_eq!(transfer_sequence(&[]), None);
+    }
+
+    #[test]
+    fn reads_multi_byte_sequences() {
+        assert_eq!(transfer_sequence(&[0x08, 0x80, 0x01]), Some(128));
+        assert_eq!(transfer_sequence(&[0x08, 0xff, 0x7f]), Some(16_383));
+        assert_eq!(transfer_sequence(&[0x08, 0x80, 0x80, 0x01]), Some(16_384));
+        assert_eq!(
+            transfer_sequence(&[0x08, 0xff, 0xff, 0xff, 0xff, 0x0f]),
+            Some(u64::from(u32::MAX))
+        );
+        assert_eq!(
+            transfer_sequence(&[0x08, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0x01]),
+            Some(u64::MAX)
+        );
+        // Bytes after the varint belong to other fields
+        assert_eq!(
+            transfer_sequence(&[0x08, 0xac, 0x02, 0x12, 0x00]),
+            Some(300)
+        );
+    }
+
+    #[test]
+    fn rejects_sequences_beyond_u64() {
+        assert_eq!(
+            transfer_sequence(&[0x08, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0x02]),
+            None
+        );
+        assert_eq!(
+            transfer_sequence(&[
+                0x08, 0x80, 0x80, 0x80, 0x80, 0x80, 0x80, 0x80, 0x80, 0x80, 0x80, 0x01
+            ]),
+            None
+        );
+    }
+}
@@ -0,0 +1,50 @@
+use abstract_adapter::sdk::AbstractResponse;
+use cosmwasm_std::{DepsMut, Env};
+use ibcmail::server::{
+    msg::{IbcLifecycleComplete, ServerSudoMsg},
+    state::PENDING_TRANSFERS,
+    ServerAdapter,
+};
+
+use crate::{contract::ServerResult, ibc::bounce_packet};
+
+/// Bounce the messages of the ICS-20 transfers that failed or timed out, as reported by ibc-hooks.
+pub fn sudo_handler(
+    deps: DepsMut,
+    env: Env,
+    app: ServerAdapter,
+    msg: ServerSudoMsg,
+) -> ServerResult {
+    let ServerSudoMsg::IbcLifecycleComplete(lifecycle) = msg;
+    let (channel, sequence, failure) = match lifecycle {
+        IbcLifecycleComplete::IbcAck {
+            channel,
+            sequence,
+            ack,
+            success,
+        } => (
+            channel,
+            sequence,
+            (!success).then(|| format!("transfer failed: {ack}")),
+        ),
+        IbcLifecycleComplete::IbcTimeout { channel, sequence } => {
+            (channel, sequence, Some("transfer timed out".to_string()))
+        }
+    };
+
+    let response = app
+        .response("ibc_lifecycle_complete")
+        .add_attribute("channel", &channel)
+        .add_attribute("sequence", sequence.to_string());
+    let Some(packet) = PENDING_TRANSFERS.may_load(deps.storage, (&channel, sequence))? else {
+        return Ok(response);
+    };
+    PENDING_TRANSFERS.remove(deps.storage, (&channel, sequence));
+
+    let bounces = match failure {
+        Some(reason) => bounce_packet(deps, &env, &packet, &reason)?,
+        None => vec![],
+    };
+
+    Ok(response.add_submessages(bounces))
+}
@@ -0,0 +1,150 @@
+use abstract_adapter::sdk::features::AccountIdentification;
+use abstract_adapter::traits::AbstractResponse;
+use cosmwasm_std::{ensure, Addr, DepsMut, Env, Order, StdResult, SubMsg};
+use ibcmail::{
+    is_account_address,
+    server::state::{DeliveryStatus, HeldMessage, CONFIG, HELD_MAIL},
+    Header, IbcMailMessage,
+};
+
+use crate::{
+    contract::{Adapter, ServerResult},
+    error::ServerError,
+    handlers::execute::{bounce_msg, deliver_msg, log_delivery, receiver_module},
+};
+
+/// Hold a message for `address` until it is claimed or expires.
+pub(crate) fn hold_msg(
+    deps: DepsMut,
+    env: &Env,
+    address: &Addr,
+    msg: IbcMailMessage,
+    header: Header,
+) -> ServerResult<()> {
+    let config = CONFIG.load(deps.storage)?;
+    log_delivery(deps.storage, env, &msg, DeliveryStatus::Held {})?;
+    let id = msg.id.clone();
+    HELD_MAIL.save(
+        deps.storage,
+        (address, id.as_str()),
+        &HeldMessage {
+            msg,
+            header,
+            held_at: env.block.time,
+            expires_at: env.block.time.plus_seconds(config.held_mail_expiry),
+        },
+    )?;
+
+    Ok(())
+}
+
+/// Remove the mail held for `address`, split into live and expired messages.
+pub(crate) fn take_held_mail(
+    deps: DepsMut,
+    env: &Env,
+    address: &Addr,
+) -> ServerResult<(Vec<HeldMessage>, Vec<HeldMessage>)> {
+    let held = HELD_MAIL
+        .prefix(address)
+        .range(deps.storage, None, None, Order::Ascending)
+        .collect::<StdResult<Vec<_>>>()?;
+
+    let mut live = vec![];
+    let mut expired = vec![];
+    for (id, held_msg) in held {
+        HELD_MAIL.remove(deps.storage, (address, id.as_str()));
+        if held_msg.expires_at <= env.block.time {
+            expired.push(held_msg);
+        } else {
+            live.push(held_msg);
+        }
+    }
+
+    Ok((live, expired))
+}
+
+pub(crate) fn claim_mail(
+    mut deps: DepsMut,
+    env: Env,
+    mut app: Adapter,
+    address: String,
+) -> ServerResult {
+    let address = deps.api.addr_validate(&address)?;
+    let account = app.account(deps.as_ref())?;
+    let account_id = app.account_id(deps.as_ref())?;
+    ensure!(
+        is_account_address(&deps.querier, account.addr(), &address)?,
+        ServerError::CannotClaim(address.to_string())
+    );
+
+    let (live, expired) = take_held_mail(deps.branch(), &env, &address)?;
+
+    let mut msgs: Vec<SubMsg> = Vec::with_capacity(live.len() + expired.len());
+    for held_msg in live {
+        let recipient = held_msg.msg.message.recipient.clone();
+        let receiver_id = receiver_module(deps.as_ref(), &account, &recipient)?
+            .ok_or_else(|| ServerError::NoMailClient(account_id.clone()))?;
+        msgs.push(deliver_msg(
+            deps.as_ref(),
+            held_msg.msg,
+            held_msg.header,
+            &app,
+            &receiver_id,
+        )?);
+    }
+    let claimed = msgs.len();
+
+    // Bouncing changes the target account, so it happens after delivery
+    for held_msg in expired {
+        msgs.extend(bounce_msg(
+            deps.branch(),
+            &env,
+            &mut app,
+            held_msg.msg,
+            held_msg.header,
+            "held mail expired",
+        )?);
+    }
+
+    Ok(app
+        .response("claim_mail")
+        .add_attribute("claimed", claimed.to_string())
+        .add_submessages(msgs))
+}
+
+pub(crate) fn bounce_expired_mail(
+    mut deps: DepsMut,
+    env: Env,
+    mut app: Adapter,
+    address: String,
+) -> ServerResult {
+    let address = deps.api.addr_validate(&address)?;
+
+    let (live, expired) = take_held_mail(deps.branch(), &env, &address)?;
+    // Put back the mail that is still waiting to be claimed
+    for held_msg in live {
+        HELD_MAIL.save(
+            deps.storage,
+            (&address, held_msg.msg.id.as_str()),
+            &held_msg,
+        )?;
+    }
+
+    let bounced = expired.len();
+    let mut msgs: Vec<SubMsg> = vec![];
+    for held_msg in expired {
+        msgs.extend(bounce_msg(
+            deps.branch(),
+            &env,
+            &mut app,
+            held_msg.msg,
+            held_msg.header,
+            "held mail expired",
+        )?);
+    }
+
+    Ok(app
+        .response("bounce_expired_mail")
+        .add_attribute("bounced", bounced.to_string())
+        .add_submessages(msgs))
+}
@@ -0,0 +1,407 @@
+//! Entry points of the mail server's own IBC port.
+//! Servers connected by an unordered `ibcmail-1` channel exchange [`ServerIbcMessage`]s directly,
+//! without going through Abstract IBC. Only the ports of trusted servers may open channels.
+
+use abstract_adapter::objects::TruncatedChainId;
+use abstract_adapter::std::adapter::{self, AdapterRequestMsg};
+use cosmwasm_std::{
+    ensure, from_json, to_json_binary, wasm_execute, Binary, CosmosMsg, Deps, DepsMut, Env,
+    Ibc3ChannelOpenResponse, IbcBasicResponse, IbcChannel, IbcChannelCloseMsg,
+    IbcChannelConnectMsg, IbcChannelOpenMsg, IbcChannelOpenResponse, IbcOrder, IbcPacketAckMsg,
+    IbcPacketReceiveMsg, IbcPacketTimeoutMsg, IbcReceiveResponse, Order, StdResult, Storage,
+    SubMsg,
+};
+use ibcmail::server::{
+    error::ServerError,
+    msg::{IbcMailAck, ServerExecuteMsg, ServerIbcMessage},
+    state::{IBC_CHANNELS, IBC_RELAY_ACCOUNT, OPEN_CHANNELS, TRUSTED_PORTS},
+    transport::IBCMAIL_IBC_VERSION,
+    wire,
+};
+
+use crate::{
+    contract::{ServerResult, ADAPTER, IBC_RECEIVE_REPLY_ID},
+    handlers::execute::bounce_msg,
+};
+
+#[cfg_attr(feature = "export", cosmwasm_std::entry_point)]
+pub fn ibc_channel_open(
+    deps: DepsMut,
+    _env: Env,
+    msg: IbcChannelOpenMsg,
+) -> ServerResult<IbcChannelOpenResponse> {
+    let channel = msg.channel();
+    ensure_valid_channel(channel, msg.counterparty_version())?;
+    counterparty_chain(deps.storage, channel)?;
+
+    Ok(Some(Ibc3ChannelOpenResponse {
+        version: IBCMAIL_IBC_VERSION.to_string(),
+    }))
+}
+
+#[cfg_attr(feature = "export", cosmwasm_std::entry_point)]
+pub fn ibc_channel_connect(
+    deps: DepsMut,
+    _env: Env,
+    msg: IbcChannelConnectMsg,
+) -> ServerResult<IbcBasicResponse> {
+    let channel = msg.channel();
+    ensure_valid_channel(channel, msg.counterparty_version())?;
+    let chain = counterparty_chain(deps.storage, channel)?;
+
+    OPEN_CHANNELS.save(deps.storage, &channel.endpoint.channel_id, &chain)?;
+
+    Ok(IbcBasicResponse::new()
+        .add_attribute("action", "ibc_channel_connect")
+        .add_attribute("channel_id", &channel.endpoint.channel_id)
+        .add_attribute("chain", chain.as_str()))
+}
+
+#[cfg_attr(feature = "export", cosmwasm_std::entry_point)]
+pub fn ibc_channel_close(
+    deps: DepsMut,
+    _env: Env,
+    msg: IbcChannelCloseMsg,
+) -> ServerResult<IbcBasicResponse> {
+    let channel_id = &msg.channel().endpoint.channel_id;
+    OPEN_CHANNELS.remove(deps.storage, channel_id);
+
+    // Chains that were reached over the channel fall back to the other transports
+    let chains = IBC_CHANNELS
+        .range(deps.storage, None, None, Order::Ascending)
+        .filter(|item| matches!(item, Ok((_, channel)) if channel == channel_id))
+        .map(|item| item.map(|(chain, _)| chain))
+        .collect::<StdResult<Vec<_>>>()?;
+    for chain in chains {
+        IBC_CHANNELS.remove(deps.storage, &chain);
+    }
+
+    Ok(IbcBasicResponse::new()
+        .add_attribute("action", "ibc_channel_close")
+        .add_attribute("channel_id", channel_id))
+}
+
+/// Route the messages of the packet in a sub-message that is acknowledged from its reply.
+/// Failed packets are reverted and acknowledged with an error so the sending server
+/// can bounce the messages instead of the relayer's transaction failing.
+#[cfg_attr(feature = "export", cosmwasm_std::entry_point)]
+pub fn ibc_packet_receive(
+    deps: DepsMut,
+    env: Env,
+    msg: IbcPacketReceiveMsg,
+) -> ServerResult<IbcReceiveResponse> {
+    let channel_id = msg.packet.dest.channel_id;
+    let response = match handle_packet_msg(deps.as_ref(), &env, &channel_id, msg.packet.data) {
+        Ok(handle_msg) => IbcReceiveResponse::new(to_json_binary(&IbcMailAck::Success {})?)
+            .add_submessage(SubMsg::reply_always(handle_msg, IBC_RECEIVE_REPLY_ID)),
+        Err(error) => {
+            IbcReceiveResponse::new(to_json_binary(&IbcMailAck::Error(error.to_string()))?)
+                .add_attribute("error", error.to_string())
+        }
+    };
+
+    Ok(response
+        .add_attribute("action", "ibc_packet_receive")
+        .add_attribute("channel_id", channel_id))
+}
+
+#[cfg_attr(feature = "export", cosmwasm_std::entry_point)]
+pub fn ibc_packet_ack(
+    deps: DepsMut,
+    env: Env,
+    msg: IbcPacketAckMsg,
+) -> ServerResult<IbcBasicResponse> {
+    let ack: IbcMailAck = from_json(&msg.acknowledgement.data)?;
+
+    let bounces = match ack {
+        IbcMailAck::Success {} => vec![],
+        IbcMailAck::Error(error) => bounce_packet(deps, &env, &msg.original_packet.data, &error)?,
+    };
+
+    Ok(IbcBasicResponse::new()
+        .add_attribute("action", "ibc_packet_ack")
+        .add_submessages(bounces))
+}
+
+#[cfg_attr(feature = "export", cosmwasm_std::entry_point)]
+pub fn ibc_packet_timeout(
+    deps: DepsMut,
+    env: Env,
+    msg: IbcPacketTimeoutMsg,
+) -> ServerResult<IbcBasicResponse> {
+    let bounces = bounce_packet(deps, &env, &msg.packet.data, "packet timed out")?;
+
+    Ok(IbcBasicResponse::new()
+        .add_attribute("action", "ibc_packet_timeout")
+        .add_submessages(bounces))
+}
+
+fn ensure_valid_channel(
+    channel: &IbcChannel,
+    counterparty_version: Option<&str>,
+) -> ServerResult<()> {
+    let versions = [Some(channel.version.as_str()), counterparty_version];
+    let invalid_version = versions
+        .into_iter()
+        .flatten()
+        .find(|version| *version != IBCMAIL_IBC_VERSION);
+
+    match (invalid_version, &channel.order) {
+        (None, IbcOrder::Unordered) => Ok(()),
+        (version, _) => Err(ServerError::InvalidChannel {
+            version: version.unwrap_or(&channel.version).to_string(),
+            expected: IBCMAIL_IBC_VERSION.to_string(),
+        }),
+    }
+}
+
+/// Chain of the trusted mail server on the other end of `channel`.
+fn counterparty_chain(
+    storage: &dyn Storage,
+    channel: &IbcChannel,
+) -> ServerResult<TruncatedChainId> {
+    let port_id = &channel.counterparty_endpoint.port_id;
+    TRUSTED_PORTS
+        .may_load(storage, port_id)?
+        .ok_or_else(|| ServerError::UntrustedPort(port_id.clone()))
+}
+
+/// Chain of the mail server that the open channel `channel_id` is registered for.
+pub(crate) fn channel_chain(
+    storage: &dyn Storage,
+    channel_id: &str,
+) -> ServerResult<TruncatedChainId> {
+    let chain = OPEN_CHANNELS
+        .may_load(storage, channel_id)?
+        .ok_or_else(|| ServerError::UnknownChannel(channel_id.to_string()))?;
+    ensure!(
+        IBC_CHANNELS.may_load(storage, chain.as_str())?.as_deref() == Some(channel_id),
+        ServerError::UnknownChannel(channel_id.to_string())
+    );
+
+    Ok(chain)
+}
+
+/// Message that makes the server handle a packet received on `channel_id` for the relay account.
+fn handle_packet_msg(
+    deps: Deps,
+    env: &Env,
+    channel_id: &str,
+    msg: Binary,
+) -> ServerResult<CosmosMsg> {
+    channel_chain(deps.storage, channel_id)?;
+    let relay_account = IBC_RELAY_ACCOUNT.load(deps.storage)?;
+
+    let request: adapter::ExecuteMsg<ServerExecuteMsg> =
+        adapter::ExecuteMsg::Module(AdapterRequestMsg {
+            account_address: Some(relay_account.to_string()),
+            request: ServerExecuteMsg::HandleIbcPacket {
+                channel_id: channel_id.to_string(),
+                msg,
+            },
+        });
+
+    Ok(wasm_execute(&env.contract.address, &request, vec![])?.into())
+}
+
+/// Notify the senders of the messages in a packet that could not be delivered.
+pub(crate) fn bounce_packet(
+    mut deps: DepsMut,
+    env: &Env,
+    packet_data: &Binary,
+    reason: &str,
+) -> ServerResult<Vec<SubMsg>> {
+    let msgs = match wire::decode(packet_data)?.msg {
+        ServerIbcMessage::RouteMessage { msg, header } => vec![(msg, header)],
+        ServerIbcMessage::RouteMessages { msgs } => msgs,
+        _ => vec![],
+    };
+
+    let mut app = ADAPTER;
+    let mut bounces = vec![];
+    for (msg, header) in msgs {
+        bounces.extend(bounce_msg(
+            deps.branch(),
+            env,
+            &mut app,
+            msg,
+            header,
+            reason,
+        )?);
+    }
+
+    Ok(bounces)
+}
+
+#[cfg(test)]
+mod tests {
+    use abstract_adapter::std::objects::{account::AccountTrace, AccountId};
+    use cosmwasm_std::{
+        testing::{mock_dependencies, mock_env, mock_ibc_channel, mock_ibc_channel_open_try},
+        IbcEndpoint, IbcPacket, IbcTimeout, Reply, ReplyOn, SubMsgResult, WasmMsg,
+    };
+    use ibcmail::{
+        server::state::{delivery_log, DeliveryStatus},
+        Header, IbcMailMessage, Message, Recipient, Sender, EMAIL_VERSION,
+    };
+
+    use super::*;
+    use crate::handlers::ibc_receive_reply_handler;
+
+    const CHANNEL: &str = "channel-1";
+
+    fn juno() -> TruncatedChainId {
+        "juno".parse().unwrap()
+    }
+
+    /// A message from the juno server, which can't be bounced to
+    fn packet_data() -> Binary {
+        let msg = ServerIbcMessage::RouteMessage {
+            msg: IbcMailMessage {
+                id: "test-id".to_string(),
+                sender: Sender::Server { chain: juno() },
+                message: Message::new(
+                    Recipient::account(AccountId::local(1), None),
+                    "test-subject",
+                    "test-body",
+                ),
+                timestamp: Default::default(),
+                version: EMAIL_VERSION.to_string(),
+            },
+            header: Header::new(AccountTrace::Remote(vec![juno(), "mock".parse().unwrap()])),
+        };
+        wire::encode(&msg, *wire::SUPPORTED_WIRE_VERSIONS.last().unwrap()).unwrap()
+    }
+
+    fn packet() -> IbcPacket {
+        IbcPacket::new(
+            packet_data(),
+            IbcEndpoint {
+                port_id: "their_port".to_string(),
+                channel_id: "channel-7".to_string(),
+            },
+            IbcEndpoint {
+                port_id: "my_port".to_string(),
+                channel_id: CHANNEL.to_string(),
+            },
+            1,
+            IbcTimeout::with_timestamp(mock_env().block.time.plus_seconds(60)),
+        )
+    }
+
+    #[test]
+    fn channels_are_only_opened_to_trusted_ports() {
+        let mut deps = mock_dependencies();
+        let channel = mock_ibc_channel(CHANNEL, IbcOrder::Unordered, IBCMAIL_IBC_VERSION);
+        let open = IbcChannelOpenMsg::OpenTry {
+            channel: channel.clone(),
+            counterparty_version: IBCMAIL_IBC_VERSION.to_string(),
+        };
+
+        let res = ibc_channel_open(deps.as_mut(), mock_env(), open.clone());
+        assert!(matches!(res, Err(ServerError::UntrustedPort(_))));
+
+        TRUSTED_PORTS
+            .save(
+                deps.as_mut().storage,
+                &channel.counterparty_endpoint.port_id,
+                &juno(),
+            )
+            .unwrap();
+        ibc_channel_open(deps.as_mut(), mock_env(), open).unwrap();
+
+        let ordered = mock_ibc_channel_open_try(CHANNEL, IbcOrder::Ordered, IBCMAIL_IBC_VERSION);
+        let res = ibc_channel_open(deps.as_mut(), mock_env(), ordered);
+        assert!(matches!(res, Err(ServerError::InvalidChannel { .. })));
+
+        let connect = IbcChannelConnectMsg::OpenAck {
+            channel,
+            counterparty_version: IBCMAIL_IBC_VERSION.to_string(),
+        };
+        ibc_channel_connect(deps.as_mut(), mock_env(), connect).unwrap();
+        assert_eq!(OPEN_CHANNELS.load(&deps.storage, CHANNEL).unwrap(), juno());
+    }
+
+    #[test]
+    fn packets_are_only_handled_on_registered_channels() {
+        let mut deps = mock_dependencies();
+        let relayer = deps.api.addr_make("relayer");
+        let receive = IbcPacketReceiveMsg::new(packet(), relayer);
+
+        // The channel is open but not registered for its chain
+        OPEN_CHANNELS
+            .save(deps.as_mut().storage, CHANNEL, &juno())
+            .unwrap();
+        let res = ibc_packet_receive(deps.as_mut(), mock_env(), receive.clone()).unwrap();
+        assert!(res.messages.is_empty());
+        let ack: IbcMailAck = from_json(res.acknowledgement.unwrap()).unwrap();
+        assert!(matches!(ack, IbcMailAck::Error(_)));
+
+        let relay_account = deps.api.addr_make("relay-account");
+        IBC_CHANNELS
+            .save(deps.as_mut().storage, juno().as_str(), &CHANNEL.to_string())
+            .unwrap();
+        IBC_RELAY_ACCOUNT
+            .save(deps.as_mut().storage, &relay_account)
+            .unwrap();
+        let res = ibc_packet_receive(deps.as_mut(), mock_env(), receive).unwrap();
+
+        // The packet is handled by the server itself so its reply can revert it
+        assert_eq!(res.messages.len(), 1);
+        let handle = &res.messages[0];
+        assert_eq!(handle.id, IBC_RECEIVE_REPLY_ID);
+        assert_eq!(handle.reply_on, ReplyOn::Always);
+        let CosmosMsg::Wasm(WasmMsg::Execute {
+            contract_addr, msg, ..
+        }) = &handle.msg
+        else {
+            panic!("expected the server to execute itself");
+        };
+        assert_eq!(contract_addr, mock_env().contract.address.as_str());
+        let request: adapter::ExecuteMsg<ServerExecuteMsg> = from_json(msg).unwrap();
+        assert_eq!(
+            request,
+            adapter::ExecuteMsg::Module(AdapterRequestMsg {
+                account_address: Some(relay_account.to_string()),
+                request: ServerExecuteMsg::HandleIbcPacket {
+                    channel_id: CHANNEL.to_string(),
+                    msg: packet_data(),
+                },
+            })
+        );
+    }
+
+    #[test]
+    fn failed_packets_are_acknowledged_with_error() {
+        let mut deps = mock_dependencies();
+        let reply = Reply {
+            id: IBC_RECEIVE_REPLY_ID,
+            payload: Binary::default(),
+            gas_used: 0,
+            result: SubMsgResult::Err("rate limit exceeded".to_string()),
+        };
+
+        let res = ibc_receive_reply_handler(deps.as_mut(), mock_env(), ADAPTER, reply).unwrap();
+        let ack: IbcMailAck = from_json(res.data.unwrap()).unwrap();
+        assert_eq!(ack, IbcMailAck::Error("rate limit exceeded".to_string()));
+    }
+
+    #[test]
+    fn timed_out_packets_are_bounced() {
+        let mut deps = mock_dependencies();
+        let relayer = deps.api.addr_make("relayer");
+        let timeout = IbcPacketTimeoutMsg::new(packet(), relayer);
+
+        ibc_packet_timeout(deps.as_mut(), mock_env(), timeout).unwrap();
+
+        // Servers can't be replied to, their undeliverable messages are only logged
+        let record = delivery_log().load(&deps.storage, 0).unwrap();
+        assert_eq!(record.message_id, "test-id");
+        assert_eq!(
+            record.status,
+            DeliveryStatus::Failed {
+                reason: "packet timed out".to_string()
+            }
+        );
+    }
+}
@@ -1,5 +1,15 @@
+mod admin;
 pub mod contract;
+mod dead_letter;
 mod handlers;
+mod held_mail;
+pub mod ibc;
+mod pause;
+mod rate_limit;
+mod receive;
+mod relay_fee;
+mod routing;
+mod stats;
 
 #[cfg(feature = "interface")]
 pub use contract::interface::ServerInterface;
@@ -0,0 +1,64 @@
+use abstract_adapter::objects::TruncatedChainId;
+use abstract_adapter::std::objects::account::AccountTrace;
+use cosmwasm_std::Storage;
+use ibcmail::{
+    server::state::{PauseMode, CHAIN_BREAKERS, PAUSED},
+    Header,
+};
+
+use crate::{contract::ServerResult, error::ServerError};
+
+/// Ensure that the mail with `header` may pass through this server at its current hop.
+/// Mail is inbound if it was received from another chain and outbound if it continues to another chain.
+/// Inbound mail is checked against the breakers of the chains it came from,
+/// outbound mail against the breakers of the chains it is routed to.
+pub(crate) fn ensure_not_paused(storage: &dyn Storage, header: &Header) -> ServerResult<()> {
+    let AccountTrace::Remote(chains) = &header.route else {
+        return ensure_running(storage, |mode| mode == &PauseMode::All);
+    };
+
+    let current_hop = (header.current_hop as usize).min(chains.len());
+    let (from, to) = (
+        &chains[..current_hop],
+        &chains[(current_hop + 1).min(chains.len())..],
+    );
+    let inbound = !from.is_empty();
+    let outbound = !to.is_empty();
+
+    ensure_running(storage, |mode| {
+        mode == &PauseMode::All
+            || (inbound && mode.stops_inbound())
+            || (outbound && mode.stops_outbound())
+    })?;
+
+    for chain in from {
+        ensure_chain_enabled(storage, chain, "from", PauseMode::stops_inbound)?;
+    }
+    for chain in to {
+        ensure_chain_enabled(storage, chain, "to", PauseMode::stops_outbound)?;
+    }
+
+    Ok(())
+}
+
+fn ensure_running(storage: &dyn Storage, stops: impl Fn(&PauseMode) -> bool) -> ServerResult<()> {
+    match PAUSED.may_load(storage)? {
+        Some(mode) if stops(&mode) => Err(ServerError::Paused(mode.to_string())),
+        _ => Ok(()),
+    }
+}
+
+fn ensure_chain_enabled(
+    storage: &dyn Storage,
+    chain: &TruncatedChainId,
+    direction: &str,
+    stops: impl Fn(&PauseMode) -> bool,
+) -> ServerResult<()> {
+    match CHAIN_BREAKERS.may_load(storage, chain.as_str())? {
+        Some(mode) if stops(&mode) => Err(ServerError::ChainDisabled {
+            chain: chain.clone(),
+            direction: direction.to_string(),
+        }),
+        _ => Ok(()),
+    }
+}
@@ -0,0 +1,115 @@
+use abstract_adapter::objects::TruncatedChainId;
+use abstract_adapter::std::objects::account::AccountTrace;
+use cosmwasm_std::{ensure, Env, Storage};
+use ibcmail::{
+    server::state::{sender_key, RateWindow, CHAIN_RATE_LIMITS, CONFIG, RATE_WINDOWS},
+    Route, Sender,
+};
+
+use crate::{contract::ServerResult, error::ServerError};
+
+/// Count a message of `sender` for mail originating from `origin_chain`.
+/// Errors once the sender has sent the allowed number of messages in the current window.
+/// Messages of servers, like bounces, are not limited.
+pub(crate) fn check_rate_limit(
+    storage: &mut dyn Storage,
+    env: &Env,
+    origin_chain: &TruncatedChainId,
+    sender: &Sender,
+) -> ServerResult<()> {
+    if let Some((key, window)) = counted_window(storage, env, origin_chain, sender)? {
+        RATE_WINDOWS.save(storage, (origin_chain.as_str(), &key), &window)?;
+    }
+
+    Ok(())
+}
+
+/// Ensure that a message of `sender` would be within the rate limit, without counting it.
+pub(crate) fn ensure_within_rate_limit(
+    storage: &dyn Storage,
+    env: &Env,
+    origin_chain: &TruncatedChainId,
+    sender: &Sender,
+) -> ServerResult<()> {
+    counted_window(storage, env, origin_chain, sender).map(|_| ())
+}
+
+/// The rate window of `sender` with one more message counted, if the sender is limited.
+fn counted_window(
+    storage: &dyn Storage,
+    env: &Env,
+    origin_chain: &TruncatedChainId,
+    sender: &Sender,
+) -> ServerResult<Option<(String, RateWindow)>> {
+    if let Sender::Server { .. } = sender {
+        return Ok(None);
+    }
+
+    let limit = match CHAIN_RATE_LIMITS.may_load(storage, origin_chain.as_str())? {
+        Some(limit) => limit,
+        None => match CONFIG.load(storage)?.rate_limit {
+            Some(limit) => limit,
+            None => return Ok(None),
+        },
+    };
+
+    let key = sender_key(sender)?;
+    let now = env.block.time;
+    let window = RATE_WINDOWS
+        .may_load(storage, (origin_chain.as_str(), &key))?
+        .filter(|window| now < window.start.plus_seconds(limit.window))
+        .unwrap_or(RateWindow {
+            start: now,
+            count: 0,
+        });
+
+    ensure!(
+        window.count < limit.max_messages,
+        ServerError::RateLimitExceeded {
+            sender: key,
+            max_messages: limit.max_messages,
+            window: limit.window,
+        }
+    );
+
+    Ok(Some((
+        key,
+        RateWindow {
+            count: window.count + 1,
+            ..window
+        },
+    )))
+}
+
+/// Chain that a message on `route` was sent from.
+pub(crate) fn origin_chain(env: &Env, route: &Route) -> TruncatedChainId {
+    match route {
+        AccountTrace::Remote(chains) if !chains.is_empty() => chains[0].clone(),
+        _ => TruncatedChainId::new(env),
+    }
+}
+
+/// Give back the count of a message of `sender` that was counted but could not be sent.
+pub(crate) fn release_rate_limit(
+    storage: &mut dyn Storage,
+    origin_chain: &TruncatedChainId,
+    sender: &Sender,
+) -> ServerResult<()> {
+    if let Sender::Server { .. } = sender {
+        return Ok(());
+    }
+
+    let key = sender_key(sender)?;
+    if let Some(window) = RATE_WINDOWS.may_load(storage, (origin_chain.as_str(), &key))? {
+        RATE_WINDOWS.save(
+            storage,
+            (origin_chain.as_str(), &key),
+            &RateWindow {
+                count: window.count.saturating_sub(1),
+                ..window
+            },
+        )?;
+    }
+
+    Ok(())
+}
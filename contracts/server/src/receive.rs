@@ -0,0 +1,61 @@
+use cosmwasm_std::{ensure_eq, Binary, DepsMut, Env, MessageInfo};
+use ibcmail::server::state::{TRUSTED_HOOK_SENDERS, TRUSTED_PROXIES};
+
+use crate::{
+    contract::{Adapter, ServerResult},
+    error::ServerError,
+    handlers::module_ibc::handle_server_msg,
+    ibc::channel_chain,
+};
+
+/// Handle a packet received on the server's own IBC port.
+/// The server calls itself from the packet receive entry point so a failed packet is reverted as a whole.
+pub(crate) fn handle_ibc_packet(
+    deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    app: Adapter,
+    channel_id: String,
+    msg: Binary,
+) -> ServerResult {
+    ensure_eq!(
+        info.sender,
+        env.contract.address,
+        ServerError::UnauthorizedIbcMessage
+    );
+    let chain = channel_chain(deps.storage, &channel_id)?;
+
+    handle_server_msg(deps, env, app, chain, msg, vec![])
+}
+
+/// Handle a message delivered by the Polytone proxy of another chain's server
+pub(crate) fn receive_polytone(
+    deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    app: Adapter,
+    msg: Binary,
+) -> ServerResult {
+    let chain = TRUSTED_PROXIES
+        .may_load(deps.storage, &info.sender)?
+        .ok_or_else(|| ServerError::UntrustedProxy(info.sender.to_string()))?;
+
+    handle_server_msg(deps, env, app, chain, msg, vec![])
+}
+
+/// Handle a message delivered by ibc-hooks for a transfer from another chain's server.
+/// The transferred tokens pay the relay fees of the messages that are forwarded,
+/// the rest is passed on to the relay account that the hook sender acts for.
+pub(crate) fn receive_ics20(
+    deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    app: Adapter,
+    msg: Binary,
+) -> ServerResult {
+    let chain = TRUSTED_HOOK_SENDERS
+        .may_load(deps.storage, &info.sender)?
+        .ok_or_else(|| ServerError::UntrustedHookSender(info.sender.to_string()))?;
+
+    handle_server_msg(deps, env, app, chain, msg, info.funds)
+}
@@ -0,0 +1,195 @@
+use abstract_adapter::objects::TruncatedChainId;
+use abstract_adapter::sdk::features::AccountIdentification;
+use abstract_adapter::std::objects::account::AccountTrace;
+use cosmwasm_std::{ensure, BankMsg, Coin, CosmosMsg, Deps, Env, StdResult};
+use ibcmail::{
+    server::{
+        state::{RemoteRelayFee, CONFIG, REMOTE_RELAY_FEES},
+        ServerAdapter,
+    },
+    Header, Route, Sender,
+};
+
+use crate::{contract::ServerResult, error::ServerError};
+
+/// Relay fee of the server that forwards a message along `route` first.
+/// Only its fee can be prepaid, as it is transferred with the message to the next hop.
+/// Forwarding servers without a known fee are not paid.
+pub(crate) fn route_relay_fees(
+    deps: Deps,
+    route: &Route,
+) -> ServerResult<Vec<(TruncatedChainId, RemoteRelayFee)>> {
+    let AccountTrace::Remote(chains) = route else {
+        return Ok(vec![]);
+    };
+
+    // The first chain sends the message and the last one delivers it
+    let Some(forwarding) = chains.get(1).filter(|_| chains.len() > 2) else {
+        return Ok(vec![]);
+    };
+    let fee = REMOTE_RELAY_FEES.may_load(deps.storage, forwarding.as_str())?;
+
+    Ok(fee
+        .map(|fee| (forwarding.clone(), fee))
+        .into_iter()
+        .collect())
+}
+
+/// Funds that go along with a message with `header` to `next_hop`, to pay the relay fee prepaid for it.
+/// Only the chain that sends the message transfers prepaid fees.
+pub(crate) fn relay_fee_funds(
+    deps: Deps,
+    header: &Header,
+    next_hop: &TruncatedChainId,
+) -> ServerResult<Vec<Coin>> {
+    if header.current_hop > 0 || header.relay_fee(next_hop).is_none() {
+        return Ok(vec![]);
+    }
+    let fee = REMOTE_RELAY_FEES.load(deps.storage, next_hop.as_str())?;
+
+    Ok(vec![relay_fee_payment(&fee)])
+}
+
+/// Funds that senders on this chain attach to prepay the remote relay `fee`.
+pub(crate) fn relay_fee_payment(fee: &RemoteRelayFee) -> Coin {
+    Coin {
+        denom: fee.denom.clone(),
+        amount: fee.fee.amount,
+    }
+}
+
+/// Record of the relay `fees` prepaid for a route, carried in the header of its messages.
+/// The forwarding servers check them against their own fee.
+pub(crate) fn prepaid_relay_fees(
+    fees: &[(TruncatedChainId, RemoteRelayFee)],
+) -> Vec<(TruncatedChainId, Coin)> {
+    fees.iter()
+        .map(|(chain, fee)| (chain.clone(), fee.fee.clone()))
+        .collect()
+}
+
+/// Sum of `fees` per denom.
+pub(crate) fn total_relay_fees<'a>(
+    fees: impl IntoIterator<Item = &'a Coin>,
+) -> StdResult<Vec<Coin>> {
+    let mut total: Vec<Coin> = vec![];
+    for fee in fees {
+        match total.iter_mut().find(|coin| coin.denom == fee.denom) {
+            Some(coin) => coin.amount = coin.amount.checked_add(fee.amount)?,
+            None => total.push(fee.clone()),
+        }
+    }
+
+    Ok(total)
+}
+
+fn format_coins(coins: &[Coin]) -> String {
+    coins
+        .iter()
+        .map(ToString::to_string)
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+/// Ensure that the funds sent along cover the `required` relay fees and ICS-20 transfers.
+pub(crate) fn ensure_relay_fees_paid(funds: &[Coin], required: &[Coin]) -> ServerResult<()> {
+    let paid = required.iter().all(|fee| {
+        funds
+            .iter()
+            .any(|coin| coin.denom == fee.denom && coin.amount >= fee.amount)
+    });
+
+    ensure!(
+        paid,
+        ServerError::InsufficientRelayFees {
+            required: format_coins(required),
+        }
+    );
+
+    Ok(())
+}
+
+/// Funds sent along in excess of the `spent` fees, which are summed per denom.
+pub(crate) fn excess_funds(funds: &[Coin], spent: &[Coin]) -> Vec<Coin> {
+    funds
+        .iter()
+        .filter_map(|coin| {
+            let spent = spent
+                .iter()
+                .find(|fee| fee.denom == coin.denom)
+                .map(|fee| fee.amount)
+                .unwrap_or_default();
+            let excess = coin.amount.saturating_sub(spent);
+            (!excess.is_zero()).then(|| Coin {
+                denom: coin.denom.clone(),
+                amount: excess,
+            })
+        })
+        .collect()
+}
+
+/// Take the relay fee of this server for forwarding the message with `header` from the `funds` that arrived with it.
+/// Messages that are delivered on this chain and the messages of servers, like bounces, are relayed for free.
+pub(crate) fn take_relay_fee(
+    deps: Deps,
+    env: &Env,
+    sender: &Sender,
+    header: &Header,
+    funds: &mut [Coin],
+) -> ServerResult<Option<Coin>> {
+    let Some(relay_fee) = CONFIG.load(deps.storage)?.relay_fee else {
+        return Ok(None);
+    };
+    if !header.is_forwarding() || matches!(sender, Sender::Server { .. }) {
+        return Ok(None);
+    }
+
+    let available = funds
+        .iter_mut()
+        .find(|coin| coin.denom == relay_fee.amount.denom && coin.amount >= relay_fee.amount.amount)
+        .ok_or_else(|| ServerError::RelayFeeNotPaid {
+            chain: TruncatedChainId::new(env),
+            required: relay_fee.amount.to_string(),
+        })?;
+    available.amount -= relay_fee.amount.amount;
+
+    Ok(Some(relay_fee.amount))
+}
+
+/// Pay the relay `fees` of the forwarded messages to the collector of this server
+/// and the rest of the `funds` that arrived with them to the account that the server handles them for.
+pub(crate) fn pay_out_funds(
+    deps: Deps,
+    app: &ServerAdapter,
+    funds: &[Coin],
+    fees: &[Coin],
+) -> ServerResult<Vec<CosmosMsg>> {
+    let fees = total_relay_fees(fees)?;
+    let rest = excess_funds(funds, &fees);
+
+    let mut msgs = vec![];
+    if let Some(relay_fee) = CONFIG
+        .load(deps.storage)?
+        .relay_fee
+        .filter(|_| !fees.is_empty())
+    {
+        msgs.push(
+            BankMsg::Send {
+                to_address: relay_fee.collector.to_string(),
+                amount: fees,
+            }
+            .into(),
+        );
+    }
+    if !rest.is_empty() {
+        msgs.push(
+            BankMsg::Send {
+                to_address: app.account(deps)?.into_addr().to_string(),
+                amount: rest,
+            }
+            .into(),
+        );
+    }
+
+    Ok(msgs)
+}
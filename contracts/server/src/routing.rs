@@ -0,0 +1,213 @@
+use std::{
+    cmp::Reverse,
+    collections::{BTreeMap, BinaryHeap},
+};
+
+use abstract_adapter::objects::TruncatedChainId;
+use abstract_adapter::std::objects::account::AccountTrace;
+use cosmwasm_std::{Coin, Deps, Env, SubMsg};
+use ibcmail::{
+    server::{
+        error::ServerError,
+        msg::ServerIbcMessage,
+        state::{
+            BLOCKED_LINKS, DEFAULT_LINK_COST, IBC_CHANNELS, ICS20_ROUTES, LINK_COSTS,
+            PEER_WIRE_VERSIONS, POLYTONE_ROUTES, PREFERRED_HOPS, ROUTE_TABLE,
+        },
+        transport::{AbstractIbc, IbcPort, Ics20, Polytone, Transport},
+        wire::{self, CURRENT_WIRE_VERSION},
+        ServerAdapter,
+    },
+    Header, Route,
+};
+
+use crate::contract::ServerResult;
+
+/// Transport that reaches the server on `dest_chain`.
+/// Polytone routes take precedence over the server's own IBC channels and those over ICS-20 routes,
+/// chains without any of them are reached over Abstract IBC.
+pub(crate) fn transport_to<'a>(
+    deps: Deps,
+    app: &'a ServerAdapter,
+    dest_chain: &TruncatedChainId,
+) -> Box<dyn Transport + 'a> {
+    let chain = dest_chain.as_str();
+    if POLYTONE_ROUTES.has(deps.storage, chain) {
+        Box::new(Polytone)
+    } else if IBC_CHANNELS.has(deps.storage, chain) {
+        Box::new(IbcPort)
+    } else if ICS20_ROUTES.has(deps.storage, chain) {
+        Box::new(Ics20)
+    } else {
+        Box::new(AbstractIbc { app })
+    }
+}
+
+/// Chains whose mail server can be reached directly over any of the transports.
+pub(crate) fn remote_hosts(deps: Deps, app: &ServerAdapter) -> ServerResult<Vec<TruncatedChainId>> {
+    let mut hosts = AbstractIbc { app }.remote_hosts(deps)?;
+    let other_hosts = [
+        Polytone.remote_hosts(deps)?,
+        IbcPort.remote_hosts(deps)?,
+        Ics20.remote_hosts(deps)?,
+    ];
+    for chain in other_hosts.into_iter().flatten() {
+        if !hosts.contains(&chain) {
+            hosts.push(chain);
+        }
+    }
+
+    Ok(hosts)
+}
+
+/// Send `msg` to the server on `dest_chain` with `funds`, in the wire version negotiated with it.
+pub(crate) fn send_to_server(
+    deps: Deps,
+    env: &Env,
+    app: &ServerAdapter,
+    dest_chain: TruncatedChainId,
+    msg: &ServerIbcMessage,
+    funds: Vec<Coin>,
+) -> ServerResult<SubMsg> {
+    let msg = wire::encode(msg, peer_wire_version(deps, &dest_chain)?)?;
+
+    transport_to(deps, app, &dest_chain).send(deps, env, dest_chain, msg, funds)
+}
+
+/// Wire version to send in to the server on `chain`.
+/// Servers are sent the current version until they are known to be older,
+/// from the legacy messages they send or because the admin marked them.
+pub(crate) fn peer_wire_version(deps: Deps, chain: &TruncatedChainId) -> ServerResult<u32> {
+    Ok(PEER_WIRE_VERSIONS
+        .may_load(deps.storage, chain.as_str())?
+        .unwrap_or(CURRENT_WIRE_VERSION))
+}
+
+/// Tokens transferred with a message that leaves this chain, with the chain it goes to.
+/// Senders on this chain pay them with their mail.
+pub(crate) fn transfer_funds(
+    deps: Deps,
+    app: &ServerAdapter,
+    header: &Header,
+) -> ServerResult<Option<(TruncatedChainId, Coin)>> {
+    let AccountTrace::Remote(chains) = &header.route else {
+        return Ok(None);
+    };
+    let Some(next_hop) = chains.get(header.current_hop as usize + 1) else {
+        return Ok(None);
+    };
+
+    Ok(transport_to(deps, app, next_hop)
+        .transfer_funds(deps, next_hop)?
+        .map(|funds| (next_hop.clone(), funds)))
+}
+
+/// Find the cheapest route from the current chain to `dest_chain`.
+/// A preferred next hop towards the destination is used when it is reachable,
+/// blocked links are never used and links without a set cost count as [`DEFAULT_LINK_COST`].
+pub(crate) fn find_route(
+    deps: Deps,
+    app: &ServerAdapter,
+    current_chain: TruncatedChainId,
+    dest_chain: &TruncatedChainId,
+) -> ServerResult<Route> {
+    let graph = RouteGraph {
+        deps,
+        current_chain: current_chain.to_string(),
+        direct_hosts: remote_hosts(deps, app)?,
+    };
+
+    if let Some(next_hop) = PREFERRED_HOPS.may_load(deps.storage, dest_chain.as_str())? {
+        let next_hops = graph.neighbours(current_chain.as_str())?;
+        if next_hops
+            .iter()
+            .any(|(chain, _)| chain == next_hop.as_str())
+        {
+            let path = graph.cheapest_path(next_hop.as_str(), dest_chain.as_str())?;
+            if let Some(path) = path.filter(|path| !path.contains(&current_chain)) {
+                let mut chains = vec![current_chain];
+                chains.extend(path);
+                return Ok(AccountTrace::Remote(chains));
+            }
+        }
+    }
+
+    graph
+        .cheapest_path(current_chain.as_str(), dest_chain.as_str())?
+        .map(AccountTrace::Remote)
+        .ok_or(ServerError::NoRoute(dest_chain.clone()))
+}
+
+/// Whether the admin blocked the link from `from` to `to`.
+pub(crate) fn is_blocked(deps: Deps, from: &str, to: &str) -> bool {
+    BLOCKED_LINKS.has(deps.storage, (from, to))
+}
+
+/// Chains connected by the IBC client and the route table.
+struct RouteGraph<'a> {
+    deps: Deps<'a>,
+    current_chain: String,
+    direct_hosts: Vec<TruncatedChainId>,
+}
+
+impl<'a> RouteGraph<'a> {
+    /// Unblocked links from `chain` with their cost.
+    fn neighbours(&self, chain: &str) -> ServerResult<Vec<(String, u64)>> {
+        let hosts = if chain == self.current_chain {
+            self.direct_hosts.clone()
+        } else {
+            ROUTE_TABLE
+                .may_load(self.deps.storage, chain)?
+                .unwrap_or_default()
+        };
+
+        hosts
+            .into_iter()
+            .filter(|host| !is_blocked(self.deps, chain, host.as_str()))
+            .map(|host| {
+                let cost = LINK_COSTS
+                    .may_load(self.deps.storage, (chain, host.as_str()))?
+                    .unwrap_or(DEFAULT_LINK_COST);
+                Ok((host.to_string(), cost))
+            })
+            .collect()
+    }
+
+    /// Dijkstra's shortest path from `from` to `to`, including both ends.
+    fn cheapest_path(&self, from: &str, to: &str) -> ServerResult<Option<Vec<TruncatedChainId>>> {
+        let mut costs: BTreeMap<String, u64> = BTreeMap::new();
+        let mut previous: BTreeMap<String, String> = BTreeMap::new();
+        let mut queue = BinaryHeap::new();
+        costs.insert(from.to_string(), 0);
+        queue.push(Reverse((0u64, from.to_string())));
+
+        while let Some(Reverse((cost, chain))) = queue.pop() {
+            if chain == to {
+                let mut path = vec![chain];
+                while let Some(hop) = path.last().and_then(|last| previous.get(last)).cloned() {
+                    path.push(hop);
+                }
+                path.reverse();
+                return path
+                    .into_iter()
+                    .map(|chain| Ok(TruncatedChainId::from_string(chain)?))
+                    .collect::<ServerResult<_>>()
+                    .map(Some);
+            }
+            if costs.get(&chain).is_some_and(|best| cost > *best) {
+                continue;
+            }
+
+            for (host, link_cost) in self.neighbours(&chain)? {
+                let host_cost = cost.saturating_add(link_cost);
+                if costs.get(&host).map_or(true, |best| host_cost < *best) {
+                    costs.insert(host.clone(), host_cost);
+                    previous.insert(host.clone(), chain.clone());
+                    queue.push(Reverse((host_cost, host)));
+                }
+            }
+        }
+
+        Ok(None)
+    }
+}
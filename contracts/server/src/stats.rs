@@ -0,0 +1,61 @@
+use abstract_adapter::objects::TruncatedChainId;
+use abstract_adapter::std::objects::account::AccountTrace;
+use cosmwasm_std::{Env, StdResult, Storage};
+use ibcmail::{
+    server::state::{MessageStats, CHAIN_STATS, TOTAL_STATS},
+    Route,
+};
+
+use crate::contract::ServerResult;
+
+/// Statistic counted by the server.
+pub(crate) enum Counter {
+    Originated,
+    Forwarded,
+    Delivered,
+    Bounced,
+}
+
+impl Counter {
+    fn increment(&self, stats: &mut MessageStats) {
+        let count = match self {
+            Counter::Originated => &mut stats.originated,
+            Counter::Forwarded => &mut stats.forwarded,
+            Counter::Delivered => &mut stats.delivered,
+            Counter::Bounced => &mut stats.bounced,
+        };
+        *count += 1;
+    }
+}
+
+/// Count a message on `route` for its source and destination chain and in the totals.
+pub(crate) fn count_message(
+    storage: &mut dyn Storage,
+    env: &Env,
+    route: &Route,
+    counter: Counter,
+) -> ServerResult<()> {
+    let current_chain = TruncatedChainId::new(env);
+    let (source, destination) = match route {
+        AccountTrace::Remote(chains) if !chains.is_empty() => {
+            (&chains[0], &chains[chains.len() - 1])
+        }
+        _ => (&current_chain, &current_chain),
+    };
+
+    CHAIN_STATS.update(
+        storage,
+        (source.as_str(), destination.as_str()),
+        |stats| -> StdResult<_> {
+            let mut stats = stats.unwrap_or_default();
+            counter.increment(&mut stats);
+            Ok(stats)
+        },
+    )?;
+
+    let mut total = TOTAL_STATS.may_load(storage)?.unwrap_or_default();
+    counter.increment(&mut total);
+    TOTAL_STATS.save(storage, &total)?;
+
+    Ok(())
+}
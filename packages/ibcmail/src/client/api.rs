@@ -2,7 +2,7 @@ use abstract_adapter::{sdk::AbstractSdkResult, std::objects::module::ModuleId};
 
 use abstract_app::sdk::AppInterface;
 use abstract_app::std::app;
-use cosmwasm_std::{wasm_execute, CosmosMsg, Deps};
+use cosmwasm_std::{wasm_execute, Coin, CosmosMsg, Deps};
 
 use crate::{
     client::msg::ClientExecuteMsg, Header, IbcMailMessage, Message, Route, IBCMAIL_CLIENT_ID,
@@ -37,12 +37,21 @@ impl<'a, T: ClientInterface> MailClient<'a, T> {
 
     // Execute a request on the ibc mail client
     fn request(&self, msg: ClientExecuteMsg) -> AbstractSdkResult<CosmosMsg> {
+        self.request_with_funds(msg, vec![])
+    }
+
+    // Execute a request on the ibc mail client with `funds` attached
+    fn request_with_funds(
+        &self,
+        msg: ClientExecuteMsg,
+        funds: Vec<Coin>,
+    ) -> AbstractSdkResult<CosmosMsg> {
         let app_msg: app::ExecuteMsg<_> = msg.into();
 
         let modules = self.base.modules(self.deps);
         let app_address = modules.module_address(self.module_id())?;
 
-        Ok(wasm_execute(app_address, &app_msg, vec![])?.into())
+        Ok(wasm_execute(app_address, &app_msg, funds)?.into())
     }
 
     /// Send message
@@ -50,12 +59,25 @@ impl<'a, T: ClientInterface> MailClient<'a, T> {
         self.request(ClientExecuteMsg::SendMessage { message, route })
     }
 
+    /// Send message, prepaying the relay fees of its route with `funds`
+    pub fn send_msg_with_funds(
+        &self,
+        message: Message,
+        route: Option<Route>,
+        funds: Vec<Coin>,
+    ) -> AbstractSdkResult<CosmosMsg> {
+        self.request_with_funds(ClientExecuteMsg::SendMessage { message, route }, funds)
+    }
+
     /// Receive message
     pub fn receive_msg(
         &self,
         message: IbcMailMessage,
-        _header: Header,
+        header: Header,
     ) -> AbstractSdkResult<CosmosMsg> {
-        self.request(ClientExecuteMsg::ReceiveMessage(message))
+        self.request(ClientExecuteMsg::ReceiveMessage {
+            msg: message,
+            header,
+        })
     }
 }
@@ -30,6 +30,9 @@ pub enum ClientError {
     #[error("Recipient is not the current account")]
     NotRecipient {},
 
+    #[error("{0} is not allowed to send mail through this client")]
+    Unauthorized(String),
+
     #[error("{0} is not implemented")]
     NotImplemented(String),
 }
@@ -1,7 +1,8 @@
 use cosmwasm_schema::QueryResponses;
 
 use crate::{
-    client::ClientApp, IbcMailMessage, Message, MessageHash, MessageStatus, Route, Sender,
+    client::ClientApp, Header, HopTrace, IbcMailMessage, Message, MessageHash, MessageStatus,
+    Route, Sender,
 };
 
 // This is used for type safety and re-exporting the contract endpoint structs.
@@ -17,13 +18,20 @@ pub struct ClientInstantiateMsg {}
 #[derive(cw_orch::ExecuteFns)]
 // #[cw_orch(impl_into(ExecuteMsg))]
 pub enum ClientExecuteMsg {
-    /// Receive a message from the server
-    ReceiveMessage(IbcMailMessage),
-    /// Send a message
+    /// Receive a message from the server.
+    /// Shares its format with [`crate::receiver::MailReceiverExecuteMsg::ReceiveMessage`].
+    ReceiveMessage { msg: IbcMailMessage, header: Header },
+    /// Send a message, the funds sent along prepay the relay fees of its route
     SendMessage {
         message: Message,
         route: Option<Route>,
     },
+    /// Send a batch of messages, grouped by the server into one IBC packet per next hop.
+    /// The funds sent along prepay the relay fees of their routes.
+    SendMessages(Vec<Message>),
+    /// Pull mail held by the server into this client.
+    /// Defaults to the mail held for the account itself.
+    ClaimMail { address: Option<String> },
 }
 // # ANCHOR_END: execute_msg
 
@@ -33,6 +41,7 @@ pub enum ClientExecuteMsg {
 // #[cw_orch(impl_into(QueryMsg))]
 #[derive(QueryResponses)]
 pub enum ClientQueryMsg {
+    /// List a page of messages, the filter applies to the messages of the page.
     #[returns(MessagesResponse)]
     ListMessages {
         status: MessageStatus,
@@ -45,11 +54,37 @@ pub enum ClientQueryMsg {
         status: MessageStatus,
         ids: Vec<MessageHash>,
     },
+    /// Servers that handled a received message, in order.
+    #[returns(TraceResponse)]
+    Trace { id: MessageHash },
+    /// Route back to the sender of a received message.
+    #[returns(ReturnRouteResponse)]
+    ReturnRoute { id: MessageHash },
 }
 
 #[cosmwasm_schema::cw_serde]
+#[derive(Default)]
 pub struct MessageFilter {
     pub from: Option<Sender>,
+    /// Only return messages sent by modules (`true`) or by accounts (`false`).
+    pub from_module: Option<bool>,
+}
+
+impl MessageFilter {
+    /// Whether the message passes the filter.
+    pub fn matches(&self, msg: &IbcMailMessage) -> bool {
+        if let Some(from) = &self.from {
+            if from != &msg.sender {
+                return false;
+            }
+        }
+        if let Some(from_module) = self.from_module {
+            if from_module != msg.sender.is_module() {
+                return false;
+            }
+        }
+        true
+    }
 }
 
 #[cosmwasm_schema::cw_serde]
@@ -62,3 +97,13 @@ pub struct ConfigResponse {}
 pub struct MessagesResponse {
     pub messages: Vec<IbcMailMessage>,
 }
+
+#[cosmwasm_schema::cw_serde]
+pub struct TraceResponse {
+    pub trace: Vec<HopTrace>,
+}
+
+#[cosmwasm_schema::cw_serde]
+pub struct ReturnRouteResponse {
+    pub route: Route,
+}
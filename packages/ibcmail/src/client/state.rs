@@ -1,7 +1,20 @@
 use cw_storage_plus::Map;
 
-use crate::{IbcMailMessage, MessageHash};
+use cosmwasm_std::{to_json_string, StdResult};
+
+use crate::{HopTrace, IbcMailMessage, MessageHash, Recipient, Route};
 
 // TODO: use an indexed map in the future
 pub const RECEIVED: Map<MessageHash, IbcMailMessage> = Map::new("received");
 pub const SENT: Map<MessageHash, IbcMailMessage> = Map::new("sent");
+/// Trace of the servers that handled each received message.
+pub const RECEIVED_TRACES: Map<MessageHash, Vec<HopTrace>> = Map::new("received_traces");
+/// Route back to the sender of each received message.
+pub const RETURN_ROUTES: Map<MessageHash, Route> = Map::new("return_routes");
+/// Route of the latest message received from a sender, keyed by [`reply_key`] of the sender's reply recipient.
+pub const REPLY_ROUTES: Map<String, Route> = Map::new("reply_routes");
+
+/// Key of the reply route for `recipient` in [`REPLY_ROUTES`].
+pub fn reply_key(recipient: &Recipient) -> StdResult<String> {
+    to_json_string(recipient)
+}
@@ -1,11 +1,13 @@
 pub mod client;
+pub mod receiver;
 pub mod server;
 
 use abstract_app::objects::TruncatedChainId;
+use abstract_app::std::objects::ownership::nested_admin::query_top_level_owner_addr;
 use abstract_app::std::objects::AccountId;
 use abstract_app::std::objects::{account::AccountTrace, namespace::Namespace};
 use const_format::concatcp;
-use cosmwasm_std::Timestamp;
+use cosmwasm_std::{Addr, Coin, QuerierWrapper, StdResult, Timestamp};
 
 pub const IBCMAIL_NAMESPACE: &str = "ibcmail";
 pub const IBCMAIL_CLIENT_ID: &str = concatcp!(IBCMAIL_NAMESPACE, ":", "client");
@@ -39,15 +41,132 @@ impl Message {
 pub struct IbcMailMessage {
     pub id: MessageHash,
     pub sender: Sender,
+    /// [`EMAIL_VERSION`] of the message format the message was created in
     pub version: String,
     pub timestamp: Timestamp,
     pub message: Message,
 }
 
+impl IbcMailMessage {
+    /// Whether the message format is compatible with [`EMAIL_VERSION`].
+    /// That is the case for every release of the same major version,
+    /// the wire format carries the shape of messages from older servers.
+    pub fn is_supported_version(&self) -> bool {
+        match (
+            semver::Version::parse(&self.version),
+            semver::Version::parse(EMAIL_VERSION),
+        ) {
+            (Ok(version), Ok(supported)) => version.major == supported.major,
+            _ => false,
+        }
+    }
+}
+
 #[cosmwasm_schema::cw_serde]
 pub struct Header {
     pub current_hop: u32,
     pub route: Route,
+    /// Maximum number of hops the message may take, unlimited if not set.
+    #[serde(default)]
+    pub max_hops: Option<u32>,
+    /// Time after which servers stop forwarding the message and bounce it instead.
+    #[serde(default)]
+    pub expires_at: Option<Timestamp>,
+    /// Stamps of the servers that handled the message, in order.
+    #[serde(default)]
+    pub trace: Vec<HopTrace>,
+    /// Relay fees that the sender prepaid for the servers that forward the message, by chain.
+    /// Only a record, forwarding servers pay out the fees that arrived with the message.
+    #[serde(default)]
+    pub relay_fees: Vec<(TruncatedChainId, Coin)>,
+}
+
+/// Stamp left by a server that handled a message.
+#[cosmwasm_schema::cw_serde]
+pub struct HopTrace {
+    pub chain: TruncatedChainId,
+    pub height: u64,
+    pub time: Timestamp,
+}
+
+impl Header {
+    /// Header for a message that starts on the current chain without hop limit or expiry.
+    pub fn new(route: Route) -> Self {
+        Self {
+            current_hop: 0,
+            route,
+            max_hops: None,
+            expires_at: None,
+            trace: vec![],
+            relay_fees: vec![],
+        }
+    }
+
+    /// The first chain that the route visits more than once, if any.
+    pub fn repeated_chain(&self) -> Option<&TruncatedChainId> {
+        let AccountTrace::Remote(chains) = &self.route else {
+            return None;
+        };
+        chains
+            .iter()
+            .enumerate()
+            .find(|(i, chain)| chains[..*i].contains(chain))
+            .map(|(_, chain)| chain)
+    }
+
+    /// Number of hops of the full route.
+    pub fn hop_count(&self) -> u32 {
+        match &self.route {
+            AccountTrace::Local => 0,
+            AccountTrace::Remote(chains) => chains.len().saturating_sub(1) as u32,
+        }
+    }
+
+    /// Whether the route or the hops taken so far exceed the hop limit.
+    pub fn exceeds_max_hops(&self) -> bool {
+        self.max_hops
+            .is_some_and(|max_hops| self.current_hop > max_hops || self.hop_count() > max_hops)
+    }
+
+    /// Whether the current hop forwards the message to another chain instead of delivering it.
+    pub fn is_forwarding(&self) -> bool {
+        match &self.route {
+            AccountTrace::Local => false,
+            AccountTrace::Remote(chains) => (self.current_hop as usize) + 1 < chains.len(),
+        }
+    }
+
+    /// Relay fee prepaid to the server on `chain`, if any.
+    pub fn relay_fee(&self, chain: &TruncatedChainId) -> Option<&Coin> {
+        self.relay_fees
+            .iter()
+            .find(|(fee_chain, _)| fee_chain == chain)
+            .map(|(_, fee)| fee)
+    }
+
+    pub fn is_expired(&self, now: Timestamp) -> bool {
+        self.expires_at.is_some_and(|expires_at| now > expires_at)
+    }
+
+    /// The route from the current hop back to the chain the message originated from.
+    pub fn return_route(&self) -> Route {
+        match &self.route {
+            AccountTrace::Local => AccountTrace::Local,
+            AccountTrace::Remote(chains) => {
+                let chains: Vec<TruncatedChainId> = chains
+                    .iter()
+                    .take(self.current_hop as usize + 1)
+                    .rev()
+                    .cloned()
+                    .collect();
+                if chains.len() <= 1 {
+                    AccountTrace::Local
+                } else {
+                    AccountTrace::Remote(chains)
+                }
+            }
+        }
+    }
 }
 
 pub type Route = AccountTrace;
@@ -63,6 +182,17 @@ pub enum Recipient {
         namespace: Namespace,
         chain: Option<TruncatedChainId>,
     },
+    /// A module installed on `account` that implements the [`receiver`] interface.
+    Module {
+        account: AccountId,
+        module_id: String,
+        chain: Option<TruncatedChainId>,
+    },
+    /// A plain address, delivered to the account at that address or held until its owner claims it.
+    Address {
+        address: String,
+        chain: Option<TruncatedChainId>,
+    },
 }
 
 impl From<AccountId> for Recipient {
@@ -84,6 +214,34 @@ impl Recipient {
     pub fn namespace(namespace: Namespace, chain: Option<TruncatedChainId>) -> Self {
         Recipient::Namespace { namespace, chain }
     }
+    pub fn module(
+        account_id: AccountId,
+        module_id: impl Into<String>,
+        chain: Option<TruncatedChainId>,
+    ) -> Self {
+        Recipient::Module {
+            account: account_id,
+            module_id: module_id.into(),
+            chain,
+        }
+    }
+
+    pub fn address(address: impl Into<String>, chain: Option<TruncatedChainId>) -> Self {
+        Recipient::Address {
+            address: address.into(),
+            chain,
+        }
+    }
+
+    /// The chain of the recipient, `None` if it is on the sender's chain.
+    pub fn chain(&self) -> Option<&TruncatedChainId> {
+        match self {
+            Recipient::Account { chain, .. }
+            | Recipient::Namespace { chain, .. }
+            | Recipient::Module { chain, .. }
+            | Recipient::Address { chain, .. } => chain.as_ref(),
+        }
+    }
 }
 
 #[non_exhaustive]
@@ -93,6 +251,19 @@ pub enum Sender {
         id: AccountId,
         chain: Option<TruncatedChainId>,
     },
+    /// A module installed on `account` that sent the message programmatically.
+    Module {
+        account: AccountId,
+        module_id: String,
+        chain: Option<TruncatedChainId>,
+    },
+    /// A wallet or contract that is not an Abstract account.
+    Address {
+        address: String,
+        chain: Option<TruncatedChainId>,
+    },
+    /// The mail server itself, used for delivery notifications such as bounces.
+    Server { chain: TruncatedChainId },
 }
 
 impl Sender {
@@ -102,6 +273,62 @@ impl Sender {
             chain,
         }
     }
+    pub fn module(
+        account_id: AccountId,
+        module_id: impl Into<String>,
+        chain: Option<TruncatedChainId>,
+    ) -> Self {
+        Sender::Module {
+            account: account_id,
+            module_id: module_id.into(),
+            chain,
+        }
+    }
+
+    pub fn address(address: impl Into<String>, chain: Option<TruncatedChainId>) -> Self {
+        Sender::Address {
+            address: address.into(),
+            chain,
+        }
+    }
+
+    /// The sender with its chain filled in as `chain` if it was left out.
+    /// Senders without a chain are on the chain the message was sent from.
+    pub fn with_chain(&self, chain: &TruncatedChainId) -> Self {
+        let fill = |sender_chain: &Option<TruncatedChainId>| {
+            Some(sender_chain.clone().unwrap_or_else(|| chain.clone()))
+        };
+        match self {
+            Sender::Account { id, chain } => Sender::account(id.clone(), fill(chain)),
+            Sender::Module {
+                account,
+                module_id,
+                chain,
+            } => Sender::module(account.clone(), module_id.clone(), fill(chain)),
+            Sender::Address { address, chain } => Sender::address(address.clone(), fill(chain)),
+            Sender::Server { .. } => self.clone(),
+        }
+    }
+
+    /// Whether the message was sent by a module rather than by the account itself.
+    pub fn is_module(&self) -> bool {
+        matches!(self, Sender::Module { .. })
+    }
+
+    /// The recipient to address when replying to this sender.
+    /// Modules are replied to through the mail client of their account, servers can't be replied to.
+    pub fn reply_recipient(&self) -> Option<Recipient> {
+        match self {
+            Sender::Account { id, chain }
+            | Sender::Module {
+                account: id, chain, ..
+            } => Some(Recipient::account(id.clone(), chain.clone())),
+            Sender::Address { address, chain } => {
+                Some(Recipient::address(address.clone(), chain.clone()))
+            }
+            Sender::Server { .. } => None,
+        }
+    }
 }
 
 #[non_exhaustive]
@@ -110,3 +337,17 @@ pub enum MessageStatus {
     Sent,
     Received,
 }
+
+/// Returns whether mail for `address` may be delivered to `account`.
+/// That is the case when `address` is the account itself or the account's top-level owner.
+pub fn is_account_address(
+    querier: &QuerierWrapper,
+    account: &Addr,
+    address: &Addr,
+) -> StdResult<bool> {
+    if account == address {
+        return Ok(true);
+    }
+    let owner = query_top_level_owner_addr(querier, account.clone())?;
+    Ok(&owner == address)
+}
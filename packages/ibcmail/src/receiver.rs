@@ -0,0 +1,63 @@
+use abstract_app::sdk::{AbstractSdkResult, AppInterface, ModuleRegistryInterface};
+use abstract_app::std::{app, objects::module::ModuleId};
+use cosmwasm_std::{wasm_execute, Addr, CosmosMsg, Deps};
+
+use crate::{Header, IbcMailMessage, IBCMAIL_SERVER_ID};
+
+/// Execute message that an app must accept to receive mail from the server.
+/// Add the variant to the app's own execute message to become a mail receiver,
+/// the ibcmail client implements it through [`crate::client::msg::ClientExecuteMsg::ReceiveMessage`].
+#[cosmwasm_schema::cw_serde]
+pub enum MailReceiverExecuteMsg {
+    /// Receive a message from the server
+    ReceiveMessage { msg: IbcMailMessage, header: Header },
+}
+
+/// Returns whether `sender` is the ibcmail server.
+/// Receivers should only accept [`MailReceiverExecuteMsg::ReceiveMessage`] from the server.
+pub fn is_mail_server<T: ModuleRegistryInterface>(base: &T, deps: Deps, sender: Addr) -> bool {
+    base.module_registry(deps)
+        .and_then(|registry| registry.module_info(sender))
+        .is_ok_and(|module| module.info.id() == IBCMAIL_SERVER_ID)
+}
+
+// API for Abstract SDK users
+pub trait MailReceiverInterface: AppInterface {
+    /// Construct a new interface to the mail receiver module `module_id`.
+    fn mail_receiver<'a>(&'a self, deps: Deps<'a>, module_id: ModuleId<'a>) -> MailReceiver<Self> {
+        MailReceiver {
+            base: self,
+            deps,
+            module_id,
+        }
+    }
+}
+
+impl<T: AppInterface> MailReceiverInterface for T {}
+
+#[derive(Clone)]
+pub struct MailReceiver<'a, T: MailReceiverInterface> {
+    pub base: &'a T,
+    pub module_id: ModuleId<'a>,
+    pub deps: Deps<'a>,
+}
+
+impl<'a, T: MailReceiverInterface> MailReceiver<'a, T> {
+    /// Deliver a message to the receiver module
+    pub fn receive_msg(
+        &self,
+        message: IbcMailMessage,
+        header: Header,
+    ) -> AbstractSdkResult<CosmosMsg> {
+        let receiver_msg: app::ExecuteMsg<MailReceiverExecuteMsg> =
+            app::ExecuteMsg::Module(MailReceiverExecuteMsg::ReceiveMessage {
+                msg: message,
+                header,
+            });
+
+        let modules = self.base.modules(self.deps);
+        let receiver_address = modules.module_address(self.module_id)?;
+
+        Ok(wasm_execute(receiver_address, &receiver_msg, vec![])?.into())
+    }
+}
@@ -1,15 +1,15 @@
 use abstract_adapter::{
     sdk::{
         features::{AccountIdentification, Dependencies, ModuleIdentification},
-        AbstractSdkResult, AdapterInterface,
+        AbstractSdkResult, AdapterInterface, ModuleInterface,
     },
-    std::objects::module::ModuleId,
+    std::{adapter, objects::module::ModuleId},
 };
 use cosmwasm_schema::serde::de::DeserializeOwned;
-use cosmwasm_std::{CosmosMsg, Deps, Uint128};
+use cosmwasm_std::{wasm_execute, Coin, CosmosMsg, Deps};
 
 use crate::{
-    server::msg::{ServerExecuteMsg, ServerQueryMsg},
+    server::msg::{ConfigResponse, ServerExecuteMsg, ServerQueryMsg},
     IbcMailMessage, Route, IBCMAIL_SERVER_ID,
 };
 
@@ -47,6 +47,21 @@ impl<'a, T: ServerInterface> MailServer<'a, T> {
         adapters.execute(self.module_id(), msg)
     }
 
+    /// Request with `funds` attached, which are sent from this module.
+    fn request_with_funds(
+        &self,
+        msg: ServerExecuteMsg,
+        funds: Vec<Coin>,
+    ) -> AbstractSdkResult<CosmosMsg> {
+        let adapter_address = self
+            .base
+            .modules(self.deps)
+            .module_address(self.module_id())?;
+        let adapter_msg: adapter::ExecuteMsg<ServerExecuteMsg> = msg.into();
+
+        Ok(wasm_execute(adapter_address, &adapter_msg, funds)?.into())
+    }
+
     pub fn process_msg(
         &self,
         msg: IbcMailMessage,
@@ -54,6 +69,32 @@ impl<'a, T: ServerInterface> MailServer<'a, T> {
     ) -> AbstractSdkResult<CosmosMsg> {
         self.request(ServerExecuteMsg::ProcessMessage { msg, route })
     }
+
+    /// Route a message, prepaying the relay fees of its route with `funds`
+    pub fn process_msg_with_funds(
+        &self,
+        msg: IbcMailMessage,
+        route: Option<Route>,
+        funds: Vec<Coin>,
+    ) -> AbstractSdkResult<CosmosMsg> {
+        self.request_with_funds(ServerExecuteMsg::ProcessMessage { msg, route }, funds)
+    }
+
+    /// Route a batch of messages, prepaying the relay fees of their routes with `funds`
+    pub fn process_msgs(
+        &self,
+        msgs: Vec<(IbcMailMessage, Option<Route>)>,
+        funds: Vec<Coin>,
+    ) -> AbstractSdkResult<CosmosMsg> {
+        self.request_with_funds(ServerExecuteMsg::ProcessMessages { msgs }, funds)
+    }
+
+    /// Claim the mail held for `address` into the mail client of the account
+    pub fn claim_mail(&self, address: impl Into<String>) -> AbstractSdkResult<CosmosMsg> {
+        self.request(ServerExecuteMsg::ClaimMail {
+            address: address.into(),
+        })
+    }
 }
 
 /// Queries
@@ -65,7 +106,7 @@ impl<'a, T: ServerInterface> MailServer<'a, T> {
     }
 
     // Queries
-    pub fn config(&self) -> AbstractSdkResult<Uint128> {
+    pub fn config(&self) -> AbstractSdkResult<ConfigResponse> {
         self.query(ServerQueryMsg::Config {})
     }
 }
@@ -1,9 +1,10 @@
+use abstract_adapter::objects::TruncatedChainId;
 use abstract_adapter::std::ibc::ModuleIbcInfo;
 use abstract_adapter::{
     sdk::AbstractSdkError, std::AbstractError, AdapterError as AbstractAdapterError,
 };
-use abstract_app::std::objects::{account::AccountTrace, namespace::Namespace};
-use cosmwasm_std::StdError;
+use abstract_app::std::objects::{account::AccountTrace, namespace::Namespace, AccountId};
+use cosmwasm_std::{Addr, StdError};
 use cw_asset::AssetError;
 use cw_controllers::AdminError;
 use thiserror::Error;
@@ -37,9 +38,100 @@ pub enum ServerError {
     #[error("Unauthorized IBC message")]
     UnauthorizedIbcMessage,
 
-    #[error("Invalid route hop")]
-    InvalidRoute { route: AccountTrace, hop: u32 },
+    #[error("Invalid route {route:?} at hop {hop}: {reason}")]
+    InvalidRoute {
+        route: AccountTrace,
+        hop: u32,
+        reason: String,
+    },
 
     #[error("Unclaimed namespace: {0}")]
     UnclaimedNamespace(Namespace),
+
+    #[error("Sender is not the owner of the ibcmail namespace")]
+    Unauthorized {},
+
+    #[error("Account can't send mail as {0}")]
+    InvalidSender(String),
+
+    #[error("Account {0} has no accepted mail client installed")]
+    NoMailClient(AccountId),
+
+    #[error("Account is not allowed to claim mail for {0}")]
+    CannotClaim(String),
+
+    #[error("No route to chain {0}")]
+    NoRoute(TruncatedChainId),
+
+    #[error("Message from chain {chain} does not come from the previous hop of route {route:?}")]
+    WrongSourceChain {
+        chain: TruncatedChainId,
+        route: AccountTrace,
+    },
+
+    #[error("Sender {0} is not a trusted Polytone proxy")]
+    UntrustedProxy(String),
+
+    #[error(
+        "Sender {sender} exceeded the rate limit of {max_messages} messages per {window} seconds"
+    )]
+    RateLimitExceeded {
+        sender: String,
+        max_messages: u32,
+        window: u64,
+    },
+
+    #[error("Fees of {required} must be attached to the message")]
+    InsufficientRelayFees { required: String },
+
+    #[error("Relay fee of {required} did not arrive with the mail for chain {chain}")]
+    RelayFeeNotPaid {
+        chain: TruncatedChainId,
+        required: String,
+    },
+
+    #[error("Server is paused for {0} mail")]
+    Paused(String),
+
+    #[error("Mail {direction} chain {chain} is disabled")]
+    ChainDisabled {
+        chain: TruncatedChainId,
+        direction: String,
+    },
+
+    #[error("Unsupported message version {0}")]
+    UnsupportedMessageVersion(String),
+
+    #[error("Unsupported wire version {0}")]
+    UnsupportedWireVersion(u32),
+
+    #[error("Sender {0} is not a trusted ibc-hooks sender")]
+    UntrustedHookSender(String),
+
+    #[error("Only unordered channels with version {expected} are supported, got {version}")]
+    InvalidChannel { version: String, expected: String },
+
+    #[error("Channel {channel} is not open to the mail server of chain {chain}")]
+    ChannelNotOpen {
+        channel: String,
+        chain: TruncatedChainId,
+    },
+
+    #[error("Port {0} is not a trusted mail server port")]
+    UntrustedPort(String),
+
+    #[error("Channel {0} is not registered for a chain")]
+    UnknownChannel(String),
+
+    #[error("Funds of {funds} can't be sent with mail to chain {chain}")]
+    FundsNotTransferable {
+        chain: TruncatedChainId,
+        funds: String,
+    },
+
+    #[error("Account {0} must authorize the server on itself to receive packets on its channels")]
+    ServerNotAuthorized(Addr),
+
+    #[error("Transfer response without packet sequence")]
+    MissingTransferSequence {},
 }
@@ -2,13 +2,21 @@ use abstract_adapter::AdapterContract;
 
 use crate::server::{
     error::ServerError,
-    msg::{ServerExecuteMsg, ServerInstantiateMsg, ServerQueryMsg},
+    msg::{ServerExecuteMsg, ServerInstantiateMsg, ServerQueryMsg, ServerSudoMsg},
 };
 
 pub mod api;
 pub mod error;
 pub mod msg;
+pub mod state;
+pub mod transport;
+pub mod wire;
 
 /// The type of the client that is used to build your client and access the Abstract SDK features.
-pub type ServerAdapter =
-    AdapterContract<ServerError, ServerInstantiateMsg, ServerExecuteMsg, ServerQueryMsg>;
+pub type ServerAdapter = AdapterContract<
+    ServerError,
+    ServerInstantiateMsg,
+    ServerExecuteMsg,
+    ServerQueryMsg,
+    ServerSudoMsg,
+>;
@@ -1,6 +1,17 @@
+use abstract_app::objects::{AccountId, TruncatedChainId};
 use cosmwasm_schema::QueryResponses;
+use cosmwasm_std::{Binary, Coin};
 
-use crate::{server::ServerAdapter, Header, IbcMailMessage, Route};
+use crate::{
+    server::{
+        state::{
+            DeadLetter, DeliveryRecord, HeldMessage, Ics20Route, MessageStats, PauseMode,
+            PolytoneRoute, RateLimit, RelayFee, RemoteRelayFee, ServerConfig,
+        },
+        ServerAdapter,
+    },
+    Header, IbcMailMessage, Message, MessageHash, Route, Sender,
+};
 
 // This is used for type safety and re-exporting the contract endpoint structs.
 abstract_adapter::adapter_msg_types!(ServerAdapter, ServerExecuteMsg, ServerQueryMsg);
@@ -13,11 +24,144 @@ pub struct ServerInstantiateMsg {}
 #[cosmwasm_schema::cw_serde]
 #[derive(cw_orch::ExecuteFns)]
 pub enum ServerExecuteMsg {
-    /// Route a message
+    /// Route a message, with the fees of its route attached as returned by [`ServerQueryMsg::DryRun`].
+    /// Any excess is returned to the caller and reported in [`ProcessMessagesResponse`].
     ProcessMessage {
         msg: IbcMailMessage,
         route: Option<Route>,
     },
+    /// Route a batch of messages, with one IBC message per next hop.
+    /// Messages are sent without preflight, the result of each message is returned in [`ProcessMessagesResponse`].
+    /// The fees of all routes must be attached, messages for the same ICS-20 hop share one transfer.
+    /// The funds of the messages that are not sent and any excess are returned to the caller.
+    ProcessMessages {
+        msgs: Vec<(IbcMailMessage, Option<Route>)>,
+    },
+    /// Deliver the mail held for `address` to the mail client of the calling account.
+    /// The account must be at `address` or be owned by it.
+    ClaimMail { address: String },
+    /// Bounce the expired mail held for `address` back to its senders.
+    BounceExpiredMail { address: String },
+    /// Set or remove the known remote hosts of chains, only callable by the owner of the ibcmail namespace
+    UpdateRouteTable {
+        to_add: Vec<(TruncatedChainId, Vec<TruncatedChainId>)>,
+        to_remove: Vec<TruncatedChainId>,
+    },
+    /// Set or remove the preferred next hop towards `destination`, only callable by the owner of the ibcmail namespace
+    SetPreferredHop {
+        destination: TruncatedChainId,
+        next_hop: Option<TruncatedChainId>,
+    },
+    /// Set or reset the cost of the link from `from` to `to`, only callable by the owner of the ibcmail namespace
+    SetLinkCost {
+        from: TruncatedChainId,
+        to: TruncatedChainId,
+        cost: Option<u64>,
+    },
+    /// Block or unblock the link from `from` to `to`, only callable by the owner of the ibcmail namespace
+    SetLinkBlocked {
+        from: TruncatedChainId,
+        to: TruncatedChainId,
+        blocked: bool,
+    },
+    /// Set or remove the Polytone route to `chain`, only callable by the owner of the ibcmail namespace
+    SetPolytoneRoute {
+        chain: TruncatedChainId,
+        route: Option<PolytoneRoute>,
+    },
+    /// Trust or distrust the Polytone proxy of the server on `chain`, only callable by the owner of the ibcmail namespace.
+    /// The proxy must also be authorized on this server by the relay account it executes for.
+    SetTrustedProxy {
+        proxy: String,
+        chain: Option<TruncatedChainId>,
+    },
+    /// Trust or distrust the IBC port `port_id` of the server on `chain` to open channels to this server's own port.
+    /// Only callable by the owner of the ibcmail namespace.
+    SetTrustedPort {
+        port_id: String,
+        chain: Option<TruncatedChainId>,
+    },
+    /// Send mail for `chain` over the mail server's own IBC channel `channel_id`, or stop using it.
+    /// Only callable by the owner of the ibcmail namespace, whose account the packets received on the channel
+    /// are handled for. Fails unless the account authorized the server's own address on the server.
+    SetIbcChannel {
+        chain: TruncatedChainId,
+        channel_id: Option<String>,
+    },
+    /// Set or remove the ICS-20 route to `chain`, only callable by the owner of the ibcmail namespace
+    SetIcs20Route {
+        chain: TruncatedChainId,
+        route: Option<Ics20Route>,
+    },
+    /// Trust or distrust the server `remote_server` on `chain` whose transfers arrive over the local `channel`.
+    /// Only callable by the owner of the ibcmail namespace.
+    /// The ibc-hooks sender derived from them must also be authorized on this server by the relay account.
+    SetTrustedHookSender {
+        channel: String,
+        remote_server: String,
+        chain: Option<TruncatedChainId>,
+    },
+    /// Receive a message from the server of another chain through its Polytone proxy
+    ReceivePolytone { msg: Binary },
+    /// Receive a message from the server of another chain through an ibc-hooks transfer
+    ReceiveIcs20 { msg: Binary },
+    /// Handle a packet received on the server's own IBC channel `channel_id`, only callable by the server itself
+    HandleIbcPacket { channel_id: String, msg: Binary },
+    /// Deliver the dead letter `id` of the account at `address` again.
+    /// Only callable by that account or the owner of the ibcmail namespace.
+    RetryDeadLetter { address: String, id: MessageHash },
+    /// Remove the dead letter `id` of the account at `address`, notifying its sender if `bounce` is set.
+    /// Only callable by that account or the owner of the ibcmail namespace.
+    DiscardDeadLetter {
+        address: String,
+        id: MessageHash,
+        bounce: bool,
+    },
+    /// Learn the wire versions supported by the server on `chain` over Abstract IBC.
+    /// The known version is kept if the server doesn't answer.
+    /// Only callable by the owner of the ibcmail namespace.
+    SyncWireVersion { chain: TruncatedChainId },
+    /// Set the wire version to send in to the server on `chain`, like the legacy version for servers
+    /// that can't be synced, or reset it to the current version.
+    /// Only callable by the owner of the ibcmail namespace.
+    SetPeerWireVersion {
+        chain: TruncatedChainId,
+        version: Option<u32>,
+    },
+    /// Set or remove the rate limit for the mail originating from `chain`, or the default rate limit if no chain is given.
+    /// Only callable by the owner of the ibcmail namespace.
+    SetRateLimit {
+        chain: Option<TruncatedChainId>,
+        limit: Option<RateLimit>,
+    },
+    /// Set the fee for forwarding the mail of other chains, or forward it for free.
+    /// Mail that doesn't arrive with the fee over ICS-20 is bounced. Only callable by the owner of the ibcmail namespace.
+    SetRelayFee { fee: Option<RelayFee> },
+    /// Set or remove the relay fee charged by the server on `chain`.
+    /// Senders prepay it when routing mail through `chain` as the next hop,
+    /// it is transferred to that server with the mail over its ICS-20 route.
+    /// Only callable by the owner of the ibcmail namespace.
+    SetRemoteRelayFee {
+        chain: TruncatedChainId,
+        fee: Option<RemoteRelayFee>,
+    },
+    /// Pause the server for the mail of `mode`, or resume it.
+    /// Only callable by the owner of the ibcmail namespace.
+    SetPause { mode: Option<PauseMode> },
+    /// Stop routing mail to (outbound) or from (inbound) `chain`, or resume it.
+    /// Only callable by the owner of the ibcmail namespace.
+    SetChainBreaker {
+        chain: TruncatedChainId,
+        mode: Option<PauseMode>,
+    },
+    /// Update the server configuration, only callable by the owner of the ibcmail namespace
+    UpdateConfig {
+        accepted_clients: Option<Vec<String>>,
+        held_mail_expiry: Option<u64>,
+        max_hops: Option<u32>,
+        message_ttl: Option<u64>,
+        remote_preflight: Option<bool>,
+    },
 }
 
 /// App execute messages
@@ -26,6 +170,46 @@ pub enum ServerExecuteMsg {
 pub enum ServerIbcMessage {
     /// Route a message
     RouteMessage { msg: IbcMailMessage, header: Header },
+    /// Route a batch of messages that share the same next hop
+    RouteMessages { msgs: Vec<(IbcMailMessage, Header)> },
+}
+
+/// Acknowledgement of a packet on the mail server's own IBC port
+#[cosmwasm_schema::cw_serde]
+pub enum IbcMailAck {
+    Success {},
+    /// The packet could not be handled, its messages are bounced by the sending server
+    Error(String),
+}
+
+/// Sudo messages that ibc-hooks sends for the ICS-20 transfers of the server that requested a callback
+#[cosmwasm_schema::cw_serde]
+pub enum ServerSudoMsg {
+    IbcLifecycleComplete(IbcLifecycleComplete),
+}
+
+/// Outcome of an ICS-20 transfer, identified by the channel and sequence it was sent with
+#[cosmwasm_schema::cw_serde]
+pub enum IbcLifecycleComplete {
+    IbcAck {
+        channel: String,
+        sequence: u64,
+        ack: String,
+        success: bool,
+    },
+    IbcTimeout {
+        channel: String,
+        sequence: u64,
+    },
+}
+
+/// Callbacks of the IBC actions started by the server
+#[cosmwasm_schema::cw_serde]
+pub enum ServerCallbackMsg {
+    /// Send `msg` if the destination server confirmed it can be delivered
+    Preflight { msg: IbcMailMessage, header: Header },
+    /// Store the wire version to use for `chain`
+    WireVersions { chain: TruncatedChainId },
 }
 
 /// App query messages
@@ -35,6 +219,56 @@ pub enum ServerIbcMessage {
 pub enum ServerQueryMsg {
     #[returns(ConfigResponse)]
     Config {},
+    /// Mail held for `address` that has not been claimed yet
+    #[returns(HeldMailResponse)]
+    HeldMail {
+        address: String,
+        start_after: Option<MessageHash>,
+        limit: Option<u32>,
+    },
+    /// Messages for the account at `address` whose delivery failed
+    #[returns(DeadLettersResponse)]
+    DeadLetters {
+        address: String,
+        start_after: Option<MessageHash>,
+        limit: Option<u32>,
+    },
+    /// The routing table: remote hosts of other chains, preferred hops, link costs and blocked links
+    #[returns(RouteTableResponse)]
+    RouteTable {},
+    /// Resolve how `message` would be routed and delivered without sending it.
+    /// Runs the checks of the server for mail of `sender`.
+    #[returns(DryRunResponse)]
+    DryRun {
+        message: Message,
+        route: Option<Route>,
+        sender: Sender,
+    },
+    /// What this server did with the message `id`, oldest first
+    #[returns(DeliveryLogResponse)]
+    MessageHistory { id: MessageHash },
+    /// What this server did with the messages of `sender`, oldest first
+    #[returns(DeliveryLogResponse)]
+    SenderHistory {
+        sender: Sender,
+        start_after: Option<u64>,
+        limit: Option<u32>,
+    },
+    /// The wire versions supported by this server and the versions used for other chains
+    #[returns(WireVersionsResponse)]
+    WireVersions {},
+    /// Whether the server is paused and the chains it doesn't route mail to or from
+    #[returns(PauseStatusResponse)]
+    PauseStatus {},
+    /// Number of messages handled by the server
+    #[returns(StatisticsResponse)]
+    Statistics {},
+    /// Number of messages handled by the server by source and destination chain
+    #[returns(ChainStatisticsResponse)]
+    ChainStatistics {
+        start_after: Option<(TruncatedChainId, TruncatedChainId)>,
+        limit: Option<u32>,
+    },
 }
 
 // impl From<ServerQueryMsg> for QueryMsg {
@@ -44,7 +278,111 @@ pub enum ServerQueryMsg {
 // }
 
 #[cosmwasm_schema::cw_serde]
-pub struct ConfigResponse {}
+pub struct ConfigResponse {
+    pub config: ServerConfig,
+    /// Rate limits for the mail from specific chains
+    pub chain_rate_limits: Vec<(TruncatedChainId, RateLimit)>,
+    /// Relay fees charged by the servers of other chains
+    pub remote_relay_fees: Vec<(TruncatedChainId, RemoteRelayFee)>,
+}
+
+#[cosmwasm_schema::cw_serde]
+pub struct HeldMailResponse {
+    pub messages: Vec<HeldMessage>,
+}
+
+#[cosmwasm_schema::cw_serde]
+pub struct DeadLettersResponse {
+    pub messages: Vec<DeadLetter>,
+}
+
+#[cosmwasm_schema::cw_serde]
+pub struct RouteTableResponse {
+    pub chains: Vec<(TruncatedChainId, Vec<TruncatedChainId>)>,
+    /// Preferred next hop by destination
+    pub preferred_hops: Vec<(TruncatedChainId, TruncatedChainId)>,
+    pub link_costs: Vec<LinkCost>,
+    pub blocked_links: Vec<(TruncatedChainId, TruncatedChainId)>,
+    /// Chains that are reached over Polytone
+    pub polytone_routes: Vec<(TruncatedChainId, PolytoneRoute)>,
+    /// Chains that are reached over the server's own IBC channels, with the channel
+    pub ibc_channels: Vec<(TruncatedChainId, String)>,
+    /// Chains that are reached with ICS-20 transfers
+    pub ics20_routes: Vec<(TruncatedChainId, Ics20Route)>,
+}
+
+#[cosmwasm_schema::cw_serde]
+pub struct DeliveryLogResponse {
+    /// Delivery log entries with their id
+    pub entries: Vec<(u64, DeliveryRecord)>,
+}
+
+#[cosmwasm_schema::cw_serde]
+pub struct PauseStatusResponse {
+    /// Mail the server is paused for, running if not set
+    pub paused: Option<PauseMode>,
+    pub chain_breakers: Vec<(TruncatedChainId, PauseMode)>,
+}
+
+#[cosmwasm_schema::cw_serde]
+pub struct StatisticsResponse {
+    pub total: MessageStats,
+}
+
+#[cosmwasm_schema::cw_serde]
+pub struct ChainStatistics {
+    pub source: TruncatedChainId,
+    pub destination: TruncatedChainId,
+    pub stats: MessageStats,
+}
+
+#[cosmwasm_schema::cw_serde]
+pub struct ChainStatisticsResponse {
+    pub chains: Vec<ChainStatistics>,
+}
+
+#[cosmwasm_schema::cw_serde]
+pub struct WireVersionsResponse {
+    pub supported: Vec<u32>,
+    /// Highest common version with the server of each chain it is known for
+    pub peers: Vec<(TruncatedChainId, u32)>,
+}
+
+#[cosmwasm_schema::cw_serde]
+pub struct LinkCost {
+    pub from: TruncatedChainId,
+    pub to: TruncatedChainId,
+    pub cost: u64,
+}
+
+/// Data of the response to [`ServerExecuteMsg::ProcessMessage`] and [`ServerExecuteMsg::ProcessMessages`]
+#[cosmwasm_schema::cw_serde]
+pub struct ProcessMessagesResponse {
+    pub results: Vec<MessageResult>,
+    /// Funds returned to the caller
+    pub refund: Vec<Coin>,
+}
+
+#[cosmwasm_schema::cw_serde]
+pub struct MessageResult {
+    pub id: MessageHash,
+    /// Why the message could not be sent, `None` if it was sent
+    pub error: Option<String>,
+}
+
+#[cosmwasm_schema::cw_serde]
+pub struct DryRunResponse {
+    /// Full route from the current chain, if one could be resolved
+    pub route: Option<Route>,
+    /// Local account the message would be delivered to
+    pub recipient_account: Option<AccountId>,
+    /// Module on the recipient account that would receive the message
+    pub mail_client: Option<String>,
+    /// Funds to attach to the message: the relay fees of its route and the tokens of an ICS-20 transfer to the next hop
+    pub fees: Vec<Coin>,
+    /// Reasons the message would be rejected, empty if it can be sent
+    pub errors: Vec<String>,
+}
 
 #[cosmwasm_schema::cw_serde]
 pub struct CountResponse {
@@ -0,0 +1,331 @@
+use abstract_app::objects::TruncatedChainId;
+use cosmwasm_std::{to_json_string, Addr, Binary, Coin, Empty, StdResult, Timestamp};
+use cw_storage_plus::{Index, IndexList, IndexedMap, Item, Map, MultiIndex};
+
+use crate::{Header, IbcMailMessage, MessageHash, Sender, IBCMAIL_CLIENT_ID};
+
+/// Default time in seconds that held mail is kept before it is bounced (30 days).
+pub const DEFAULT_HELD_MAIL_EXPIRY: u64 = 30 * 24 * 60 * 60;
+
+/// Default maximum number of hops of a message.
+pub const DEFAULT_MAX_HOPS: u32 = 8;
+
+/// Default time in seconds that a message may spend in transit before it is bounced (1 day).
+pub const DEFAULT_MESSAGE_TTL: u64 = 24 * 60 * 60;
+
+#[cosmwasm_schema::cw_serde]
+pub struct ServerConfig {
+    /// Ids of the mail client modules the server delivers to, in order of preference.
+    pub accepted_clients: Vec<String>,
+    /// Time in seconds after which held mail is bounced to its sender.
+    pub held_mail_expiry: u64,
+    /// Maximum number of hops of the messages sent from this chain.
+    pub max_hops: u32,
+    /// Time in seconds that messages sent from this chain may spend in transit.
+    pub message_ttl: u64,
+    /// Check with the destination server that the recipient can receive mail before sending.
+    /// Only applies to messages for directly connected chains.
+    pub remote_preflight: bool,
+    /// Messages a sender may send per time window, unlimited if not set.
+    /// Can be overridden for the mail from specific chains.
+    pub rate_limit: Option<RateLimit>,
+    /// Fee for forwarding the mail of other chains, which is forwarded for free if not set.
+    /// Senders prepay it on the chain the mail is sent from, it arrives with the mail over the ICS-20 route to this server.
+    #[serde(default)]
+    pub relay_fee: Option<RelayFee>,
+}
+
+impl Default for ServerConfig {
+    fn default() -> Self {
+        Self {
+            accepted_clients: vec![IBCMAIL_CLIENT_ID.to_string()],
+            held_mail_expiry: DEFAULT_HELD_MAIL_EXPIRY,
+            max_hops: DEFAULT_MAX_HOPS,
+            message_ttl: DEFAULT_MESSAGE_TTL,
+            remote_preflight: false,
+            rate_limit: None,
+            relay_fee: None,
+        }
+    }
+}
+
+pub const CONFIG: Item<ServerConfig> = Item::new("config");
+
+/// Relay fee that the server charges for forwarding a message.
+#[cosmwasm_schema::cw_serde]
+pub struct RelayFee {
+    /// Fee per forwarded message, in the denom that the prepaid fees arrive in on this chain
+    pub amount: Coin,
+    /// Address on the server's chain that the fees are paid to
+    pub collector: Addr,
+}
+
+/// Relay fee of the server on another chain, as senders on this chain prepay it.
+#[cosmwasm_schema::cw_serde]
+pub struct RemoteRelayFee {
+    /// Fee charged by the other server, in the denom of its chain
+    pub fee: Coin,
+    /// Denom on this chain that arrives as the fee denom when it is transferred over the ICS-20 route to the other chain.
+    /// Senders attach the fee amount in this denom.
+    pub denom: String,
+}
+
+/// Relay fees charged by the servers of other chains, which senders prepay on this chain for routes through them.
+/// Only the first server that forwards a message can be prepaid, its fee is added to the ICS-20 transfer of the mail.
+pub const REMOTE_RELAY_FEES: Map<&str, RemoteRelayFee> = Map::new("remote_relay_fees");
+
+#[cosmwasm_schema::cw_serde]
+pub struct RateLimit {
+    /// Maximum number of messages per sender in a window
+    pub max_messages: u32,
+    /// Length of a window in seconds
+    pub window: u64,
+}
+
+/// Rate limits for the senders of the mail originating from a chain, overriding the configured one.
+pub const CHAIN_RATE_LIMITS: Map<&str, RateLimit> = Map::new("chain_rate_limits");
+
+/// Messages counted for a sender in the current window.
+#[cosmwasm_schema::cw_serde]
+pub struct RateWindow {
+    pub start: Timestamp,
+    pub count: u32,
+}
+
+/// Rate limit windows by the chain the mail originates from and the [`sender_key`] of its sender.
+pub const RATE_WINDOWS: Map<(&str, &str), RateWindow> = Map::new("rate_windows");
+
+/// Mail that is stopped while the server or a chain is paused.
+#[cosmwasm_schema::cw_serde]
+pub enum PauseMode {
+    /// All mail
+    All,
+    /// Mail sent to other chains
+    Outbound,
+    /// Mail received from other chains
+    Inbound,
+}
+
+impl PauseMode {
+    pub fn stops_outbound(&self) -> bool {
+        matches!(self, PauseMode::All | PauseMode::Outbound)
+    }
+
+    pub fn stops_inbound(&self) -> bool {
+        matches!(self, PauseMode::All | PauseMode::Inbound)
+    }
+}
+
+impl std::fmt::Display for PauseMode {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            PauseMode::All => write!(f, "all"),
+            PauseMode::Outbound => write!(f, "outbound"),
+            PauseMode::Inbound => write!(f, "inbound"),
+        }
+    }
+}
+
+/// Mail the server is paused for, the server is running if not set.
+pub const PAUSED: Item<PauseMode> = Item::new("paused");
+
+/// Circuit breakers by chain, stopping the mail routed to (outbound) or from (inbound) the chain.
+pub const CHAIN_BREAKERS: Map<&str, PauseMode> = Map::new("chain_breakers");
+
+/// Number of messages handled by the server.
+#[cosmwasm_schema::cw_serde]
+#[derive(Default)]
+pub struct MessageStats {
+    /// Messages routed from this chain
+    pub originated: u64,
+    /// Messages passed on to the next hop for other chains
+    pub forwarded: u64,
+    /// Messages handed to the mail client of a local account
+    pub delivered: u64,
+    /// Messages bounced back to their sender
+    pub bounced: u64,
+}
+
+/// Statistics of all the messages handled by the server.
+pub const TOTAL_STATS: Item<MessageStats> = Item::new("total_stats");
+
+/// Statistics by the source and destination chain of the messages.
+pub const CHAIN_STATS: Map<(&str, &str), MessageStats> = Map::new("chain_stats");
+
+/// Message held by the server until its recipient claims it.
+#[cosmwasm_schema::cw_serde]
+pub struct HeldMessage {
+    pub msg: IbcMailMessage,
+    pub header: Header,
+    pub held_at: Timestamp,
+    pub expires_at: Timestamp,
+}
+
+/// Held messages by the address they are held for and message id.
+/// Mail for accounts without a mail client is held for the account address.
+pub const HELD_MAIL: Map<(&Addr, &str), HeldMessage> = Map::new("held_mail");
+
+/// Message whose delivery to the mail client of its recipient failed.
+#[cosmwasm_schema::cw_serde]
+pub struct DeadLetter {
+    pub msg: IbcMailMessage,
+    pub header: Header,
+    /// Error returned by the mail client
+    pub error: String,
+    pub failed_at: Timestamp,
+}
+
+/// Dead letters by the address of the recipient account and message id.
+/// They are kept until the recipient or the admin retries, bounces or discards them.
+pub const DEAD_LETTERS: Map<(&Addr, &str), DeadLetter> = Map::new("dead_letters");
+
+/// Known remote hosts of other chains, used to find multi-hop routes.
+/// The remote hosts of the current chain are taken from the IBC client.
+pub const ROUTE_TABLE: Map<&str, Vec<TruncatedChainId>> = Map::new("route_table");
+
+/// Preferred next hop from the current chain by destination chain.
+pub const PREFERRED_HOPS: Map<&str, TruncatedChainId> = Map::new("preferred_hops");
+
+/// Default cost of a link that has no cost set.
+pub const DEFAULT_LINK_COST: u64 = 1;
+
+/// Cost of the link between two chains by (from, to).
+pub const LINK_COSTS: Map<(&str, &str), u64> = Map::new("link_costs");
+
+/// Links that are never used for routing by (from, to).
+pub const BLOCKED_LINKS: Map<(&str, &str), Empty> = Map::new("blocked_links");
+
+/// Polytone connection to the mail server of a chain without Abstract IBC.
+#[cosmwasm_schema::cw_serde]
+pub struct PolytoneRoute {
+    /// Polytone note on this chain that is connected to the destination chain
+    pub note: Addr,
+    /// Address of the mail server on the destination chain
+    pub remote_server: String,
+    /// Account on the destination chain that authorized this server's Polytone proxy on the remote server
+    pub relay_account: String,
+}
+
+/// Polytone routes by destination chain, these chains are reached over Polytone instead of Abstract IBC.
+pub const POLYTONE_ROUTES: Map<&str, PolytoneRoute> = Map::new("polytone_routes");
+
+/// Polytone proxies of the mail servers of other chains that may deliver mail, with their chain.
+pub const TRUSTED_PROXIES: Map<&Addr, TruncatedChainId> = Map::new("trusted_proxies");
+
+/// Ports of the mail servers of other chains that may open channels to the mail server's own IBC port, with their chain.
+pub const TRUSTED_PORTS: Map<&str, TruncatedChainId> = Map::new("trusted_ports");
+
+/// Open channels of the mail server's own IBC port, with the chain of their counterparty.
+pub const OPEN_CHANNELS: Map<&str, TruncatedChainId> = Map::new("open_channels");
+
+/// Account that the packets received on the mail server's own IBC port are handled for.
+/// Set to the account that registers the channels, the server executes itself on its behalf to handle them.
+/// [`SetIbcChannel`](crate::server::msg::ServerExecuteMsg::SetIbcChannel) requires the account to have added
+/// the server's own address to its authorized addresses on the server, packets fail to be handled once it is removed.
+pub const IBC_RELAY_ACCOUNT: Item<Addr> = Item::new("ibc_relay_account");
+
+/// Channel of the mail server's own IBC port by the chain it connects to.
+/// These chains are reached over the channel instead of Abstract IBC.
+pub const IBC_CHANNELS: Map<&str, String> = Map::new("ibc_channels");
+
+/// ICS-20 connection to the mail server of a chain that only supports ibc-hooks.
+#[cosmwasm_schema::cw_serde]
+pub struct Ics20Route {
+    /// Transfer channel on this chain that is connected to the destination chain
+    pub channel: String,
+    /// Tokens transferred with every message.
+    /// Senders on this chain pay them with their mail, the server pays them for the mail it forwards or bounces.
+    /// The destination server passes them on to `relay_account`.
+    pub funds: Coin,
+    /// Address of the mail server on the destination chain
+    pub remote_server: String,
+    /// Account on the destination chain that authorized this server's hook sender on the remote server
+    pub relay_account: String,
+}
+
+/// ICS-20 routes by destination chain, these chains are reached with ibc-hooks transfers instead of Abstract IBC.
+pub const ICS20_ROUTES: Map<&str, Ics20Route> = Map::new("ics20_routes");
+
+/// Encoded messages of the ICS-20 transfers that ibc-hooks has not reported the outcome of yet,
+/// by channel and packet sequence. Bounced if the transfer fails or times out.
+pub const PENDING_TRANSFERS: Map<(&str, u64), Binary> = Map::new("pending_transfers");
+
+/// Addresses that ibc-hooks executes for the mail servers of other chains, with their chain.
+pub const TRUSTED_HOOK_SENDERS: Map<&Addr, TruncatedChainId> = Map::new("trusted_hook_senders");
+
+/// Highest wire version supported by both this server and the server of a chain, by chain.
+pub const PEER_WIRE_VERSIONS: Map<&str, u32> = Map::new("peer_wire_versions");
+
+/// What the server did with a message.
+#[cosmwasm_schema::cw_serde]
+pub enum DeliveryStatus {
+    /// Sent on to the server of the next hop
+    Dispatched { next_hop: TruncatedChainId },
+    /// Delivered to the mail client of the recipient
+    Delivered {},
+    /// Held until the recipient claims it
+    Held {},
+    /// Could not be routed
+    Failed { reason: String },
+    /// Dropped and bounced to its sender
+    Bounced { reason: String },
+    /// Delivery to the mail client failed, kept as a dead letter
+    DeadLettered { reason: String },
+    /// Dead letter discarded without notifying the sender
+    Discarded {},
+}
+
+/// Entry of the delivery log.
+#[cosmwasm_schema::cw_serde]
+pub struct DeliveryRecord {
+    pub message_id: MessageHash,
+    pub sender: Sender,
+    /// Key of the sender in the sender index, see [`sender_key`]
+    pub sender_key: String,
+    pub status: DeliveryStatus,
+    pub height: u64,
+    pub time: Timestamp,
+}
+
+/// Number of entries kept in the delivery log, older entries are removed.
+pub const DELIVERY_LOG_SIZE: u64 = 1_000;
+
+/// Id of the next delivery log entry.
+pub const DELIVERY_LOG_NEXT_ID: Item<u64> = Item::new("delivery_log_next_id");
+
+pub struct DeliveryLogIndexes<'a> {
+    pub message_id: MultiIndex<'a, String, DeliveryRecord, u64>,
+    /// Senders are indexed by [`DeliveryRecord::sender_key`]
+    pub sender: MultiIndex<'a, String, DeliveryRecord, u64>,
+}
+
+impl IndexList<DeliveryRecord> for DeliveryLogIndexes<'_> {
+    fn get_indexes(&'_ self) -> Box<dyn Iterator<Item = &'_ dyn Index<DeliveryRecord>> + '_> {
+        let v: Vec<&dyn Index<DeliveryRecord>> = vec![&self.message_id, &self.sender];
+        Box::new(v.into_iter())
+    }
+}
+
+/// Bounded log of what the server did with the messages it processed, by entry id.
+pub fn delivery_log<'a>() -> IndexedMap<u64, DeliveryRecord, DeliveryLogIndexes<'a>> {
+    let indexes = DeliveryLogIndexes {
+        message_id: MultiIndex::new(
+            |_, record| record.message_id.clone(),
+            "delivery_log",
+            "delivery_log__message_id",
+        ),
+        sender: MultiIndex::new(
+            |_, record| record.sender_key.clone(),
+            "delivery_log",
+            "delivery_log__sender",
+        ),
+    };
+    IndexedMap::new("delivery_log", indexes)
+}
+
+/// Key of `sender` in the delivery log sender index.
+/// Senders are keyed by their JSON encoding, servers fill in their chain when they receive a message
+/// so the same sender has one key, see [`Sender::with_chain`].
+pub fn sender_key(sender: &Sender) -> StdResult<String> {
+    to_json_string(sender)
+}
@@ -0,0 +1,387 @@
+use abstract_adapter::objects::TruncatedChainId;
+use abstract_adapter::sdk::{features::ModuleIdentification, ModuleRegistryInterface};
+use abstract_adapter::std::{
+    adapter::{self, AdapterRequestMsg},
+    ibc_client::{self, ListRemoteHostsResponse},
+    objects::module::ModuleInfo,
+    IBC_CLIENT,
+};
+use cosmwasm_std::{
+    ensure, to_json_binary, to_json_string, wasm_execute, Addr, Api, Binary, CanonicalAddr, Coin,
+    CosmosMsg, Deps, Env, IbcMsg, IbcTimeout, Order, StdError, StdResult, SubMsg, Uint64,
+};
+use sha2::{Digest, Sha256};
+
+use crate::server::{
+    error::ServerError,
+    msg::ServerExecuteMsg,
+    state::{IBC_CHANNELS, ICS20_ROUTES, POLYTONE_ROUTES},
+    ServerAdapter,
+};
+
+/// Time in seconds after which a Polytone packet times out.
+pub const POLYTONE_TIMEOUT_SECONDS: u64 = 10 * 60;
+
+/// Time in seconds after which an ICS-20 transfer times out.
+pub const ICS20_TIMEOUT_SECONDS: u64 = 10 * 60;
+
+/// Reply id of the ICS-20 transfers that carry messages.
+pub const ICS20_TRANSFER_REPLY_ID: u64 = 3;
+
+/// Channel version of the mail server's own IBC port.
+pub const IBCMAIL_IBC_VERSION: &str = "ibcmail-1";
+
+/// Time in seconds after which a packet on the mail server's own port times out.
+pub const IBC_PACKET_TIMEOUT_SECONDS: u64 = 10 * 60;
+
+/// Carries [`ServerIbcMessage`](crate::server::msg::ServerIbcMessage)s to the mail servers of other chains.
+/// The receiving server hands them to the same routing logic, whichever transport delivered them.
+pub trait Transport {
+    /// Chains whose mail server can be reached directly.
+    fn remote_hosts(&self, deps: Deps) -> Result<Vec<TruncatedChainId>, ServerError>;
+
+    /// Message that sends `msg`, encoded with [`wire::encode`](crate::server::wire::encode),
+    /// to the mail server on `dest_chain`, with the reply the transport needs.
+    /// `funds`, like prepaid relay fees, go along with the message. Transports that can't transfer them fail.
+    fn send(
+        &self,
+        deps: Deps,
+        env: &Env,
+        dest_chain: TruncatedChainId,
+        msg: Binary,
+        funds: Vec<Coin>,
+    ) -> Result<SubMsg, ServerError>;
+
+    /// Tokens transferred with every message sent to `dest_chain`.
+    fn transfer_funds(
+        &self,
+        _deps: Deps,
+        _dest_chain: &TruncatedChainId,
+    ) -> Result<Option<Coin>, ServerError> {
+        Ok(None)
+    }
+
+    /// Whether the mail server on the other end can be queried before sending to it.
+    fn can_query(&self) -> bool {
+        false
+    }
+}
+
+/// Transport over the Abstract IBC client and host, which delivers through module IBC actions.
+pub struct AbstractIbc<'a> {
+    pub app: &'a ServerAdapter,
+}
+
+impl AbstractIbc<'_> {
+    /// Address of the Abstract IBC client.
+    pub fn ibc_client_addr(&self, deps: Deps) -> Result<Addr, ServerError> {
+        let ibc_client_addr: Addr = self
+            .app
+            .module_registry(deps)?
+            .query_module(ModuleInfo::from_id_latest(IBC_CLIENT)?)?
+            .reference
+            .unwrap_native()?;
+
+        Ok(ibc_client_addr)
+    }
+}
+
+impl Transport for AbstractIbc<'_> {
+    fn remote_hosts(&self, deps: Deps) -> Result<Vec<TruncatedChainId>, ServerError> {
+        let response: ListRemoteHostsResponse = deps.querier.query_wasm_smart(
+            self.ibc_client_addr(deps)?,
+            &ibc_client::QueryMsg::ListRemoteHosts {},
+        )?;
+
+        Ok(response.hosts.into_iter().map(|(chain, _)| chain).collect())
+    }
+
+    fn send(
+        &self,
+        deps: Deps,
+        _env: &Env,
+        dest_chain: TruncatedChainId,
+        msg: Binary,
+        funds: Vec<Coin>,
+    ) -> Result<SubMsg, ServerError> {
+        ensure_no_funds(&dest_chain, &funds)?;
+        let current_module_info =
+            ModuleInfo::from_id(self.app.module_id(), self.app.version().into())?;
+
+        // ANCHOR: ibc_client
+        // Call IBC client
+        let ibc_client_msg = ibc_client::ExecuteMsg::ModuleIbcAction {
+            host_chain: dest_chain,
+            target_module: current_module_info,
+            msg,
+            callback: None,
+        };
+
+        let ibc_client_addr: Addr = self.ibc_client_addr(deps)?;
+
+        let msg: CosmosMsg = wasm_execute(ibc_client_addr, &ibc_client_msg, vec![])?.into();
+        // ANCHOR_END: ibc_client
+        Ok(SubMsg::new(msg))
+    }
+
+    fn can_query(&self) -> bool {
+        true
+    }
+}
+
+/// Transport over Polytone for chains that only have Polytone deployed.
+/// The Polytone proxy of this server delivers to the remote server on behalf of the route's relay account.
+pub struct Polytone;
+
+/// Execute message of the Polytone note, limited to what the transport sends.
+#[cosmwasm_schema::cw_serde]
+enum NoteExecuteMsg {
+    Execute {
+        msgs: Vec<CosmosMsg>,
+        callback: Option<NoteCallbackRequest>,
+        timeout_seconds: Uint64,
+    },
+}
+
+#[cosmwasm_schema::cw_serde]
+struct NoteCallbackRequest {
+    receiver: String,
+    msg: Binary,
+}
+
+impl Transport for Polytone {
+    fn remote_hosts(&self, deps: Deps) -> Result<Vec<TruncatedChainId>, ServerError> {
+        let chains = POLYTONE_ROUTES
+            .keys(deps.storage, None, None, Order::Ascending)
+            .collect::<StdResult<Vec<_>>>()?;
+
+        Ok(chains
+            .into_iter()
+            .map(TruncatedChainId::from_string)
+            .collect::<Result<_, _>>()?)
+    }
+
+    fn send(
+        &self,
+        deps: Deps,
+        _env: &Env,
+        dest_chain: TruncatedChainId,
+        msg: Binary,
+        funds: Vec<Coin>,
+    ) -> Result<SubMsg, ServerError> {
+        ensure_no_funds(&dest_chain, &funds)?;
+        let route = POLYTONE_ROUTES.load(deps.storage, dest_chain.as_str())?;
+
+        let delivery: adapter::ExecuteMsg<ServerExecuteMsg> =
+            adapter::ExecuteMsg::Module(AdapterRequestMsg {
+                account_address: Some(route.relay_account),
+                request: ServerExecuteMsg::ReceivePolytone { msg },
+            });
+        let note_msg = NoteExecuteMsg::Execute {
+            msgs: vec![wasm_execute(route.remote_server, &delivery, vec![])?.into()],
+            callback: None,
+            timeout_seconds: POLYTONE_TIMEOUT_SECONDS.into(),
+        };
+
+        Ok(SubMsg::new(wasm_execute(route.note, &note_msg, vec![])?))
+    }
+}
+
+/// Transport over IBC channels opened by the mail server itself with version [`IBCMAIL_IBC_VERSION`].
+/// Packets carry an encoded [`ServerIbcMessage`](crate::server::msg::ServerIbcMessage)
+/// and are acknowledged with an [`IbcMailAck`](crate::server::msg::IbcMailAck).
+pub struct IbcPort;
+
+impl Transport for IbcPort {
+    fn remote_hosts(&self, deps: Deps) -> Result<Vec<TruncatedChainId>, ServerError> {
+        let chains = IBC_CHANNELS
+            .keys(deps.storage, None, None, Order::Ascending)
+            .collect::<StdResult<Vec<_>>>()?;
+
+        Ok(chains
+            .into_iter()
+            .map(TruncatedChainId::from_string)
+            .collect::<Result<_, _>>()?)
+    }
+
+    fn send(
+        &self,
+        deps: Deps,
+        env: &Env,
+        dest_chain: TruncatedChainId,
+        msg: Binary,
+        funds: Vec<Coin>,
+    ) -> Result<SubMsg, ServerError> {
+        ensure_no_funds(&dest_chain, &funds)?;
+        let channel_id = IBC_CHANNELS.load(deps.storage, dest_chain.as_str())?;
+
+        Ok(SubMsg::new(IbcMsg::SendPacket {
+            channel_id,
+            data: msg,
+            timeout: IbcTimeout::with_timestamp(
+                env.block.time.plus_seconds(IBC_PACKET_TIMEOUT_SECONDS),
+            ),
+        }))
+    }
+}
+
+/// Transport over ICS-20 transfers for chains that only support ibc-hooks.
+/// The transfer memo makes ibc-hooks deliver to the remote server on behalf of the route's relay account
+/// and report the outcome of the transfer back to this server.
+pub struct Ics20;
+
+/// Memo that ibc-hooks executes on the receiving chain.
+#[cosmwasm_schema::cw_serde]
+struct HookMemo {
+    wasm: HookExecute,
+    /// Contract that ibc-hooks on the sending chain reports the acknowledgement or timeout to
+    ibc_callback: String,
+}
+
+#[cosmwasm_schema::cw_serde]
+struct HookExecute {
+    contract: String,
+    msg: adapter::ExecuteMsg<ServerExecuteMsg>,
+}
+
+/// Encoded message of an ICS-20 transfer, passed to the transfer reply.
+#[cosmwasm_schema::cw_serde]
+pub struct Ics20TransferPayload {
+    pub channel: String,
+    pub msg: Binary,
+}
+
+impl Transport for Ics20 {
+    fn remote_hosts(&self, deps: Deps) -> Result<Vec<TruncatedChainId>, ServerError> {
+        let chains = ICS20_ROUTES
+            .keys(deps.storage, None, None, Order::Ascending)
+            .collect::<StdResult<Vec<_>>>()?;
+
+        Ok(chains
+            .into_iter()
+            .map(TruncatedChainId::from_string)
+            .collect::<Result<_, _>>()?)
+    }
+
+    fn send(
+        &self,
+        deps: Deps,
+        env: &Env,
+        dest_chain: TruncatedChainId,
+        msg: Binary,
+        funds: Vec<Coin>,
+    ) -> Result<SubMsg, ServerError> {
+        let route = ICS20_ROUTES.load(deps.storage, dest_chain.as_str())?;
+        // A transfer carries a single denom, the funds are added to those of the route
+        let mut amount = route.funds;
+        for coin in funds {
+            ensure!(
+                coin.denom == amount.denom,
+                ServerError::FundsNotTransferable {
+                    chain: dest_chain,
+                    funds: coin.to_string(),
+                }
+            );
+            amount.amount = amount
+                .amount
+                .checked_add(coin.amount)
+                .map_err(StdError::from)?;
+        }
+        let payload = to_json_binary(&Ics20TransferPayload {
+            channel: route.channel.clone(),
+            msg: msg.clone(),
+        })?;
+
+        let memo = HookMemo {
+            wasm: HookExecute {
+                contract: route.remote_server.clone(),
+                msg: adapter::ExecuteMsg::Module(AdapterRequestMsg {
+                    account_address: Some(route.relay_account),
+                    request: ServerExecuteMsg::ReceiveIcs20 { msg },
+                }),
+            },
+            ibc_callback: env.contract.address.to_string(),
+        };
+
+        // ibc-hooks requires the receiver of the transfer to be the contract it executes
+        let transfer = IbcMsg::Transfer {
+            channel_id: route.channel,
+            to_address: route.remote_server,
+            amount,
+            timeout: IbcTimeout::with_timestamp(env.block.time.plus_seconds(ICS20_TIMEOUT_SECONDS)),
+            memo: Some(to_json_string(&memo)?),
+        };
+
+        // ibc-hooks reports the outcome of a transfer by its sequence, which is only known from the reply
+        Ok(SubMsg::reply_on_success(transfer, ICS20_TRANSFER_REPLY_ID).with_payload(payload))
+    }
+
+    fn transfer_funds(
+        &self,
+        deps: Deps,
+        dest_chain: &TruncatedChainId,
+    ) -> Result<Option<Coin>, ServerError> {
+        let route = ICS20_ROUTES.load(deps.storage, dest_chain.as_str())?;
+
+        Ok(Some(route.funds))
+    }
+}
+
+/// Ensure that no `funds` go along with a message to `dest_chain` over a transport that can't transfer them.
+fn ensure_no_funds(dest_chain: &TruncatedChainId, funds: &[Coin]) -> Result<(), ServerError> {
+    ensure!(
+        funds.is_empty(),
+        ServerError::FundsNotTransferable {
+            chain: dest_chain.clone(),
+            funds: funds
+                .iter()
+                .map(ToString::to_string)
+                .collect::<Vec<_>>()
+                .join(", "),
+        }
+    );
+
+    Ok(())
+}
+
+/// Address that ibc-hooks executes as for transfers from `original_sender` arriving over the local `channel`.
+pub fn ibc_hooks_sender(api: &dyn Api, channel: &str, original_sender: &str) -> StdResult<Addr> {
+    let type_hash = Sha256::digest(b"ibc-wasm-hook-intermediary");
+    let address = Sha256::new()
+        .chain_update(type_hash)
+        .chain_update(format!("{channel}/{original_sender}"))
+        .finalize();
+
+    api.addr_humanize(&CanonicalAddr::from(address.as_slice()))
+}
+
+#[cfg(test)]
+mod tests {
+    use cosmwasm_std::testing::MockApi;
+
+    use super::*;
+
+    #[test]
+    fn derives_ibc_hooks_sender_per_channel_and_sender() {
+        let api = MockApi::default();
+        let sender = ibc_hooks_sender(&api, "channel-0", "remote-server").unwrap();
+
+        // ibc-hooks derives a module address from "<channel>/<original sender>"
+        let type_hash = Sha256::digest(b"ibc-wasm-hook-intermediary");
+        let mut preimage = type_hash.to_vec();
+        preimage.extend_from_slice(b"channel-0/remote-server");
+        let expected = api
+            .addr_humanize(&CanonicalAddr::from(Sha256::digest(&preimage).as_slice()))
+            .unwrap();
+        assert_eq!(sender, expected);
+
+        assert_ne!(
+            sender,
+            ibc_hooks_sender(&api, "channel-1", "remote-server").unwrap()
+        );
+        assert_ne!(
+            sender,
+            ibc_hooks_sender(&api, "channel-0", "other-server").unwrap()
+        );
+    }
+}
@@ -0,0 +1,434 @@
+//! Wire format of the messages exchanged between mail servers.
+//! Servers send in the highest version both sides support and decode every version they support,
+//! so chains running different server releases keep interoperating during upgrades.
+//! The chain of the sender is not part of the message, receiving servers take it from the transport.
+
+use cosmwasm_std::{from_json, to_json_binary, Binary};
+
+use crate::server::{error::ServerError, msg::ServerIbcMessage};
+
+/// Version of the bare message sent by servers without versioning, see [`v1`].
+pub const LEGACY_WIRE_VERSION: u32 = 1;
+
+/// Version this server sends in to servers that are not known to be older.
+pub const CURRENT_WIRE_VERSION: u32 = 2;
+
+/// Wire versions this server can encode and decode, oldest first.
+pub const SUPPORTED_WIRE_VERSIONS: &[u32] = &[LEGACY_WIRE_VERSION, CURRENT_WIRE_VERSION];
+
+/// [`ServerIbcMessage`] with the version it is encoded in and the versions its sender supports.
+#[cosmwasm_schema::cw_serde]
+pub struct ServerIbcEnvelope {
+    pub version: u32,
+    pub supported_versions: Vec<u32>,
+    pub msg: ServerIbcMessage,
+}
+
+/// Decoded message, with the supported versions of its sender if it advertised them.
+pub struct Decoded {
+    pub msg: ServerIbcMessage,
+    pub peer_versions: Option<Vec<u32>>,
+}
+
+/// Encode `msg` in wire `version`.
+/// Messages that servers of the legacy version can't understand fail with [`ServerError::UnsupportedWireVersion`].
+pub fn encode(msg: &ServerIbcMessage, version: u32) -> Result<Binary, ServerError> {
+    if version == LEGACY_WIRE_VERSION {
+        return Ok(to_json_binary(&v1::ServerIbcMessage::try_from(
+            msg.clone(),
+        )?)?);
+    }
+    if !SUPPORTED_WIRE_VERSIONS.contains(&version) {
+        return Err(ServerError::UnsupportedWireVersion(version));
+    }
+
+    Ok(to_json_binary(&ServerIbcEnvelope {
+        version,
+        supported_versions: SUPPORTED_WIRE_VERSIONS.to_vec(),
+        msg: msg.clone(),
+    })?)
+}
+
+/// Decode a message in any supported wire version.
+pub fn decode(data: &Binary) -> Result<Decoded, ServerError> {
+    match from_json::<ServerIbcEnvelope>(data) {
+        Ok(envelope) if SUPPORTED_WIRE_VERSIONS.contains(&envelope.version) => Ok(Decoded {
+            msg: envelope.msg,
+            peer_versions: Some(envelope.supported_versions),
+        }),
+        Ok(envelope) => Err(ServerError::UnsupportedWireVersion(envelope.version)),
+        Err(_) => Ok(Decoded {
+            msg: from_json::<v1::ServerIbcMessage>(data)?.into(),
+            peer_versions: None,
+        }),
+    }
+}
+
+/// Highest wire version supported by both this server and a peer supporting `peer_versions`.
+pub fn highest_common_version(peer_versions: &[u32]) -> u32 {
+    SUPPORTED_WIRE_VERSIONS
+        .iter()
+        .rev()
+        .find(|version| peer_versions.contains(version))
+        .copied()
+        .unwrap_or(LEGACY_WIRE_VERSION)
+}
+
+/// Messages in the shape that servers without versioning encode and decode.
+/// Their types deny unknown fields, so only what they define can be sent to them.
+pub mod v1 {
+    use abstract_adapter::objects::TruncatedChainId;
+    use abstract_adapter::std::objects::{namespace::Namespace, AccountId};
+    use cosmwasm_std::Timestamp;
+
+    use crate::{server::error::ServerError, MessageHash, Route};
+
+    #[cosmwasm_schema::cw_serde]
+    pub enum ServerIbcMessage {
+        RouteMessage { msg: IbcMailMessage, header: Header },
+    }
+
+    #[cosmwasm_schema::cw_serde]
+    pub struct IbcMailMessage {
+        pub id: MessageHash,
+        pub sender: Sender,
+        pub version: String,
+        pub timestamp: Timestamp,
+        pub message: Message,
+    }
+
+    #[cosmwasm_schema::cw_serde]
+    pub struct Message {
+        pub recipient: Recipient,
+        pub subject: String,
+        pub body: String,
+    }
+
+    #[cosmwasm_schema::cw_serde]
+    pub struct Header {
+        pub current_hop: u32,
+        pub route: Route,
+    }
+
+    #[cosmwasm_schema::cw_serde]
+    pub enum Recipient {
+        Account {
+            id: AccountId,
+            chain: Option<TruncatedChainId>,
+        },
+        Namespace {
+            namespace: Namespace,
+            chain: Option<TruncatedChainId>,
+        },
+    }
+
+    #[cosmwasm_schema::cw_serde]
+    pub enum Sender {
+        Account {
+            id: AccountId,
+            chain: Option<TruncatedChainId>,
+        },
+    }
+
+    fn unsupported() -> ServerError {
+        ServerError::UnsupportedWireVersion(super::LEGACY_WIRE_VERSION)
+    }
+
+    impl TryFrom<crate::server::msg::ServerIbcMessage> for ServerIbcMessage {
+        type Error = ServerError;
+
+        /// Batches have no legacy encoding, they are sent message by message instead.
+        fn try_from(msg: crate::server::msg::ServerIbcMessage) -> Result<Self, Self::Error> {
+            match msg {
+                crate::server::msg::ServerIbcMessage::RouteMessage { msg, header } => {
+                    Ok(ServerIbcMessage::RouteMessage {
+                        msg: msg.try_into()?,
+                        header: header.try_into()?,
+                    })
+                }
+                crate::server::msg::ServerIbcMessage::RouteMessages { .. } => Err(unsupported()),
+            }
+        }
+    }
+
+    impl TryFrom<crate::IbcMailMessage> for IbcMailMessage {
+        type Error = ServerError;
+
+        fn try_from(msg: crate::IbcMailMessage) -> Result<Self, Self::Error> {
+            Ok(IbcMailMessage {
+                id: msg.id,
+                sender: msg.sender.try_into()?,
+                version: msg.version,
+                timestamp: msg.timestamp,
+                message: Message {
+                    recipient: msg.message.recipient.try_into()?,
+                    subject: msg.message.subject,
+                    body: msg.message.body,
+                },
+            })
+        }
+    }
+
+    impl TryFrom<crate::Header> for Header {
+        type Error = ServerError;
+
+        /// Legacy servers don't enforce hop limits or expiry and keep no trace, so those are left out.
+        /// Prepaid relay fees would be lost on the way, so such messages are not sent.
+        fn try_from(header: crate::Header) -> Result<Self, Self::Error> {
+            if !header.relay_fees.is_empty() {
+                return Err(unsupported());
+            }
+
+            Ok(Header {
+                current_hop: header.current_hop,
+                route: header.route,
+            })
+        }
+    }
+
+    impl TryFrom<crate::Recipient> for Recipient {
+        type Error = ServerError;
+
+        fn try_from(recipient: crate::Recipient) -> Result<Self, Self::Error> {
+            match recipient {
+                crate::Recipient::Account { id, chain } => Ok(Recipient::Account { id, chain }),
+                crate::Recipient::Namespace { namespace, chain } => {
+                    Ok(Recipient::Namespace { namespace, chain })
+                }
+                _ => Err(unsupported()),
+            }
+        }
+    }
+
+    impl TryFrom<crate::Sender> for Sender {
+        type Error = ServerError;
+
+        fn try_from(sender: crate::Sender) -> Result<Self, Self::Error> {
+            match sender {
+                crate::Sender::Account { id, chain } => Ok(Sender::Account { id, chain }),
+                _ => Err(unsupported()),
+            }
+        }
+    }
+
+    impl From<ServerIbcMessage> for crate::server::msg::ServerIbcMessage {
+        fn from(msg: ServerIbcMessage) -> Self {
+            let ServerIbcMessage::RouteMessage { msg, header } = msg;
+            crate::server::msg::ServerIbcMessage::RouteMessage {
+                msg: msg.into(),
+                header: crate::Header {
+                    current_hop: header.current_hop,
+                    ..crate::Header::new(header.route)
+                },
+            }
+        }
+    }
+
+    impl From<IbcMailMessage> for crate::IbcMailMessage {
+        fn from(msg: IbcMailMessage) -> Self {
+            crate::IbcMailMessage {
+                id: msg.id,
+                sender: match msg.sender {
+                    Sender::Account { id, chain } => crate::Sender::Account { id, chain },
+                },
+                version: msg.version,
+                timestamp: msg.timestamp,
+                message: crate::Message {
+                    recipient: match msg.message.recipient {
+                        Recipient::Account { id, chain } => crate::Recipient::Account { id, chain },
+                        Recipient::Namespace { namespace, chain } => {
+                            crate::Recipient::Namespace { namespace, chain }
+                        }
+                    },
+                    subject: msg.message.subject,
+                    body: msg.message.body,
+                },
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use abstract_adapter::std::objects::{account::AccountTrace, AccountId};
+
+    use super::*;
+    use crate::{Header, IbcMailMessage, Message, Recipient, Sender, EMAIL_VERSION};
+
+    fn route_message() -> ServerIbcMessage {
+        ServerIbcMessage::RouteMessage {
+            msg: IbcMailMessage {
+                id: "test-id".to_string(),
+                sender: Sender::account(AccountId::local(1), None),
+                message: Message::new(
+                    Recipient::account(AccountId::local(2), None),
+                    "test-subject",
+                    "test-body",
+                ),
+                timestamp: Default::default(),
+                version: EMAIL_VERSION.to_string(),
+            },
+            header: Header::new(AccountTrace::Local),
+        }
+    }
+
+    /// Message types as released before wire versioning, which deny unknown fields.
+    mod released {
+        use abstract_adapter::objects::TruncatedChainId;
+        use abstract_adapter::std::objects::{
+            account::AccountTrace, namespace::Namespace, AccountId,
+        };
+        use cosmwasm_std::Timestamp;
+
+        #[cosmwasm_schema::cw_serde]
+        pub enum ServerIbcMessage {
+            RouteMessage { msg: IbcMailMessage, header: Header },
+        }
+
+        #[cosmwasm_schema::cw_serde]
+        pub struct IbcMailMessage {
+            pub id: String,
+            pub sender: Sender,
+            pub version: String,
+            pub timestamp: Timestamp,
+            pub message: Message,
+        }
+
+        #[cosmwasm_schema::cw_serde]
+        pub struct Message {
+            pub recipient: Recipient,
+            pub subject: String,
+            pub body: String,
+        }
+
+        #[cosmwasm_schema::cw_serde]
+        pub struct Header {
+            pub current_hop: u32,
+            pub route: AccountTrace,
+        }
+
+        #[cosmwasm_schema::cw_serde]
+        pub enum Recipient {
+            Account {
+                id: AccountId,
+                chain: Option<TruncatedChainId>,
+            },
+            Namespace {
+                namespace: Namespace,
+                chain: Option<TruncatedChainId>,
+            },
+        }
+
+        #[cosmwasm_schema::cw_serde]
+        pub enum Sender {
+            Account {
+                id: AccountId,
+                chain: Option<TruncatedChainId>,
+            },
+        }
+    }
+
+    #[test]
+    fn released_servers_decode_legacy_messages() {
+        let mut msg = route_message();
+        if let ServerIbcMessage::RouteMessage { header, .. } = &mut msg {
+            header.max_hops = Some(3);
+            header.current_hop = 1;
+        }
+
+        let legacy = encode(&msg, LEGACY_WIRE_VERSION).unwrap();
+        let released: released::ServerIbcMessage = from_json(&legacy).unwrap();
+        let released::ServerIbcMessage::RouteMessage {
+            msg: released_msg,
+            header,
+        } = released;
+        assert_eq!(released_msg.id, "test-id");
+        assert_eq!(header.current_hop, 1);
+
+        // Released servers send the bare message
+        let decoded = decode(
+            &to_json_binary(&released::ServerIbcMessage::RouteMessage {
+                msg: released_msg,
+                header,
+            })
+            .unwrap(),
+        )
+        .unwrap();
+        assert_eq!(decoded.peer_versions, None);
+        let ServerIbcMessage::RouteMessage { header, .. } = decoded.msg else {
+            panic!("expected a single message");
+        };
+        assert_eq!(header.current_hop, 1);
+        assert_eq!(header.max_hops, None);
+    }
+
+    #[test]
+    fn messages_without_legacy_shape_are_not_encoded_as_legacy() {
+        let unsupported = Err(ServerError::UnsupportedWireVersion(LEGACY_WIRE_VERSION));
+
+        let ServerIbcMessage::RouteMessage { msg, header } = route_message() else {
+            unreachable!()
+        };
+        let batch = ServerIbcMessage::RouteMessages {
+            msgs: vec![(msg.clone(), header.clone())],
+        };
+        assert_eq!(encode(&batch, LEGACY_WIRE_VERSION), unsupported);
+
+        let bounce = ServerIbcMessage::RouteMessage {
+            msg: IbcMailMessage {
+                sender: Sender::Server {
+                    chain: "juno".parse().unwrap(),
+                },
+                ..msg.clone()
+            },
+            header: header.clone(),
+        };
+        assert_eq!(encode(&bounce, LEGACY_WIRE_VERSION), unsupported);
+
+        let prepaid = ServerIbcMessage::RouteMessage {
+            msg,
+            header: Header {
+                relay_fees: vec![("juno".parse().unwrap(), cosmwasm_std::coin(1, "ujuno"))],
+                ..header
+            },
+        };
+        assert_eq!(encode(&prepaid, LEGACY_WIRE_VERSION), unsupported);
+    }
+
+    #[test]
+    fn decodes_legacy_and_versioned_messages() {
+        let msg = route_message();
+
+        let legacy = decode(&encode(&msg, LEGACY_WIRE_VERSION).unwrap()).unwrap();
+        assert_eq!(legacy.msg, msg);
+        assert_eq!(legacy.peer_versions, None);
+
+        let latest = *SUPPORTED_WIRE_VERSIONS.last().unwrap();
+        let versioned = decode(&encode(&msg, latest).unwrap()).unwrap();
+        assert_eq!(versioned.msg, msg);
+        assert_eq!(
+            versioned.peer_versions,
+            Some(SUPPORTED_WIRE_VERSIONS.to_vec())
+        );
+
+        assert_eq!(
+            encode(&msg, latest + 1),
+            Err(ServerError::UnsupportedWireVersion(latest + 1))
+        );
+    }
+
+    #[test]
+    fn picks_highest_common_version() {
+        let latest = *SUPPORTED_WIRE_VERSIONS.last().unwrap();
+
+        assert_eq!(
+            highest_common_version(&[LEGACY_WIRE_VERSION, latest]),
+            latest
+        );
+        assert_eq!(
+            highest_common_version(&[LEGACY_WIRE_VERSION, latest + 1]),
+            LEGACY_WIRE_VERSION
+        );
+        assert_eq!(highest_common_version(&[]), LEGACY_WIRE_VERSION);
+    }
+}
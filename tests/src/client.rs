@@ -1,16 +1,26 @@
-use abstract_app::objects::{namespace::Namespace, AccountId};
-use abstract_client::{AbstractClient, Application, Publisher};
+use abstract_app::objects::{account::AccountTrace, namespace::Namespace, AccountId};
+use abstract_client::{AbstractClient, Account, Application, Publisher};
+use cosmwasm_std::Binary;
 use cw_orch::{anyhow, prelude::*};
 use speculoos::prelude::*;
 
 // Use prelude to get all the necessary imports
 use client::{contract::interface::ClientInterface, msg::ClientInstantiateMsg, *};
 use ibcmail::{
-    server::msg::ServerInstantiateMsg, IbcMailMessage, Message, Recipient, Sender,
-    IBCMAIL_NAMESPACE, IBCMAIL_SERVER_ID,
+    server::{
+        msg::{ServerIbcMessage, ServerInstantiateMsg},
+        wire::{self, SUPPORTED_WIRE_VERSIONS},
+    },
+    Header, IbcMailMessage, Message, Recipient, Sender, EMAIL_VERSION, IBCMAIL_NAMESPACE,
+    IBCMAIL_SERVER_ID,
 };
 use server::ServerInterface;
 
+use crate::{
+    receiver::{interface::ReceiverInterface, ReceiverInstantiateMsg},
+    TEST_NAMESPACE,
+};
+
 struct TestEnv<Env: CwEnv> {
     env: Env,
     abs: AbstractClient<Env>,
@@ -65,6 +75,20 @@ impl<Env: CwEnv> TestEnv<Env> {
             client2: app2,
         })
     }
+
+    /// Publish the test mail receiver and install it on `account`
+    fn install_receiver(
+        &self,
+        account: &Account<Env>,
+    ) -> anyhow::Result<Application<Env, ReceiverInterface<Env>>> {
+        let namespace = Namespace::new(TEST_NAMESPACE)?;
+        let publisher_acc = self
+            .abs
+            .fetch_or_build_account(namespace.clone(), |builder| builder.namespace(namespace))?;
+        Publisher::new(&publisher_acc)?.publish_app::<ReceiverInterface<_>>()?;
+
+        Ok(account.install_app::<ReceiverInterface<_>>(&ReceiverInstantiateMsg {}, &[])?)
+    }
 }
 
 fn create_test_message(from: AccountId, to: AccountId) -> IbcMailMessage {
@@ -77,16 +101,24 @@ fn create_test_message(from: AccountId, to: AccountId) -> IbcMailMessage {
             body: "test-body".to_string(),
         },
         timestamp: Default::default(),
-        version: "0.0.1".to_string(),
+        version: EMAIL_VERSION.to_string(),
     }
 }
 
+/// Encode `msg` like a server of this release sends it to another server.
+fn encode_server_msg(msg: &ServerIbcMessage) -> anyhow::Result<Binary> {
+    Ok(wire::encode(msg, *SUPPORTED_WIRE_VERSIONS.last().unwrap())?)
+}
+
 mod receive_msg {
     use speculoos::assert_that;
 
     use ibcmail::{MessageStatus, IBCMAIL_SERVER_ID};
 
+    use server::ServerQueryMsgFns;
+
     use super::*;
+    use crate::receiver::{ReceiverQueryMsgFns, RECEIVER_ID};
 
     /// Sending a message from the same account to the same account
     /// TODO: this test is failing because of an issue with state management...
@@ -114,7 +146,9 @@ mod receive_msg {
             .clone();
 
         println!("app_account_id: {:?}", app.account().id());
-        let res = app.call_as(&server_addr).receive_message(msg);
+        let res = app
+            .call_as(&server_addr)
+            .receive_message(Header::new(AccountTrace::Local), msg);
 
         assert_that!(res).is_ok();
 
@@ -134,7 +168,7 @@ mod receive_msg {
         let app_account_id = app.account().id().unwrap();
 
         let msg = create_test_message(app_account_id.clone(), app_account_id.clone());
-        let res = app.receive_message(msg);
+        let res = app.receive_message(Header::new(AccountTrace::Local), msg);
 
         assert_that!(res)
             .is_err()
@@ -142,16 +176,97 @@ mod receive_msg {
 
         Ok(())
     }
+
+    #[test]
+    fn client_rejects_mail_for_other_modules() -> anyhow::Result<()> {
+        let mock = MockBech32::new("mock");
+        let env = TestEnv::setup(mock)?;
+        let server = ServerInterface::new(IBCMAIL_SERVER_ID, env.env.clone());
+        let app = &env.client1;
+
+        let mut msg = create_test_message(env.client2.account().id()?, app.account().id()?);
+        msg.message.recipient = Recipient::module(app.account().id()?, RECEIVER_ID, None);
+        let res = app
+            .call_as(&server.address()?)
+            .receive_message(Header::new(AccountTrace::Local), msg);
+
+        assert_that!(res).is_err().matches(|e| {
+            e.root()
+                .to_string()
+                .contains("Recipient is not the current account")
+        });
+
+        Ok(())
+    }
+
+    #[test]
+    fn mail_receiver_module_receives_mail() -> anyhow::Result<()> {
+        let mock = MockBech32::new("mock");
+        let env = TestEnv::setup(mock)?;
+        let account = env.client2.account();
+        let receiver = env.install_receiver(account)?;
+
+        let msg = Message::new(
+            Recipient::module(account.id()?, RECEIVER_ID, None),
+            "test-subject",
+            "test-body",
+        );
+        env.client1.send_message(msg, None)?;
+
+        let received = receiver.received()?;
+        assert_that!(received).has_length(1);
+        assert_that!(received[0].message.subject).is_equal_to("test-subject".to_string());
+
+        // The mail went to the receiver module, not to the mail client
+        let messages = env
+            .client2
+            .list_messages(MessageStatus::Received, None, None, None)?;
+        assert_that!(messages.messages).is_empty();
+
+        Ok(())
+    }
+    #[test]
+    fn mail_for_missing_module_is_held_until_it_is_installed() -> anyhow::Result<()> {
+        let mock = MockBech32::new("mock");
+        let env = TestEnv::setup(mock)?;
+        let server = ServerInterface::new(IBCMAIL_SERVER_ID, env.env.clone());
+        let account = env.client2.account();
+
+        let msg = Message::new(
+            Recipient::module(account.id()?, RECEIVER_ID, None),
+            "test-subject",
+            "test-body",
+        );
+        env.client1.send_message(msg, None)?;
+
+        let held = server.held_mail(account.address()?.to_string(), None, None)?;
+        assert_that!(held.messages).has_length(1);
+
+        let receiver = env.install_receiver(account)?;
+        env.client2.claim_mail(None)?;
+
+        let received = receiver.received()?;
+        assert_that!(received).has_length(1);
+        assert_that!(received[0].message.subject).is_equal_to("test-subject".to_string());
+
+        Ok(())
+    }
 }
 
 mod send_msg {
     use std::str::FromStr;
 
     use abstract_app::objects::TruncatedChainId;
-    use abstract_app::{objects::account::AccountTrace, std::registry::ExecuteMsgFns};
+    use abstract_app::std::{app, registry::ExecuteMsgFns};
+    use cosmwasm_std::coin;
     use cw_orch_interchain::prelude::*;
 
-    use ibcmail::{server::error::ServerError, Message, MessageStatus, IBCMAIL_CLIENT_ID};
+    use ibcmail::{
+        client::msg::{ClientExecuteMsg, MessageFilter},
+        server::{error::ServerError, msg::ServerExecuteMsgFns},
+        Message, MessageStatus, IBCMAIL_CLIENT_ID,
+    };
+    use server::ServerQueryMsgFns;
 
     use super::*;
 
@@ -176,6 +291,48 @@ mod send_msg {
         Ok(())
     }
 
+    #[test]
+    fn can_filter_received_messages_by_sender_kind() -> anyhow::Result<()> {
+        let mock = MockBech32::new("mock");
+        let env = TestEnv::setup(mock)?;
+        let client1 = env.client1;
+        let client2 = env.client2;
+
+        let msg = Message::new(
+            Recipient::account(client2.account().id()?, None),
+            "test-subject",
+            "test-body",
+        );
+        client1.send_message(msg, None)?;
+
+        let from_modules = client2.list_messages(
+            MessageStatus::Received,
+            Some(MessageFilter {
+                from_module: Some(true),
+                ..Default::default()
+            }),
+            None,
+            None,
+        )?;
+        assert_that!(from_modules.messages).is_empty();
+
+        let from_accounts = client2.list_messages(
+            MessageStatus::Received,
+            Some(MessageFilter {
+                from_module: Some(false),
+                ..Default::default()
+            }),
+            None,
+            None,
+        )?;
+        assert_that!(from_accounts.messages).has_length(1);
+        let sender_id = client1.account().id()?;
+        assert_that!(from_accounts.messages[0].sender)
+            .matches(|sender| matches!(sender, Sender::Account { id, .. } if id == &sender_id));
+
+        Ok(())
+    }
+
     #[test]
     fn can_send_local_message_to_namespace() -> anyhow::Result<()> {
         // Create a sender and mock env
@@ -382,9 +539,2530 @@ mod send_msg {
             neutron_mail_client.list_messages(MessageStatus::Received, None, None, None)?;
         assert_that!(neutron_mail_client_messages.messages).has_length(1);
 
+        // Every server on the route stamped the message
+        let trace =
+            neutron_mail_client.trace(neutron_mail_client_messages.messages[0].id.clone())?;
+        assert_that!(trace.trace).has_length(3);
+
         // let juno_messages = neutron_client.list_messages(None, None, None)?;
         // assert_that!(juno_messages.messages).has_length(1);
 
         Ok(())
     }
+
+    #[test]
+    fn can_reply_over_return_route() -> anyhow::Result<()> {
+        let interchain = MockBech32InterchainEnv::new(vec![
+            ("juno-1", "juno18k2uq7srsr8lwrae6zr0qahpn29rsp7tw83nyx"),
+            (
+                "archway-1",
+                "archway18k2uq7srsr8lwrae6zr0qahpn29rsp7td7wvfd",
+            ),
+            (
+                "neutron-1",
+                "neutron18k2uq7srsr8lwrae6zr0qahpn29rsp7tu2m2ea",
+            ),
+        ]);
+
+        let arch_env = TestEnv::setup(interchain.get_chain("archway-1")?)?;
+        let juno_env = TestEnv::setup(interchain.get_chain("juno-1")?)?;
+        let neutron_env = TestEnv::setup(interchain.get_chain("neutron-1")?)?;
+
+        arch_env.abs.connect_to(&juno_env.abs, &interchain)?;
+        juno_env.abs.connect_to(&neutron_env.abs, &interchain)?;
+
+        let arch_client = arch_env.client1;
+        let neutron_client = neutron_env.client1;
+
+        let arch_to_neutron_msg = Message::new(
+            Recipient::account(
+                neutron_client.account().id()?,
+                Some(TruncatedChainId::from_str("neutron")?),
+            ),
+            "test-subject",
+            "test-body",
+        );
+        let res = arch_client.send_message(
+            arch_to_neutron_msg,
+            Some(AccountTrace::Remote(vec![
+                TruncatedChainId::from_str("juno")?,
+                TruncatedChainId::from_str("neutron")?,
+            ])),
+        )?;
+        interchain.await_and_check_packets("archway-1", res)?;
+
+        let received = neutron_client.list_messages(MessageStatus::Received, None, None, None)?;
+        let return_route = neutron_client.return_route(received.messages[0].id.clone())?;
+        assert_that!(return_route.route).is_equal_to(AccountTrace::Remote(vec![
+            TruncatedChainId::from_str("neutron")?,
+            TruncatedChainId::from_str("juno")?,
+            TruncatedChainId::from_str("archway")?,
+        ]));
+
+        // Neutron has no route to archway of its own, the reply uses the return route
+        let reply = Message::new(
+            Recipient::account(
+                arch_client.account().id()?,
+                Some(TruncatedChainId::from_str("archway")?),
+            ),
+            "re: test-subject",
+            "test-body",
+        );
+        let res = neutron_client.send_message(reply, None)?;
+        interchain.await_and_check_packets("neutron-1", res)?;
+
+        let arch_messages = arch_client.list_messages(MessageStatus::Received, None, None, None)?;
+        assert_that!(arch_messages.messages).has_length(1);
+
+        Ok(())
+    }
+
+    #[test]
+    fn preflight_bounces_mail_for_unclaimed_remote_namespace() -> anyhow::Result<()> {
+        let interchain = MockBech32InterchainEnv::new(vec![
+            ("juno-1", "juno18k2uq7srsr8lwrae6zr0qahpn29rsp7tw83nyx"),
+            (
+                "archway-1",
+                "archway18k2uq7srsr8lwrae6zr0qahpn29rsp7td7wvfd",
+            ),
+        ]);
+
+        let arch_env = TestEnv::setup(interchain.get_chain("archway-1")?)?;
+        let juno_env = TestEnv::setup(interchain.get_chain("juno-1")?)?;
+
+        arch_env.abs.connect_to(&juno_env.abs, &interchain)?;
+
+        let arch_admin = arch_env
+            .abs
+            .fetch_or_build_account(Namespace::new(IBCMAIL_NAMESPACE)?, |builder| builder)?;
+        let arch_server = ServerInterface::new(IBCMAIL_SERVER_ID, arch_env.env.clone());
+        arch_server.call_as(&arch_admin.address()?).update_config(
+            None,
+            None,
+            None,
+            None,
+            Some(true),
+        )?;
+
+        let msg = Message::new(
+            Recipient::namespace(
+                Namespace::new("nonexistent")?,
+                Some(TruncatedChainId::from_str("juno")?),
+            ),
+            "test-subject",
+            "test-body",
+        );
+        let res = arch_env.client1.send_message(msg, None)?;
+        interchain.await_and_check_packets("archway-1", res)?;
+
+        // The message never left archway, the sender got a bounce instead
+        let arch_messages =
+            arch_env
+                .client1
+                .list_messages(MessageStatus::Received, None, None, None)?;
+        assert_that!(arch_messages.messages).has_length(1);
+        assert_that!(arch_messages.messages[0].message.subject)
+            .is_equal_to("Undeliverable: test-subject".to_string());
+
+        Ok(())
+    }
+
+    #[test]
+    fn preflight_sends_mail_that_the_remote_server_holds() -> anyhow::Result<()> {
+        let interchain = MockBech32InterchainEnv::new(vec![
+            ("juno-1", "juno18k2uq7srsr8lwrae6zr0qahpn29rsp7tw83nyx"),
+            (
+                "archway-1",
+                "archway18k2uq7srsr8lwrae6zr0qahpn29rsp7td7wvfd",
+            ),
+        ]);
+
+        let arch_env = TestEnv::setup(interchain.get_chain("archway-1")?)?;
+        let juno_env = TestEnv::setup(interchain.get_chain("juno-1")?)?;
+
+        arch_env.abs.connect_to(&juno_env.abs, &interchain)?;
+
+        let arch_server = ServerInterface::new(IBCMAIL_SERVER_ID, arch_env.env.clone());
+        let arch_admin = arch_env
+            .abs
+            .fetch_or_build_account(Namespace::new(IBCMAIL_NAMESPACE)?, |builder| builder)?;
+        arch_server.call_as(&arch_admin.address()?).update_config(
+            None,
+            None,
+            None,
+            None,
+            Some(true),
+        )?;
+
+        // The recipient has no mail client yet
+        let juno_acc = juno_env.abs.account_builder().build()?;
+        let msg = Message::new(
+            Recipient::account(juno_acc.id()?, Some(TruncatedChainId::from_str("juno")?)),
+            "test-subject",
+            "test-body",
+        );
+        let res = arch_env.client1.send_message(msg, None)?;
+        interchain.await_and_check_packets("archway-1", res)?;
+
+        let juno_server = ServerInterface::new(IBCMAIL_SERVER_ID, juno_env.env.clone());
+        let held = juno_server.held_mail(juno_acc.address()?.to_string(), None, None)?;
+        assert_that!(held.messages).has_length(1);
+
+        let arch_messages =
+            arch_env
+                .client1
+                .list_messages(MessageStatus::Received, None, None, None)?;
+        assert_that!(arch_messages.messages).is_empty();
+
+        Ok(())
+    }
+
+    #[test]
+    fn can_send_batch_of_remote_and_local_messages() -> anyhow::Result<()> {
+        let interchain = MockBech32InterchainEnv::new(vec![
+            ("juno-1", "juno18k2uq7srsr8lwrae6zr0qahpn29rsp7tw83nyx"),
+            (
+                "archway-1",
+                "archway18k2uq7srsr8lwrae6zr0qahpn29rsp7td7wvfd",
+            ),
+        ]);
+
+        let arch_env = TestEnv::setup(interchain.get_chain("archway-1")?)?;
+        let juno_env = TestEnv::setup(interchain.get_chain("juno-1")?)?;
+
+        arch_env.abs.connect_to(&juno_env.abs, &interchain)?;
+
+        let juno = TruncatedChainId::from_str("juno")?;
+        let msgs = vec![
+            Message::new(
+                Recipient::account(juno_env.client1.account().id()?, Some(juno.clone())),
+                "test-subject",
+                "test-body",
+            ),
+            Message::new(
+                Recipient::account(juno_env.client2.account().id()?, Some(juno)),
+                "test-subject",
+                "test-body",
+            ),
+            Message::new(
+                Recipient::account(arch_env.client2.account().id()?, None),
+                "test-subject",
+                "test-body",
+            ),
+        ];
+
+        let res = arch_env.client1.send_messages(msgs)?;
+        interchain.await_and_check_packets("archway-1", res)?;
+
+        for client in [&juno_env.client1, &juno_env.client2, &arch_env.client2] {
+            let messages = client.list_messages(MessageStatus::Received, None, None, None)?;
+            assert_that!(messages.messages).has_length(1);
+        }
+
+        let sent = arch_env
+            .client1
+            .list_messages(MessageStatus::Sent, None, None, None)?;
+        assert_that!(sent.messages).has_length(3);
+
+        Ok(())
+    }
+
+    #[test]
+    fn rejected_messages_of_batch_are_not_listed_as_sent() -> anyhow::Result<()> {
+        let mock = MockBech32::new("mock");
+        let env = TestEnv::setup(mock)?;
+
+        let msgs = vec![
+            Message::new(
+                Recipient::account(env.client2.account().id()?, None),
+                "test-subject",
+                "test-body",
+            ),
+            // neutron is not reachable
+            Message::new(
+                Recipient::account(
+                    env.client2.account().id()?,
+                    Some(TruncatedChainId::from_str("neutron")?),
+                ),
+                "test-subject",
+                "test-body",
+            ),
+        ];
+        env.client1.send_messages(msgs)?;
+
+        let received = env
+            .client2
+            .list_messages(MessageStatus::Received, None, None, None)?;
+        assert_that!(received.messages).has_length(1);
+
+        let sent = env
+            .client1
+            .list_messages(MessageStatus::Sent, None, None, None)?;
+        assert_that!(sent.messages).has_length(1);
+        assert_that!(sent.messages[0].message.recipient)
+            .is_equal_to(Recipient::account(env.client2.account().id()?, None));
+
+        Ok(())
+    }
+
+    #[test]
+    fn unspent_funds_of_batch_are_returned() -> anyhow::Result<()> {
+        let mock = MockBech32::new("mock");
+        let env = TestEnv::setup(mock)?;
+        let sender = env.env.sender_addr();
+        env.env.add_balance(&sender, vec![coin(50, "ucosm")])?;
+
+        let msgs = vec![
+            Message::new(
+                Recipient::account(env.client2.account().id()?, None),
+                "test-subject",
+                "test-body",
+            ),
+            // neutron is not reachable
+            Message::new(
+                Recipient::account(
+                    env.client2.account().id()?,
+                    Some(TruncatedChainId::from_str("neutron")?),
+                ),
+                "test-subject",
+                "test-body",
+            ),
+        ];
+        env.env.execute(
+            &app::ExecuteMsg::<ClientExecuteMsg>::Module(ClientExecuteMsg::SendMessages(msgs)),
+            &[coin(50, "ucosm")],
+            &env.client1.address()?,
+        )?;
+
+        let received = env
+            .client2
+            .list_messages(MessageStatus::Received, None, None, None)?;
+        assert_that!(received.messages).has_length(1);
+        assert_that!(env.env.query_balance(&sender, "ucosm")?.u128()).is_equal_to(50);
+
+        Ok(())
+    }
+
+    #[test]
+    fn send_over_unreachable_hop_fails() -> anyhow::Result<()> {
+        let interchain = MockBech32InterchainEnv::new(vec![
+            ("juno-1", "juno18k2uq7srsr8lwrae6zr0qahpn29rsp7tw83nyx"),
+            (
+                "archway-1",
+                "archway18k2uq7srsr8lwrae6zr0qahpn29rsp7td7wvfd",
+            ),
+        ]);
+
+        let arch_env = TestEnv::setup(interchain.get_chain("archway-1")?)?;
+        let juno_env = TestEnv::setup(interchain.get_chain("juno-1")?)?;
+
+        arch_env.abs.connect_to(&juno_env.abs, &interchain)?;
+
+        let msg = Message::new(
+            Recipient::account(
+                juno_env.client1.account().id()?,
+                Some(TruncatedChainId::from_str("juno")?),
+            ),
+            "test-subject",
+            "test-body",
+        );
+
+        // neutron is not connected to archway
+        let res = arch_env.client1.send_message(
+            msg,
+            Some(AccountTrace::Remote(vec![
+                TruncatedChainId::from_str("neutron")?,
+                TruncatedChainId::from_str("juno")?,
+            ])),
+        );
+
+        assert_that!(res)
+            .is_err()
+            .matches(|e| e.root().to_string().contains("neutron is not reachable"));
+
+        Ok(())
+    }
+
+    #[test]
+    fn can_send_remote_message_over_discovered_route() -> anyhow::Result<()> {
+        let interchain = MockBech32InterchainEnv::new(vec![
+            ("juno-1", "juno18k2uq7srsr8lwrae6zr0qahpn29rsp7tw83nyx"),
+            (
+                "archway-1",
+                "archway18k2uq7srsr8lwrae6zr0qahpn29rsp7td7wvfd",
+            ),
+            (
+                "neutron-1",
+                "neutron18k2uq7srsr8lwrae6zr0qahpn29rsp7tu2m2ea",
+            ),
+        ]);
+
+        let arch_env = TestEnv::setup(interchain.get_chain("archway-1")?)?;
+        let juno_env = TestEnv::setup(interchain.get_chain("juno-1")?)?;
+        let neutron_env = TestEnv::setup(interchain.get_chain("neutron-1")?)?;
+
+        arch_env.abs.connect_to(&juno_env.abs, &interchain)?;
+        juno_env.abs.connect_to(&neutron_env.abs, &interchain)?;
+
+        // Let the archway server know that neutron is reachable through juno
+        let arch_admin = arch_env
+            .abs
+            .fetch_or_build_account(Namespace::new(IBCMAIL_NAMESPACE)?, |builder| builder)?;
+        let arch_server = ServerInterface::new(IBCMAIL_SERVER_ID, arch_env.env.clone());
+        arch_server
+            .call_as(&arch_admin.address()?)
+            .update_route_table(
+                vec![(
+                    TruncatedChainId::from_str("juno")?,
+                    vec![TruncatedChainId::from_str("neutron")?],
+                )],
+                vec![],
+            )?;
+
+        let arch_client = arch_env.client1;
+        let neutron_client = neutron_env.client1;
+
+        let arch_to_neutron_msg = Message::new(
+            Recipient::account(
+                neutron_client.account().id()?,
+                Some(TruncatedChainId::from_str("neutron")?),
+            ),
+            "test-subject",
+            "test-body",
+        );
+
+        let res = arch_client.send_message(arch_to_neutron_msg, None)?;
+        interchain.await_and_check_packets("archway-1", res)?;
+
+        let neutron_messages =
+            neutron_client.list_messages(MessageStatus::Received, None, None, None)?;
+        assert_that!(neutron_messages.messages).has_length(1);
+
+        Ok(())
+    }
+    #[test]
+    fn send_over_blocked_link_fails() -> anyhow::Result<()> {
+        let interchain = MockBech32InterchainEnv::new(vec![
+            ("juno-1", "juno18k2uq7srsr8lwrae6zr0qahpn29rsp7tw83nyx"),
+            (
+                "archway-1",
+                "archway18k2uq7srsr8lwrae6zr0qahpn29rsp7td7wvfd",
+            ),
+        ]);
+
+        let arch_env = TestEnv::setup(interchain.get_chain("archway-1")?)?;
+        let juno_env = TestEnv::setup(interchain.get_chain("juno-1")?)?;
+
+        arch_env.abs.connect_to(&juno_env.abs, &interchain)?;
+
+        let arch_admin = arch_env
+            .abs
+            .fetch_or_build_account(Namespace::new(IBCMAIL_NAMESPACE)?, |builder| builder)?;
+        let arch_server = ServerInterface::new(IBCMAIL_SERVER_ID, arch_env.env.clone());
+        arch_server
+            .call_as(&arch_admin.address()?)
+            .set_link_blocked(
+                true,
+                TruncatedChainId::from_str("archway")?,
+                TruncatedChainId::from_str("juno")?,
+            )?;
+
+        let msg = Message::new(
+            Recipient::account(
+                juno_env.client1.account().id()?,
+                Some(TruncatedChainId::from_str("juno")?),
+            ),
+            "test-subject",
+            "test-body",
+        );
+
+        // Neither the discovered route nor an explicit one may use the blocked link
+        let res = arch_env.client1.send_message(msg.clone(), None);
+        assert_that!(res).is_err();
+
+        let res = arch_env.client1.send_message(
+            msg,
+            Some(AccountTrace::Remote(vec![TruncatedChainId::from_str(
+                "juno",
+            )?])),
+        );
+        assert_that!(res)
+            .is_err()
+            .matches(|e| e.root().to_string().contains("is blocked"));
+
+        Ok(())
+    }
+    #[test]
+    fn send_over_looping_route_fails() -> anyhow::Result<()> {
+        let interchain = MockBech32InterchainEnv::new(vec![
+            ("juno-1", "juno18k2uq7srsr8lwrae6zr0qahpn29rsp7tw83nyx"),
+            (
+                "archway-1",
+                "archway18k2uq7srsr8lwrae6zr0qahpn29rsp7td7wvfd",
+            ),
+        ]);
+
+        let arch_env = TestEnv::setup(interchain.get_chain("archway-1")?)?;
+        let juno_env = TestEnv::setup(interchain.get_chain("juno-1")?)?;
+
+        arch_env.abs.connect_to(&juno_env.abs, &interchain)?;
+
+        let msg = Message::new(
+            Recipient::account(
+                juno_env.client1.account().id()?,
+                Some(TruncatedChainId::from_str("juno")?),
+            ),
+            "test-subject",
+            "test-body",
+        );
+
+        let res = arch_env.client1.send_message(
+            msg,
+            Some(AccountTrace::Remote(vec![
+                TruncatedChainId::from_str("juno")?,
+                TruncatedChainId::from_str("archway")?,
+                TruncatedChainId::from_str("juno")?,
+            ])),
+        );
+
+        assert_that!(res)
+            .is_err()
+            .matches(|e| e.root().to_string().contains("visited more than once"));
+
+        Ok(())
+    }
+}
+
+mod held_mail {
+    use ibcmail::{server::state::DeliveryStatus, MessageStatus, IBCMAIL_SERVER_ID};
+    use server::ServerQueryMsgFns;
+
+    use super::*;
+
+    #[test]
+    fn mail_for_account_without_client_is_held_until_claimed() -> anyhow::Result<()> {
+        let mock = MockBech32::new("mock");
+        let env = TestEnv::setup(mock)?;
+        let client1 = env.client1;
+
+        let acc = env.abs.account_builder().build()?;
+        let msg = Message::new(
+            Recipient::account(acc.id()?, None),
+            "test-subject",
+            "test-body",
+        );
+        client1.send_message(msg, None)?;
+
+        let server = ServerInterface::new(IBCMAIL_SERVER_ID, env.env.clone());
+        let held = server.held_mail(acc.address()?.to_string(), None, None)?;
+        assert_that!(held.messages).has_length(1);
+        let held_msg = held.messages[0].msg.clone();
+
+        let app = acc.install_app_with_dependencies::<ClientInterface<_>>(
+            &ClientInstantiateMsg {},
+            Empty {},
+            &[],
+        )?;
+        app.authorize_on_adapters(&[IBCMAIL_SERVER_ID])?;
+        app.claim_mail(None)?;
+
+        let held = server.held_mail(acc.address()?.to_string(), None, None)?;
+        assert_that!(held.messages).is_empty();
+
+        let messages = app.list_messages(MessageStatus::Received, None, None, None)?;
+        assert_that!(messages.messages).has_length(1);
+
+        // The server remembers what happened to the message
+        let history = server.message_history(held_msg.id.clone())?;
+        let statuses: Vec<_> = history
+            .entries
+            .into_iter()
+            .map(|(_, record)| record.status)
+            .collect();
+        assert_that!(statuses)
+            .is_equal_to(vec![DeliveryStatus::Held {}, DeliveryStatus::Delivered {}]);
+
+        let history = server.sender_history(held_msg.sender, None, None)?;
+        assert_that!(history.entries).has_length(2);
+
+        Ok(())
+    }
+
+    #[test]
+    fn mail_for_address_is_held_until_its_account_claims_it() -> anyhow::Result<()> {
+        let mock = MockBech32::new("mock");
+        let env = TestEnv::setup(mock)?;
+        let server = ServerInterface::new(IBCMAIL_SERVER_ID, env.env.clone());
+
+        // The owner of both client accounts
+        let owner = env.env.sender_addr();
+        let msg = Message::new(
+            Recipient::address(owner.to_string(), None),
+            "test-subject",
+            "test-body",
+        );
+        env.client1.send_message(msg, None)?;
+
+        let held = server.held_mail(owner.to_string(), None, None)?;
+        assert_that!(held.messages).has_length(1);
+
+        // Accounts can only claim the mail of their own or their owner's address
+        let stranger = env.env.addr_make("stranger");
+        let res = env.client2.claim_mail(Some(stranger.to_string()));
+        assert_that!(res)
+            .is_err()
+            .matches(|e| e.root().to_string().contains("not allowed to claim mail"));
+
+        env.client2.claim_mail(Some(owner.to_string()))?;
+
+        let held = server.held_mail(owner.to_string(), None, None)?;
+        assert_that!(held.messages).is_empty();
+        let messages = env
+            .client2
+            .list_messages(MessageStatus::Received, None, None, None)?;
+        assert_that!(messages.messages).has_length(1);
+
+        Ok(())
+    }
+
+    #[test]
+    fn other_callers_cannot_send_through_client() -> anyhow::Result<()> {
+        let mock = MockBech32::new("mock");
+        let env = TestEnv::setup(mock)?;
+
+        let stranger = env.env.addr_make("stranger");
+        let msg = Message::new(
+            Recipient::account(env.client2.account().id()?, None),
+            "test-subject",
+            "test-body",
+        );
+        let res = env.client1.call_as(&stranger).send_message(msg, None);
+        assert_that!(res).is_err().matches(|e| {
+            e.root()
+                .to_string()
+                .contains("is not allowed to send mail through this client")
+        });
+
+        let messages = env
+            .client2
+            .list_messages(MessageStatus::Received, None, None, None)?;
+        assert_that!(messages.messages).is_empty();
+        let sent = env
+            .client1
+            .list_messages(MessageStatus::Sent, None, None, None)?;
+        assert_that!(sent.messages).is_empty();
+
+        Ok(())
+    }
+}
+
+mod dry_run {
+    use ibcmail::server::state::{PauseMode, RateLimit};
+    use server::{msg::ServerExecuteMsgFns, ServerQueryMsgFns};
+
+    use super::*;
+
+    #[test]
+    fn dry_run_resolves_local_namespace() -> anyhow::Result<()> {
+        let mock = MockBech32::new("mock");
+        let env = TestEnv::setup(mock)?;
+        let server = ServerInterface::new(IBCMAIL_SERVER_ID, env.env.clone());
+
+        let namespace = Namespace::new("test")?;
+        let acc = env
+            .abs
+            .account_builder()
+            .namespace(namespace.clone())
+            .build()?;
+        let sender = Sender::account(env.client1.account().id()?, None);
+
+        let res = server.dry_run(
+            Message::new(
+                Recipient::namespace(namespace, None),
+                "test-subject",
+                "test-body",
+            ),
+            None,
+            sender.clone(),
+        )?;
+        assert_that!(res.errors).is_empty();
+        assert_that!(res.route).is_equal_to(Some(AccountTrace::Local));
+        assert_that!(res.recipient_account).is_equal_to(Some(acc.id()?));
+
+        let res = server.dry_run(
+            Message::new(
+                Recipient::namespace(Namespace::new("unclaimed")?, None),
+                "test-subject",
+                "test-body",
+            ),
+            None,
+            sender,
+        )?;
+        assert_that!(res.errors).has_length(1);
+        assert_that!(res.recipient_account).is_none();
+
+        Ok(())
+    }
+
+    #[test]
+    fn dry_run_reports_checks_of_sending() -> anyhow::Result<()> {
+        let mock = MockBech32::new("mock");
+        let env = TestEnv::setup(mock)?;
+        let server = ServerInterface::new(IBCMAIL_SERVER_ID, env.env.clone());
+        let admin = env
+            .abs
+            .fetch_or_build_account(Namespace::new(IBCMAIL_NAMESPACE)?, |builder| builder)?;
+        let sender = Sender::account(env.client1.account().id()?, None);
+        let msg = Message::new(
+            Recipient::account(env.client2.account().id()?, None),
+            "test-subject",
+            "test-body",
+        );
+
+        server.call_as(&admin.address()?).set_rate_limit(
+            None,
+            Some(RateLimit {
+                max_messages: 1,
+                window: 60,
+            }),
+        )?;
+        let res = server.dry_run(msg.clone(), None, sender.clone())?;
+        assert_that!(res.errors).is_empty();
+
+        env.client1.send_message(msg.clone(), None)?;
+        let res = server.dry_run(msg.clone(), None, sender.clone())?;
+        assert_that!(res.errors).has_length(1);
+        assert_that!(res.errors[0]).contains("exceeded the rate limit");
+
+        server
+            .call_as(&admin.address()?)
+            .set_rate_limit(None, None)?;
+        server
+            .call_as(&admin.address()?)
+            .set_pause(Some(PauseMode::All))?;
+        let res = server.dry_run(msg, None, sender)?;
+        assert_that!(res.errors).has_length(1);
+        assert_that!(res.errors[0]).contains("paused for all mail");
+
+        Ok(())
+    }
+}
+
+mod polytone {
+    use std::str::FromStr;
+
+    use abstract_app::objects::TruncatedChainId;
+    use abstract_app::std::adapter::{self, AdapterBaseMsg, BaseExecuteMsg};
+    use abstract_cw_orch_polytone::Polytone;
+    use cosmwasm_std::{CosmosMsg, IbcOrder, Uint64};
+    use cw_orch_interchain::prelude::*;
+    use ibcmail::{
+        server::{msg::ServerIbcMessage, state::PolytoneRoute},
+        MessageStatus, IBCMAIL_SERVER_ID,
+    };
+    use server::msg::ServerExecuteMsgFns;
+
+    use super::*;
+
+    #[cosmwasm_schema::cw_serde]
+    enum NoteExecuteMsg {
+        Execute {
+            msgs: Vec<CosmosMsg>,
+            callback: Option<Empty>,
+            timeout_seconds: Uint64,
+        },
+    }
+
+    #[cosmwasm_schema::cw_serde]
+    enum NoteQueryMsg {
+        RemoteAddress { local_address: String },
+    }
+
+    #[test]
+    fn can_send_remote_message_over_polytone() -> anyhow::Result<()> {
+        let interchain = MockBech32InterchainEnv::new(vec![
+            ("juno-1", "juno18k2uq7srsr8lwrae6zr0qahpn29rsp7tw83nyx"),
+            (
+                "archway-1",
+                "archway18k2uq7srsr8lwrae6zr0qahpn29rsp7td7wvfd",
+            ),
+        ]);
+
+        // The chains are only connected through Polytone, not through Abstract IBC
+        let arch_env = TestEnv::setup(interchain.get_chain("archway-1")?)?;
+        let juno_env = TestEnv::setup(interchain.get_chain("juno-1")?)?;
+        let arch_polytone = Polytone::deploy_on(arch_env.env.clone(), Empty {})?;
+        let juno_polytone = Polytone::deploy_on(juno_env.env.clone(), Empty {})?;
+        interchain.create_contract_channel(
+            &arch_polytone.note,
+            &juno_polytone.voice,
+            "polytone-1",
+            Some(IbcOrder::Unordered),
+        )?;
+
+        let arch_server = ServerInterface::new(IBCMAIL_SERVER_ID, arch_env.env.clone());
+        let juno_server = ServerInterface::new(IBCMAIL_SERVER_ID, juno_env.env.clone());
+        let arch_server_addr = arch_server.address()?;
+        let note_addr = arch_polytone.note.address()?;
+
+        // Create the Polytone proxy of the archway server on juno
+        let res = arch_env.env.call_as(&arch_server_addr).execute(
+            &NoteExecuteMsg::Execute {
+                msgs: vec![],
+                callback: None,
+                timeout_seconds: 600u64.into(),
+            },
+            &[],
+            &note_addr,
+        )?;
+        interchain.await_and_check_packets("archway-1", res)?;
+        let proxy: Option<String> = arch_env.env.query(
+            &NoteQueryMsg::RemoteAddress {
+                local_address: arch_server_addr.to_string(),
+            },
+            &note_addr,
+        )?;
+        let proxy = proxy.expect("proxy created");
+
+        // The relay account on juno authorizes the proxy and the juno admin trusts it
+        let relay_account = juno_env.client1.account();
+        relay_account.as_ref().execute_on_module(
+            IBCMAIL_SERVER_ID,
+            adapter::ExecuteMsg::<Empty>::Base(BaseExecuteMsg {
+                account_address: None,
+                msg: AdapterBaseMsg::UpdateAuthorizedAddresses {
+                    to_add: vec![proxy.clone()],
+                    to_remove: vec![],
+                },
+            }),
+            vec![],
+        )?;
+        let juno_admin = juno_env
+            .abs
+            .fetch_or_build_account(Namespace::new(IBCMAIL_NAMESPACE)?, |builder| builder)?;
+        juno_server
+            .call_as(&juno_admin.address()?)
+            .set_trusted_proxy(proxy, Some(TruncatedChainId::from_str("archway")?))?;
+
+        let arch_admin = arch_env
+            .abs
+            .fetch_or_build_account(Namespace::new(IBCMAIL_NAMESPACE)?, |builder| builder)?;
+        arch_server
+            .call_as(&arch_admin.address()?)
+            .set_polytone_route(
+                TruncatedChainId::from_str("juno")?,
+                Some(PolytoneRoute {
+                    note: note_addr,
+                    remote_server: juno_server.address()?.to_string(),
+                    relay_account: relay_account.address()?.to_string(),
+                }),
+            )?;
+
+        let msg = Message::new(
+            Recipient::account(
+                juno_env.client2.account().id()?,
+                Some(TruncatedChainId::from_str("juno")?),
+            ),
+            "test-subject",
+            "test-body",
+        );
+        let res = arch_env.client1.send_message(msg, None)?;
+        interchain.await_and_check_packets("archway-1", res)?;
+
+        let juno_messages =
+            juno_env
+                .client2
+                .list_messages(MessageStatus::Received, None, None, None)?;
+        assert_that!(juno_messages.messages).has_length(1);
+
+        Ok(())
+    }
+
+    #[test]
+    fn trusted_proxy_only_delivers_mail_from_its_chain() -> anyhow::Result<()> {
+        let mock = MockBech32::new("mock");
+        let env = TestEnv::setup(mock)?;
+        let server = ServerInterface::new(IBCMAIL_SERVER_ID, env.env.clone());
+        let admin = env
+            .abs
+            .fetch_or_build_account(Namespace::new(IBCMAIL_NAMESPACE)?, |builder| builder)?;
+
+        // The account stands in for the proxy of the neutron server
+        let proxy = env.client1.account().address()?;
+        server.call_as(&admin.address()?).set_trusted_proxy(
+            proxy.to_string(),
+            Some(TruncatedChainId::from_str("neutron")?),
+        )?;
+
+        let msg = ServerIbcMessage::RouteMessage {
+            msg: create_test_message(env.client1.account().id()?, env.client2.account().id()?),
+            header: Header::new(AccountTrace::Remote(vec![
+                TruncatedChainId::from_str("archway")?,
+                TruncatedChainId::from_str("juno")?,
+            ])),
+        };
+        let res = server
+            .call_as(&proxy)
+            .receive_polytone(encode_server_msg(&msg)?);
+        assert_that!(res).is_err().matches(|e| {
+            e.root()
+                .to_string()
+                .contains("does not come from the previous hop")
+        });
+
+        Ok(())
+    }
+}
+
+mod ibc_port {
+    use std::str::FromStr;
+
+    use abstract_app::objects::TruncatedChainId;
+    use abstract_app::std::adapter::{self, AdapterBaseMsg, BaseExecuteMsg};
+    use cosmwasm_std::IbcOrder;
+    use cw_orch_interchain::prelude::*;
+    use ibcmail::{server::transport::IBCMAIL_IBC_VERSION, MessageStatus};
+    use server::{msg::ServerExecuteMsgFns, ServerQueryMsgFns};
+
+    use super::*;
+
+    #[test]
+    fn can_send_remote_message_over_own_channel() -> anyhow::Result<()> {
+        let interchain = MockBech32InterchainEnv::new(vec![
+            ("juno-1", "juno18k2uq7srsr8lwrae6zr0qahpn29rsp7tw83nyx"),
+            (
+                "archway-1",
+                "archway18k2uq7srsr8lwrae6zr0qahpn29rsp7td7wvfd",
+            ),
+        ]);
+
+        // The chains are only connected through the servers' own ports, not through Abstract IBC
+        let arch_env = TestEnv::setup(interchain.get_chain("archway-1")?)?;
+        let juno_env = TestEnv::setup(interchain.get_chain("juno-1")?)?;
+        let arch_server = ServerInterface::new(IBCMAIL_SERVER_ID, arch_env.env.clone());
+        let arch_admin = arch_env
+            .abs
+            .fetch_or_build_account(Namespace::new(IBCMAIL_NAMESPACE)?, |builder| builder)?;
+        let juno_server = ServerInterface::new(IBCMAIL_SERVER_ID, juno_env.env.clone());
+        let juno_admin = juno_env
+            .abs
+            .fetch_or_build_account(Namespace::new(IBCMAIL_NAMESPACE)?, |builder| builder)?;
+        let archway = TruncatedChainId::from_str("archway")?;
+        let juno = TruncatedChainId::from_str("juno")?;
+
+        // Channels are only opened between trusted ports
+        arch_server
+            .call_as(&arch_admin.address()?)
+            .set_trusted_port(
+                format!("wasm.{}", juno_server.address()?),
+                Some(juno.clone()),
+            )?;
+        juno_server
+            .call_as(&juno_admin.address()?)
+            .set_trusted_port(
+                format!("wasm.{}", arch_server.address()?),
+                Some(archway.clone()),
+            )?;
+        let channel = interchain
+            .create_contract_channel(
+                &arch_server,
+                &juno_server,
+                IBCMAIL_IBC_VERSION,
+                Some(IbcOrder::Unordered),
+            )?
+            .interchain_channel;
+        let (arch_port, juno_port) = channel.get_ordered_ports_from("archway-1")?;
+        let juno_channel = juno_port.channel.unwrap().to_string();
+
+        // Packets are handled by the server executing itself for the admin, which must authorize it first
+        let res = juno_server
+            .call_as(&juno_admin.address()?)
+            .set_ibc_channel(archway.clone(), Some(juno_channel.clone()));
+        assert_that!(res)
+            .is_err()
+            .matches(|e| e.root().to_string().contains("must authorize the server"));
+
+        authorize_server(&arch_env, true)?;
+        authorize_server(&juno_env, true)?;
+        arch_server
+            .call_as(&arch_admin.address()?)
+            .set_ibc_channel(juno.clone(), Some(arch_port.channel.unwrap().to_string()))?;
+        juno_server
+            .call_as(&juno_admin.address()?)
+            .set_ibc_channel(archway, Some(juno_channel))?;
+
+        let msg = Message::new(
+            Recipient::account(juno_env.client1.account().id()?, Some(juno)),
+            "test-subject",
+            "test-body",
+        );
+
+        // Packets bounce once the admin removes the server from its authorized addresses
+        authorize_server(&juno_env, false)?;
+        let res = arch_env.client1.send_message(msg.clone(), None)?;
+        interchain.await_packets("archway-1", res)?;
+
+        let juno_messages =
+            juno_env
+                .client1
+                .list_messages(MessageStatus::Received, None, None, None)?;
+        assert_that!(juno_messages.messages).is_empty();
+        let arch_messages =
+            arch_env
+                .client1
+                .list_messages(MessageStatus::Received, None, None, None)?;
+        assert_that!(arch_messages.messages).has_length(1);
+        assert_that!(arch_messages.messages[0].message.subject)
+            .is_equal_to("Undeliverable: test-subject".to_string());
+
+        authorize_server(&juno_env, true)?;
+        let res = arch_env.client1.send_message(msg, None)?;
+        interchain.await_packets("archway-1", res)?;
+
+        let juno_messages =
+            juno_env
+                .client1
+                .list_messages(MessageStatus::Received, None, None, None)?;
+        assert_that!(juno_messages.messages).has_length(1);
+
+        Ok(())
+    }
+
+    /// Add the server's own address to the authorized addresses of its admin, or remove it.
+    fn authorize_server(env: &TestEnv<MockBech32>, authorize: bool) -> anyhow::Result<()> {
+        let server = ServerInterface::new(IBCMAIL_SERVER_ID, env.env.clone());
+        let admin = env
+            .abs
+            .fetch_or_build_account(Namespace::new(IBCMAIL_NAMESPACE)?, |builder| builder)?;
+        let server_addr = server.address()?.to_string();
+        let (to_add, to_remove) = if authorize {
+            (vec![server_addr], vec![])
+        } else {
+            (vec![], vec![server_addr])
+        };
+        env.env.call_as(&admin.address()?).execute(
+            &adapter::ExecuteMsg::<Empty>::Base(BaseExecuteMsg {
+                account_address: None,
+                msg: AdapterBaseMsg::UpdateAuthorizedAddresses { to_add, to_remove },
+            }),
+            &[],
+            &server.address()?,
+        )?;
+
+        Ok(())
+    }
+
+    #[test]
+    fn cannot_route_over_unopened_channel() -> anyhow::Result<()> {
+        let mock = MockBech32::new("mock");
+        let env = TestEnv::setup(mock)?;
+        let server = ServerInterface::new(IBCMAIL_SERVER_ID, env.env.clone());
+
+        let admin = env
+            .abs
+            .fetch_or_build_account(Namespace::new(IBCMAIL_NAMESPACE)?, |builder| builder)?;
+
+        let res = server.call_as(&admin.address()?).set_ibc_channel(
+            TruncatedChainId::from_str("juno")?,
+            Some("channel-0".to_string()),
+        );
+        assert_that!(res).is_err();
+
+        let route_table = server.route_table()?;
+        assert_that!(route_table.ibc_channels).is_empty();
+
+        Ok(())
+    }
+}
+
+mod ics20 {
+    use std::str::FromStr;
+
+    use abstract_app::{
+        objects::TruncatedChainId,
+        std::{
+            adapter::{self, AdapterBaseMsg, AdapterRequestMsg, BaseExecuteMsg},
+            app,
+        },
+    };
+    use cosmwasm_std::{coin, testing::MockApi, IbcOrder};
+    use cw_orch_interchain::prelude::*;
+    use ibcmail::{
+        client::msg::ClientExecuteMsg,
+        server::{
+            msg::{IbcLifecycleComplete, ServerExecuteMsg, ServerIbcMessage, ServerSudoMsg},
+            state::{DeliveryStatus, Ics20Route},
+            transport::ibc_hooks_sender,
+        },
+        MessageStatus,
+    };
+    use server::{msg::ServerExecuteMsgFns, ServerQueryMsgFns};
+
+    use super::*;
+
+    #[test]
+    fn only_trusted_hook_senders_can_deliver() -> anyhow::Result<()> {
+        let mock = MockBech32::new("mock");
+        let env = TestEnv::setup(mock.clone())?;
+        let server = ServerInterface::new(IBCMAIL_SERVER_ID, env.env.clone());
+
+        let admin = env
+            .abs
+            .fetch_or_build_account(Namespace::new(IBCMAIL_NAMESPACE)?, |builder| builder)?;
+        let remote_server = mock.addr_make("remote-server");
+        server.call_as(&admin.address()?).set_trusted_hook_sender(
+            "channel-0".to_string(),
+            remote_server.to_string(),
+            Some("juno".parse()?),
+        )?;
+
+        let msg = ServerIbcMessage::RouteMessage {
+            msg: create_test_message(env.client1.account().id()?, env.client2.account().id()?),
+            header: Header::new(AccountTrace::Local),
+        };
+
+        // The remote server itself is not the address ibc-hooks executes as
+        let res = server
+            .call_as(&admin.address()?)
+            .receive_ics_20(encode_server_msg(&msg)?);
+        assert_that!(res).is_err().matches(|e| {
+            e.root()
+                .to_string()
+                .contains("not a trusted ibc-hooks sender")
+        });
+
+        Ok(())
+    }
+
+    #[test]
+    fn trusted_hook_sender_delivers_mail() -> anyhow::Result<()> {
+        let mock = MockBech32::new("mock");
+        let env = TestEnv::setup(mock)?;
+        let server = ServerInterface::new(IBCMAIL_SERVER_ID, env.env.clone());
+        let admin = env
+            .abs
+            .fetch_or_build_account(Namespace::new(IBCMAIL_NAMESPACE)?, |builder| builder)?;
+        let juno = TruncatedChainId::from_str("juno")?;
+        let local = TruncatedChainId::from_chain_id(&env.env.block_info()?.chain_id);
+
+        // ibc-hooks executes as an address derived from the channel and the remote server
+        let remote_server = env.env.addr_make("remote-server");
+        let hook_sender = ibc_hooks_sender(
+            &MockApi::default().with_prefix("mock"),
+            "channel-0",
+            remote_server.as_str(),
+        )?;
+        server.call_as(&admin.address()?).set_trusted_hook_sender(
+            "channel-0".to_string(),
+            remote_server.to_string(),
+            Some(juno.clone()),
+        )?;
+
+        // The relay account authorizes the hook sender to act on its behalf
+        let relay_account = env.client1.account();
+        relay_account.as_ref().execute_on_module(
+            IBCMAIL_SERVER_ID,
+            adapter::ExecuteMsg::<Empty>::Base(BaseExecuteMsg {
+                account_address: None,
+                msg: AdapterBaseMsg::UpdateAuthorizedAddresses {
+                    to_add: vec![hook_sender.to_string()],
+                    to_remove: vec![],
+                },
+            }),
+            vec![],
+        )?;
+
+        let mut msg = create_test_message(relay_account.id()?, env.client2.account().id()?);
+        msg.message.recipient =
+            Recipient::account(env.client2.account().id()?, Some(local.clone()));
+        let msg = ServerIbcMessage::RouteMessage {
+            msg,
+            header: Header::new(AccountTrace::Remote(vec![juno, local])),
+        };
+        env.env.add_balance(&hook_sender, vec![coin(1, "ucosm")])?;
+        env.env.call_as(&hook_sender).execute(
+            &adapter::ExecuteMsg::<ServerExecuteMsg>::Module(AdapterRequestMsg {
+                account_address: Some(relay_account.address()?.to_string()),
+                request: ServerExecuteMsg::ReceiveIcs20 {
+                    msg: encode_server_msg(&msg)?,
+                },
+            }),
+            &[coin(1, "ucosm")],
+            &server.address()?,
+        )?;
+
+        let received = env
+            .client2
+            .list_messages(MessageStatus::Received, None, None, None)?;
+        assert_that!(received.messages).has_length(1);
+
+        // The transferred tokens don't stay with the server
+        let relay_balance = env.env.query_balance(&relay_account.address()?, "ucosm")?;
+        assert_that!(relay_balance.u128()).is_equal_to(1);
+        assert_that!(env.env.query_balance(&server.address()?, "ucosm")?.u128()).is_equal_to(0);
+
+        Ok(())
+    }
+
+    #[test]
+    fn history_of_remote_sender_includes_its_chain() -> anyhow::Result<()> {
+        let mock = MockBech32::new("mock");
+        let env = TestEnv::setup(mock)?;
+        let server = ServerInterface::new(IBCMAIL_SERVER_ID, env.env.clone());
+        let admin = env
+            .abs
+            .fetch_or_build_account(Namespace::new(IBCMAIL_NAMESPACE)?, |builder| builder)?;
+        let juno = TruncatedChainId::from_str("juno")?;
+        let local = TruncatedChainId::from_chain_id(&env.env.block_info()?.chain_id);
+
+        let remote_server = env.env.addr_make("remote-server");
+        let hook_sender = ibc_hooks_sender(
+            &MockApi::default().with_prefix("mock"),
+            "channel-0",
+            remote_server.as_str(),
+        )?;
+        server.call_as(&admin.address()?).set_trusted_hook_sender(
+            "channel-0".to_string(),
+            remote_server.to_string(),
+            Some(juno.clone()),
+        )?;
+        let relay_account = env.client1.account();
+        relay_account.as_ref().execute_on_module(
+            IBCMAIL_SERVER_ID,
+            adapter::ExecuteMsg::<Empty>::Base(BaseExecuteMsg {
+                account_address: None,
+                msg: AdapterBaseMsg::UpdateAuthorizedAddresses {
+                    to_add: vec![hook_sender.to_string()],
+                    to_remove: vec![],
+                },
+            }),
+            vec![],
+        )?;
+
+        // Servers of older releases leave out the chain of the sender
+        let sender_id = env.env.addr_make("juno-account");
+        let sender = Sender::address(sender_id.to_string(), None);
+        let mut msg = create_test_message(relay_account.id()?, env.client2.account().id()?);
+        msg.sender = sender.clone();
+        msg.message.recipient =
+            Recipient::account(env.client2.account().id()?, Some(local.clone()));
+        let msg = ServerIbcMessage::RouteMessage {
+            msg,
+            header: Header::new(AccountTrace::Remote(vec![juno.clone(), local])),
+        };
+        env.env.call_as(&hook_sender).execute(
+            &adapter::ExecuteMsg::<ServerExecuteMsg>::Module(AdapterRequestMsg {
+                account_address: Some(relay_account.address()?.to_string()),
+                request: ServerExecuteMsg::ReceiveIcs20 {
+                    msg: encode_server_msg(&msg)?,
+                },
+            }),
+            &[],
+            &server.address()?,
+        )?;
+
+        let history = server.sender_history(
+            Sender::address(sender_id.to_string(), Some(juno)),
+            None,
+            None,
+        )?;
+        assert_that!(history.entries).has_length(1);
+        assert_that!(history.entries[0].1.status).is_equal_to(DeliveryStatus::Delivered {});
+
+        // Without a chain the sender is looked up on this chain
+        let history = server.sender_history(sender, None, None)?;
+        assert_that!(history.entries).is_empty();
+
+        Ok(())
+    }
+
+    #[test]
+    fn failed_transfer_is_bounced() -> anyhow::Result<()> {
+        let interchain = MockBech32InterchainEnv::new(vec![
+            ("juno-1", "juno18k2uq7srsr8lwrae6zr0qahpn29rsp7tw83nyx"),
+            (
+                "archway-1",
+                "archway18k2uq7srsr8lwrae6zr0qahpn29rsp7td7wvfd",
+            ),
+        ]);
+
+        let arch_env = TestEnv::setup(interchain.get_chain("archway-1")?)?;
+        let juno_env = TestEnv::setup(interchain.get_chain("juno-1")?)?;
+        let transfer = interchain
+            .create_channel(
+                "archway-1",
+                "juno-1",
+                &PortId::transfer(),
+                &PortId::transfer(),
+                "ics20-1",
+                Some(IbcOrder::Unordered),
+            )?
+            .interchain_channel;
+        let channel = transfer
+            .get_ordered_ports_from("archway-1")?
+            .0
+            .channel
+            .unwrap()
+            .to_string();
+
+        let juno = TruncatedChainId::from_str("juno")?;
+        let arch_server = ServerInterface::new(IBCMAIL_SERVER_ID, arch_env.env.clone());
+        let arch_admin = arch_env
+            .abs
+            .fetch_or_build_account(Namespace::new(IBCMAIL_NAMESPACE)?, |builder| builder)?;
+        let juno_server = ServerInterface::new(IBCMAIL_SERVER_ID, juno_env.env.clone());
+        arch_server
+            .call_as(&arch_admin.address()?)
+            .set_ics_20_route(
+                juno.clone(),
+                Some(Ics20Route {
+                    channel: channel.clone(),
+                    funds: coin(1, "uarch"),
+                    remote_server: juno_server.address()?.to_string(),
+                    relay_account: juno_env.client1.account().address()?.to_string(),
+                }),
+            )?;
+
+        let sender = arch_env.env.sender_addr();
+        arch_env.env.add_balance(&sender, vec![coin(1, "uarch")])?;
+        arch_env.env.execute(
+            &app::ExecuteMsg::<ClientExecuteMsg>::Module(ClientExecuteMsg::SendMessage {
+                message: Message::new(
+                    Recipient::account(juno_env.client1.account().id()?, Some(juno)),
+                    "test-subject",
+                    "test-body",
+                ),
+                route: None,
+            }),
+            &[coin(1, "uarch")],
+            &arch_env.client1.address()?,
+        )?;
+
+        // ibc-hooks reports the outcome of the transfer by the sequence that the reply remembered
+        let timeout = ServerSudoMsg::IbcLifecycleComplete(IbcLifecycleComplete::IbcTimeout {
+            channel,
+            sequence: 1,
+        });
+        arch_env
+            .env
+            .app
+            .borrow_mut()
+            .wasm_sudo(arch_server.address()?, &timeout)?;
+
+        let bounced = arch_env
+            .client1
+            .list_messages(MessageStatus::Received, None, None, None)?;
+        assert_that!(bounced.messages).has_length(1);
+        assert_that!(bounced.messages[0].message.subject)
+            .is_equal_to("Undeliverable: test-subject".to_string());
+
+        // Each transfer is only bounced once
+        arch_env
+            .env
+            .app
+            .borrow_mut()
+            .wasm_sudo(arch_server.address()?, &timeout)?;
+        let bounced = arch_env
+            .client1
+            .list_messages(MessageStatus::Received, None, None, None)?;
+        assert_that!(bounced.messages).has_length(1);
+
+        Ok(())
+    }
+}
+
+mod wire {
+    use std::str::FromStr;
+
+    use abstract_app::objects::TruncatedChainId;
+    use cw_orch_interchain::prelude::*;
+    use ibcmail::{
+        server::wire::{LEGACY_WIRE_VERSION, SUPPORTED_WIRE_VERSIONS},
+        MessageStatus,
+    };
+    use server::{msg::ServerExecuteMsgFns, ServerQueryMsgFns};
+
+    use super::*;
+
+    #[test]
+    fn server_advertises_supported_versions() -> anyhow::Result<()> {
+        let mock = MockBech32::new("mock");
+        let env = TestEnv::setup(mock)?;
+        let server = ServerInterface::new(IBCMAIL_SERVER_ID, env.env.clone());
+
+        let res = server.wire_versions()?;
+        assert_that!(res.supported).is_equal_to(SUPPORTED_WIRE_VERSIONS.to_vec());
+        assert_that!(res.peers).is_empty();
+
+        Ok(())
+    }
+
+    #[test]
+    fn only_admin_can_set_peer_wire_versions() -> anyhow::Result<()> {
+        let mock = MockBech32::new("mock");
+        let env = TestEnv::setup(mock)?;
+        let server = ServerInterface::new(IBCMAIL_SERVER_ID, env.env.clone());
+        let admin = env
+            .abs
+            .fetch_or_build_account(Namespace::new(IBCMAIL_NAMESPACE)?, |builder| builder)?;
+        let juno = TruncatedChainId::from_str("juno")?;
+        let not_admin = |e: &CwOrchError| {
+            e.root()
+                .to_string()
+                .contains("Sender is not the owner of the ibcmail namespace")
+        };
+
+        let stranger = env.client1.account().address()?;
+        let res = server.call_as(&stranger).sync_wire_version(juno.clone());
+        assert_that!(res).is_err().matches(not_admin);
+        let res = server
+            .call_as(&stranger)
+            .set_peer_wire_version(juno.clone(), Some(LEGACY_WIRE_VERSION));
+        assert_that!(res).is_err().matches(not_admin);
+
+        server
+            .call_as(&admin.address()?)
+            .set_peer_wire_version(juno.clone(), Some(LEGACY_WIRE_VERSION))?;
+        assert_that!(server.wire_versions()?.peers)
+            .is_equal_to(vec![(juno.clone(), LEGACY_WIRE_VERSION)]);
+
+        server
+            .call_as(&admin.address()?)
+            .set_peer_wire_version(juno, None)?;
+        assert_that!(server.wire_versions()?.peers).is_empty();
+
+        Ok(())
+    }
+
+    #[test]
+    fn legacy_peer_sends_and_receives_mail() -> anyhow::Result<()> {
+        let interchain = MockBech32InterchainEnv::new(vec![
+            ("juno-1", "juno18k2uq7srsr8lwrae6zr0qahpn29rsp7tw83nyx"),
+            (
+                "archway-1",
+                "archway18k2uq7srsr8lwrae6zr0qahpn29rsp7td7wvfd",
+            ),
+        ]);
+        let arch_env = TestEnv::setup(interchain.get_chain("archway-1")?)?;
+        let juno_env = TestEnv::setup(interchain.get_chain("juno-1")?)?;
+        arch_env.abs.connect_to(&juno_env.abs, &interchain)?;
+
+        let archway = TruncatedChainId::from_str("archway")?;
+        let juno = TruncatedChainId::from_str("juno")?;
+
+        // The juno server talks to archway like a server of an older release
+        let juno_server = ServerInterface::new(IBCMAIL_SERVER_ID, juno_env.env.clone());
+        let juno_admin = juno_env
+            .abs
+            .fetch_or_build_account(Namespace::new(IBCMAIL_NAMESPACE)?, |builder| builder)?;
+        juno_server
+            .call_as(&juno_admin.address()?)
+            .set_peer_wire_version(archway.clone(), Some(LEGACY_WIRE_VERSION))?;
+
+        let juno_account = juno_env.client1.account();
+        let arch_account = arch_env.client1.account();
+        let msg = IbcMailMessage {
+            version: "0.1.0".to_string(),
+            message: Message::new(
+                Recipient::account(arch_account.id()?, Some(archway.clone())),
+                "test-subject",
+                "test-body",
+            ),
+            ..create_test_message(juno_account.id()?, arch_account.id()?)
+        };
+        let res = juno_server
+            .call_as(&juno_account.address()?)
+            .process_message(msg, None)?;
+        interchain.await_and_check_packets("juno-1", res)?;
+
+        let received = arch_env
+            .client1
+            .list_messages(MessageStatus::Received, None, None, None)?;
+        assert_that!(received.messages).has_length(1);
+        assert_that!(received.messages[0].version).is_equal_to("0.1.0".to_string());
+
+        // The archway server answers in the version juno sent in
+        let arch_server = ServerInterface::new(IBCMAIL_SERVER_ID, arch_env.env.clone());
+        assert_that!(arch_server.wire_versions()?.peers)
+            .is_equal_to(vec![(juno.clone(), LEGACY_WIRE_VERSION)]);
+
+        let reply = Message::new(
+            Recipient::account(juno_account.id()?, Some(juno)),
+            "test-subject",
+            "test-body",
+        );
+        let res = arch_env.client1.send_message(reply, None)?;
+        interchain.await_and_check_packets("archway-1", res)?;
+
+        let received = juno_env
+            .client1
+            .list_messages(MessageStatus::Received, None, None, None)?;
+        assert_that!(received.messages).has_length(1);
+
+        Ok(())
+    }
+
+    #[test]
+    fn server_rejects_unsupported_message_versions() -> anyhow::Result<()> {
+        let mock = MockBech32::new("mock");
+        let env = TestEnv::setup(mock)?;
+        let server = ServerInterface::new(IBCMAIL_SERVER_ID, env.env.clone());
+        let account = env.client1.account();
+
+        let mut msg = create_test_message(account.id()?, env.client2.account().id()?);
+        msg.version = "99.0.0".to_string();
+        let res = server
+            .call_as(&account.address()?)
+            .process_message(msg, None);
+        assert_that!(res)
+            .is_err()
+            .matches(|e| e.root().to_string().contains("Unsupported message version"));
+
+        Ok(())
+    }
+}
+
+mod dead_letters {
+    use ibcmail::server::state::DeliveryStatus;
+    use server::{msg::ServerExecuteMsgFns, ServerQueryMsgFns};
+
+    use super::*;
+    use crate::receiver::{ReceiverExecuteMsgFns, ReceiverQueryMsgFns, RECEIVER_ID};
+
+    #[test]
+    fn failed_delivery_is_dead_lettered_until_retried() -> anyhow::Result<()> {
+        let mock = MockBech32::new("mock");
+        let env = TestEnv::setup(mock)?;
+        let server = ServerInterface::new(IBCMAIL_SERVER_ID, env.env.clone());
+
+        let account = env.client2.account();
+        let receiver = env.install_receiver(account)?;
+        receiver.set_rejecting(true)?;
+
+        let msg = Message::new(
+            Recipient::module(account.id()?, RECEIVER_ID, None),
+            "test-subject",
+            "test-body",
+        );
+        env.client1.send_message(msg, None)?;
+
+        let address = account.address()?.to_string();
+        let dead_letters = server.dead_letters(address.clone(), None, None)?;
+        assert_that!(dead_letters.messages).has_length(1);
+        let dead_letter = dead_letters.messages[0].clone();
+        assert_that!(receiver.received()?).is_empty();
+
+        receiver.set_rejecting(false)?;
+        server
+            .call_as(&account.address()?)
+            .retry_dead_letter(address.clone(), dead_letter.msg.id.clone())?;
+
+        let dead_letters = server.dead_letters(address, None, None)?;
+        assert_that!(dead_letters.messages).is_empty();
+        assert_that!(receiver.received()?).is_equal_to(vec![dead_letter.msg.clone()]);
+
+        // The message is only logged as delivered once the receiver accepted it
+        let statuses: Vec<_> = server
+            .message_history(dead_letter.msg.id)?
+            .entries
+            .into_iter()
+            .map(|(_, record)| record.status)
+            .collect();
+        assert_that!(statuses).has_length(2);
+        assert_that!(statuses[0])
+            .matches(|status| matches!(status, DeliveryStatus::DeadLettered { .. }));
+        assert_that!(statuses[1]).is_equal_to(DeliveryStatus::Delivered {});
+
+        Ok(())
+    }
+
+    #[test]
+    fn cannot_handle_unknown_or_foreign_dead_letters() -> anyhow::Result<()> {
+        let mock = MockBech32::new("mock");
+        let env = TestEnv::setup(mock)?;
+        let server = ServerInterface::new(IBCMAIL_SERVER_ID, env.env.clone());
+
+        let account = env.client1.account();
+        let address = account.address()?.to_string();
+        let dead_letters = server.dead_letters(address.clone(), None, None)?;
+        assert_that!(dead_letters.messages).is_empty();
+
+        let res = server
+            .call_as(&account.address()?)
+            .retry_dead_letter(address.clone(), "unknown".to_string());
+        assert_that!(res).is_err();
+
+        // Other accounts can't touch the dead letters of an account
+        let other = env.client2.account();
+        let res = server.call_as(&other.address()?).discard_dead_letter(
+            address,
+            true,
+            "unknown".to_string(),
+        );
+        assert_that!(res)
+            .is_err()
+            .matches(|e| e.root().to_string().contains("not allowed to claim"));
+
+        Ok(())
+    }
+}
+
+mod rate_limit {
+    use ibcmail::{server::state::RateLimit, MessageStatus};
+    use server::msg::ServerExecuteMsgFns;
+
+    use super::*;
+
+    #[test]
+    fn sender_over_rate_limit_is_rejected() -> anyhow::Result<()> {
+        let mock = MockBech32::new("mock");
+        let env = TestEnv::setup(mock.clone())?;
+        let server = ServerInterface::new(IBCMAIL_SERVER_ID, env.env.clone());
+
+        let admin = env
+            .abs
+            .fetch_or_build_account(Namespace::new(IBCMAIL_NAMESPACE)?, |builder| builder)?;
+        server.call_as(&admin.address()?).set_rate_limit(
+            None,
+            Some(RateLimit {
+                max_messages: 1,
+                window: 60,
+            }),
+        )?;
+
+        let msg = Message::new(
+            Recipient::account(env.client2.account().id()?, None),
+            "test-subject",
+            "test-body",
+        );
+        env.client1.send_message(msg.clone(), None)?;
+
+        let res = env.client1.send_message(msg.clone(), None);
+        assert_that!(res)
+            .is_err()
+            .matches(|e| e.root().to_string().contains("rate limit"));
+
+        // Other senders and the next window are not affected
+        env.client2.send_message(
+            Message::new(
+                Recipient::account(env.client1.account().id()?, None),
+                "test-subject",
+                "test-body",
+            ),
+            None,
+        )?;
+        mock.wait_seconds(60)?;
+        env.client1.send_message(msg, None)?;
+
+        Ok(())
+    }
+
+    #[test]
+    fn rejected_messages_of_batch_are_not_counted() -> anyhow::Result<()> {
+        let mock = MockBech32::new("mock");
+        let env = TestEnv::setup(mock)?;
+        let server = ServerInterface::new(IBCMAIL_SERVER_ID, env.env.clone());
+        let admin = env
+            .abs
+            .fetch_or_build_account(Namespace::new(IBCMAIL_NAMESPACE)?, |builder| builder)?;
+        server.call_as(&admin.address()?).set_rate_limit(
+            None,
+            Some(RateLimit {
+                max_messages: 1,
+                window: 60,
+            }),
+        )?;
+
+        let msgs = vec![
+            // neutron is not reachable
+            Message::new(
+                Recipient::account(env.client2.account().id()?, Some("neutron".parse()?)),
+                "test-subject",
+                "test-body",
+            ),
+            Message::new(
+                Recipient::account(env.client2.account().id()?, None),
+                "test-subject",
+                "test-body",
+            ),
+        ];
+        env.client1.send_messages(msgs)?;
+
+        let received = env
+            .client2
+            .list_messages(MessageStatus::Received, None, None, None)?;
+        assert_that!(received.messages).has_length(1);
+
+        Ok(())
+    }
+
+    #[test]
+    fn sender_cannot_pose_as_server_or_other_account() -> anyhow::Result<()> {
+        let mock = MockBech32::new("mock");
+        let env = TestEnv::setup(mock)?;
+        let server = ServerInterface::new(IBCMAIL_SERVER_ID, env.env.clone());
+        let account = env.client1.account();
+
+        let mut msg = create_test_message(env.client2.account().id()?, env.client2.account().id()?);
+        let res = server
+            .call_as(&account.address()?)
+            .process_message(msg.clone(), None);
+        assert_that!(res)
+            .is_err()
+            .matches(|e| e.root().to_string().contains("can't send mail as"));
+
+        // Mail in the name of a server would skip the rate limit and relay fees
+        msg.sender = Sender::Server {
+            chain: "mock".parse()?,
+        };
+        let res = server
+            .call_as(&account.address()?)
+            .process_message(msg, None);
+        assert_that!(res)
+            .is_err()
+            .matches(|e| e.root().to_string().contains("can't send mail as"));
+
+        Ok(())
+    }
+}
+
+mod relay_fee {
+    use std::str::FromStr;
+
+    use abstract_app::{
+        objects::TruncatedChainId,
+        std::{
+            adapter::{self, AdapterBaseMsg, AdapterRequestMsg, BaseExecuteMsg},
+            app,
+        },
+    };
+    use cosmwasm_std::{coin, testing::MockApi, Coin, IbcOrder};
+    use cw_orch_interchain::prelude::*;
+    use ibcmail::{
+        client::msg::ClientExecuteMsg,
+        server::{
+            msg::ServerExecuteMsg,
+            state::{Ics20Route, RelayFee, RemoteRelayFee},
+            transport::ibc_hooks_sender,
+        },
+        MessageStatus,
+    };
+    use server::{msg::ServerExecuteMsgFns, ServerQueryMsgFns};
+
+    use super::*;
+
+    #[test]
+    fn relay_fees_of_route_must_be_prepaid() -> anyhow::Result<()> {
+        let mock = MockBech32::new("mock");
+        let env = TestEnv::setup(mock)?;
+        let server = ServerInterface::new(IBCMAIL_SERVER_ID, env.env.clone());
+
+        let admin = env
+            .abs
+            .fetch_or_build_account(Namespace::new(IBCMAIL_NAMESPACE)?, |builder| builder)?;
+        server.call_as(&admin.address()?).set_remote_relay_fee(
+            TruncatedChainId::from_str("juno")?,
+            Some(RemoteRelayFee {
+                fee: coin(100, "ujuno"),
+                denom: "ucosm".to_string(),
+            }),
+        )?;
+
+        let msg = Message::new(
+            Recipient::account(
+                env.client2.account().id()?,
+                Some(TruncatedChainId::from_str("archway")?),
+            ),
+            "test-subject",
+            "test-body",
+        );
+        let route = AccountTrace::Remote(vec![
+            TruncatedChainId::from_str("juno")?,
+            TruncatedChainId::from_str("archway")?,
+        ]);
+
+        // Only juno forwards the message, archway delivers it
+        let sender = Sender::account(env.client1.account().id()?, None);
+        let res = server.dry_run(msg.clone(), Some(route.clone()), sender)?;
+        assert_that!(res.fees).is_equal_to(vec![coin(100, "ucosm")]);
+
+        let res = env.client1.send_message(msg, Some(route));
+        assert_that!(res)
+            .is_err()
+            .matches(|e| e.root().to_string().contains("Fees of 100ucosm"));
+
+        Ok(())
+    }
+
+    #[test]
+    fn only_admin_can_set_relay_fee() -> anyhow::Result<()> {
+        let mock = MockBech32::new("mock");
+        let env = TestEnv::setup(mock)?;
+        let server = ServerInterface::new(IBCMAIL_SERVER_ID, env.env.clone());
+        let admin = env
+            .abs
+            .fetch_or_build_account(Namespace::new(IBCMAIL_NAMESPACE)?, |builder| builder)?;
+        let fee = RelayFee {
+            amount: coin(100, "ucosm"),
+            collector: env.env.addr_make("collector"),
+        };
+
+        let res = server
+            .call_as(&env.client1.account().address()?)
+            .set_relay_fee(Some(fee.clone()));
+        assert_that!(res).is_err().matches(|e| {
+            e.root()
+                .to_string()
+                .contains("Sender is not the owner of the ibcmail namespace")
+        });
+
+        server
+            .call_as(&admin.address()?)
+            .set_relay_fee(Some(fee.clone()))?;
+        assert_that!(server.config()?.config.relay_fee).is_equal_to(Some(fee));
+
+        Ok(())
+    }
+
+    #[test]
+    fn prepaid_relay_fee_is_transferred_with_the_mail() -> anyhow::Result<()> {
+        let interchain = MockBech32InterchainEnv::new(vec![
+            ("juno-1", "juno18k2uq7srsr8lwrae6zr0qahpn29rsp7tw83nyx"),
+            (
+                "archway-1",
+                "archway18k2uq7srsr8lwrae6zr0qahpn29rsp7td7wvfd",
+            ),
+        ]);
+
+        let arch_env = TestEnv::setup(interchain.get_chain("archway-1")?)?;
+        let juno_env = TestEnv::setup(interchain.get_chain("juno-1")?)?;
+        let transfer = interchain
+            .create_channel(
+                "archway-1",
+                "juno-1",
+                &PortId::transfer(),
+                &PortId::transfer(),
+                "ics20-1",
+                Some(IbcOrder::Unordered),
+            )?
+            .interchain_channel;
+        let channel = transfer
+            .get_ordered_ports_from("archway-1")?
+            .0
+            .channel
+            .unwrap()
+            .to_string();
+
+        let juno = TruncatedChainId::from_str("juno")?;
+        let neutron = TruncatedChainId::from_str("neutron")?;
+        let juno_server = ServerInterface::new(IBCMAIL_SERVER_ID, juno_env.env.clone());
+        let arch_server = ServerInterface::new(IBCMAIL_SERVER_ID, arch_env.env.clone());
+        let arch_admin = arch_env
+            .abs
+            .fetch_or_build_account(Namespace::new(IBCMAIL_NAMESPACE)?, |builder| builder)?;
+        arch_server
+            .call_as(&arch_admin.address()?)
+            .set_ics_20_route(
+                juno.clone(),
+                Some(Ics20Route {
+                    channel,
+                    funds: coin(1, "uarch"),
+                    remote_server: juno_server.address()?.to_string(),
+                    relay_account: juno_env.client1.account().address()?.to_string(),
+                }),
+            )?;
+        arch_server
+            .call_as(&arch_admin.address()?)
+            .set_remote_relay_fee(
+                juno.clone(),
+                Some(RemoteRelayFee {
+                    fee: coin(100, "uvoucher"),
+                    denom: "uarch".to_string(),
+                }),
+            )?;
+
+        // The fee is added to the transfer of the mail and the excess is returned
+        let sender = arch_env.env.sender_addr();
+        arch_env
+            .env
+            .add_balance(&sender, vec![coin(150, "uarch")])?;
+        let res = arch_env.env.execute(
+            &app::ExecuteMsg::<ClientExecuteMsg>::Module(ClientExecuteMsg::SendMessage {
+                message: Message::new(
+                    Recipient::account(juno_env.client1.account().id()?, Some(neutron.clone())),
+                    "test-subject",
+                    "test-body",
+                ),
+                route: Some(AccountTrace::Remote(vec![juno, neutron])),
+            }),
+            &[coin(150, "uarch")],
+            &arch_env.client1.address()?,
+        )?;
+        interchain.await_and_check_packets("archway-1", res)?;
+
+        assert_that!(arch_env.env.query_balance(&sender, "uarch")?.u128()).is_equal_to(49);
+        assert_that!(arch_env
+            .env
+            .query_balance(&arch_env.client1.address()?, "uarch")?
+            .u128())
+        .is_equal_to(0);
+        let transferred = juno_env
+            .env
+            .bank_querier()
+            .balance(&juno_server.address()?, None)?;
+        assert_that!(transferred).has_length(1);
+        assert_that!(transferred[0].denom).starts_with("ibc/");
+        assert_that!(transferred[0].amount.u128()).is_equal_to(101);
+
+        Ok(())
+    }
+
+    #[test]
+    fn relay_fee_that_arrived_with_the_mail_is_paid_to_the_collector() -> anyhow::Result<()> {
+        let interchain = MockBech32InterchainEnv::new(vec![
+            ("juno-1", "juno18k2uq7srsr8lwrae6zr0qahpn29rsp7tw83nyx"),
+            (
+                "neutron-1",
+                "neutron18k2uq7srsr8lwrae6zr0qahpn29rsp7tu2m2ea",
+            ),
+        ]);
+
+        let juno_env = TestEnv::setup(interchain.get_chain("juno-1")?)?;
+        let neutron_env = TestEnv::setup(interchain.get_chain("neutron-1")?)?;
+        juno_env.abs.connect_to(&neutron_env.abs, &interchain)?;
+
+        let archway = TruncatedChainId::from_str("archway")?;
+        let juno = TruncatedChainId::from_str("juno")?;
+        let neutron = TruncatedChainId::from_str("neutron")?;
+        let juno_server = ServerInterface::new(IBCMAIL_SERVER_ID, juno_env.env.clone());
+        let juno_admin = juno_env
+            .abs
+            .fetch_or_build_account(Namespace::new(IBCMAIL_NAMESPACE)?, |builder| builder)?;
+        let collector = juno_env.env.addr_make("juno-collector");
+        juno_server
+            .call_as(&juno_admin.address()?)
+            .set_relay_fee(Some(RelayFee {
+                amount: coin(100, "uvoucher"),
+                collector: collector.clone(),
+            }))?;
+
+        // Mail from archway arrives at juno with the tokens of its ICS-20 transfer
+        let remote_server = juno_env.env.addr_make("remote-server");
+        let hook_sender = ibc_hooks_sender(
+            &MockApi::default().with_prefix("juno"),
+            "channel-0",
+            remote_server.as_str(),
+        )?;
+        juno_server
+            .call_as(&juno_admin.address()?)
+            .set_trusted_hook_sender(
+                "channel-0".to_string(),
+                remote_server.to_string(),
+                Some(archway.clone()),
+            )?;
+        let relay_account = juno_env.client1.account();
+        relay_account.as_ref().execute_on_module(
+            IBCMAIL_SERVER_ID,
+            adapter::ExecuteMsg::<Empty>::Base(BaseExecuteMsg {
+                account_address: None,
+                msg: AdapterBaseMsg::UpdateAuthorizedAddresses {
+                    to_add: vec![hook_sender.to_string()],
+                    to_remove: vec![],
+                },
+            }),
+            vec![],
+        )?;
+        let receive_ics20 = |subject: &str, funds: &[Coin]| -> anyhow::Result<()> {
+            let mut msg =
+                create_test_message(juno_env.client2.account().id()?, relay_account.id()?);
+            msg.id = subject.to_string();
+            msg.message = Message::new(
+                Recipient::account(neutron_env.client1.account().id()?, Some(neutron.clone())),
+                subject,
+                "test-body",
+            );
+            // The sending server claims that the fee was prepaid either way
+            let header = Header {
+                relay_fees: vec![(juno.clone(), coin(100, "uvoucher"))],
+                ..Header::new(AccountTrace::Remote(vec![
+                    archway.clone(),
+                    juno.clone(),
+                    neutron.clone(),
+                ]))
+            };
+            let msg = ServerIbcMessage::RouteMessage { msg, header };
+            let res = juno_env.env.call_as(&hook_sender).execute(
+                &adapter::ExecuteMsg::<ServerExecuteMsg>::Module(AdapterRequestMsg {
+                    account_address: Some(relay_account.address()?.to_string()),
+                    request: ServerExecuteMsg::ReceiveIcs20 {
+                        msg: encode_server_msg(&msg)?,
+                    },
+                }),
+                funds,
+                &juno_server.address()?,
+            )?;
+            interchain.await_and_check_packets("juno-1", res)?;
+            Ok(())
+        };
+
+        // Juno pays its fee to its collector and the rest to the relay account
+        juno_env
+            .env
+            .add_balance(&hook_sender, vec![coin(150, "uvoucher")])?;
+        receive_ics20("paid-subject", &[coin(150, "uvoucher")])?;
+
+        let received =
+            neutron_env
+                .client1
+                .list_messages(MessageStatus::Received, None, None, None)?;
+        assert_that!(received.messages).has_length(1);
+        assert_that!(juno_env.env.query_balance(&collector, "uvoucher")?.u128()).is_equal_to(100);
+        assert_that!(juno_env
+            .env
+            .query_balance(&relay_account.address()?, "uvoucher")?
+            .u128())
+        .is_equal_to(50);
+        assert_that!(juno_env
+            .env
+            .query_balance(&juno_server.address()?, "uvoucher")?
+            .u128())
+        .is_equal_to(0);
+
+        // A fee that is only claimed in the header is not paid, the mail is bounced instead
+        receive_ics20("unpaid-subject", &[])?;
+
+        let received =
+            neutron_env
+                .client1
+                .list_messages(MessageStatus::Received, None, None, None)?;
+        assert_that!(received.messages).has_length(1);
+        assert_that!(juno_env.env.query_balance(&collector, "uvoucher")?.u128()).is_equal_to(100);
+
+        Ok(())
+    }
+}
+
+mod pause {
+    use std::str::FromStr;
+
+    use abstract_app::objects::TruncatedChainId;
+    use cw_orch_interchain::prelude::*;
+    use ibcmail::{server::state::PauseMode, MessageStatus};
+    use server::{msg::ServerExecuteMsgFns, ServerQueryMsgFns};
+
+    use super::*;
+
+    #[test]
+    fn paused_server_rejects_mail() -> anyhow::Result<()> {
+        let mock = MockBech32::new("mock");
+        let env = TestEnv::setup(mock)?;
+        let server = ServerInterface::new(IBCMAIL_SERVER_ID, env.env.clone());
+
+        let admin = env
+            .abs
+            .fetch_or_build_account(Namespace::new(IBCMAIL_NAMESPACE)?, |builder| builder)?;
+        let local_msg = Message::new(
+            Recipient::account(env.client2.account().id()?, None),
+            "test-subject",
+            "test-body",
+        );
+        let remote_msg = Message::new(
+            Recipient::account(
+                env.client2.account().id()?,
+                Some(TruncatedChainId::from_str("juno")?),
+            ),
+            "test-subject",
+            "test-body",
+        );
+        let remote_route = AccountTrace::Remote(vec![TruncatedChainId::from_str("juno")?]);
+
+        // Outbound pause keeps local delivery running
+        server
+            .call_as(&admin.address()?)
+            .set_pause(Some(PauseMode::Outbound))?;
+        let res = env
+            .client1
+            .send_message(remote_msg.clone(), Some(remote_route.clone()));
+        assert_that!(res)
+            .is_err()
+            .matches(|e| e.root().to_string().contains("paused for outbound mail"));
+        env.client1.send_message(local_msg.clone(), None)?;
+
+        server
+            .call_as(&admin.address()?)
+            .set_pause(Some(PauseMode::All))?;
+        let res = env.client1.send_message(local_msg.clone(), None);
+        assert_that!(res)
+            .is_err()
+            .matches(|e| e.root().to_string().contains("paused for all mail"));
+
+        // Chain breakers apply while the server is running
+        server.call_as(&admin.address()?).set_pause(None)?;
+        server.call_as(&admin.address()?).set_chain_breaker(
+            TruncatedChainId::from_str("juno")?,
+            Some(PauseMode::Outbound),
+        )?;
+        let res = env.client1.send_message(remote_msg, Some(remote_route));
+        assert_that!(res)
+            .is_err()
+            .matches(|e| e.root().to_string().contains("to chain juno is disabled"));
+        env.client1.send_message(local_msg, None)?;
+
+        let status = server.pause_status()?;
+        assert_that!(status.paused).is_none();
+        assert_that!(status.chain_breakers).has_length(1);
+
+        Ok(())
+    }
+
+    #[test]
+    fn paused_server_bounces_mail_from_other_chains() -> anyhow::Result<()> {
+        let interchain = MockBech32InterchainEnv::new(vec![
+            ("juno-1", "juno18k2uq7srsr8lwrae6zr0qahpn29rsp7tw83nyx"),
+            (
+                "archway-1",
+                "archway18k2uq7srsr8lwrae6zr0qahpn29rsp7td7wvfd",
+            ),
+        ]);
+
+        let arch_env = TestEnv::setup(interchain.get_chain("archway-1")?)?;
+        let juno_env = TestEnv::setup(interchain.get_chain("juno-1")?)?;
+
+        arch_env.abs.connect_to(&juno_env.abs, &interchain)?;
+
+        let juno_server = ServerInterface::new(IBCMAIL_SERVER_ID, juno_env.env.clone());
+        let juno_admin = juno_env
+            .abs
+            .fetch_or_build_account(Namespace::new(IBCMAIL_NAMESPACE)?, |builder| builder)?;
+        juno_server
+            .call_as(&juno_admin.address()?)
+            .set_pause(Some(PauseMode::Inbound))?;
+
+        let msg = Message::new(
+            Recipient::account(
+                juno_env.client1.account().id()?,
+                Some(TruncatedChainId::from_str("juno")?),
+            ),
+            "test-subject",
+            "test-body",
+        );
+        let res = arch_env.client1.send_message(msg, None)?;
+        interchain.await_and_check_packets("archway-1", res)?;
+
+        let juno_messages =
+            juno_env
+                .client1
+                .list_messages(MessageStatus::Received, None, None, None)?;
+        assert_that!(juno_messages.messages).is_empty();
+
+        // The sender is told instead of the mail being dropped with the failed packet
+        let arch_messages =
+            arch_env
+                .client1
+                .list_messages(MessageStatus::Received, None, None, None)?;
+        assert_that!(arch_messages.messages).has_length(1);
+        assert_that!(arch_messages.messages[0].message.subject)
+            .is_equal_to("Undeliverable: test-subject".to_string());
+
+        Ok(())
+    }
+}
+
+mod statistics {
+    use ibcmail::server::state::MessageStats;
+    use server::{msg::ServerExecuteMsgFns, ServerQueryMsgFns};
+
+    use super::*;
+    use crate::receiver::{ReceiverExecuteMsgFns, RECEIVER_ID};
+
+    #[test]
+    fn local_messages_are_counted() -> anyhow::Result<()> {
+        let mock = MockBech32::new("mock");
+        let env = TestEnv::setup(mock)?;
+        let server = ServerInterface::new(IBCMAIL_SERVER_ID, env.env.clone());
+
+        for subject in ["first-subject", "second-subject"] {
+            env.client1.send_message(
+                Message::new(
+                    Recipient::account(env.client2.account().id()?, None),
+                    subject,
+                    "test-body",
+                ),
+                None,
+            )?;
+        }
+
+        let expected = MessageStats {
+            originated: 2,
+            delivered: 2,
+            ..Default::default()
+        };
+        assert_that!(server.statistics()?.total).is_equal_to(expected.clone());
+
+        let res = server.chain_statistics(None, None)?;
+        assert_that!(res.chains).has_length(1);
+        assert_that!(res.chains[0].source).is_equal_to(res.chains[0].destination.clone());
+        assert_that!(res.chains[0].stats).is_equal_to(expected);
+
+        Ok(())
+    }
+
+    #[test]
+    fn dead_letters_are_counted_as_delivered_once_retried() -> anyhow::Result<()> {
+        let mock = MockBech32::new("mock");
+        let env = TestEnv::setup(mock)?;
+        let server = ServerInterface::new(IBCMAIL_SERVER_ID, env.env.clone());
+
+        let account = env.client2.account();
+        let receiver = env.install_receiver(account)?;
+        receiver.set_rejecting(true)?;
+
+        env.client1.send_message(
+            Message::new(
+                Recipient::module(account.id()?, RECEIVER_ID, None),
+                "test-subject",
+                "test-body",
+            ),
+            None,
+        )?;
+        let delivered = server.statistics()?.total.delivered;
+        assert_that!(delivered).is_equal_to(0);
+
+        let address = account.address()?.to_string();
+        let id = server.dead_letters(address.clone(), None, None)?.messages[0]
+            .msg
+            .id
+            .clone();
+        receiver.set_rejecting(false)?;
+        server
+            .call_as(&account.address()?)
+            .retry_dead_letter(address, id)?;
+
+        let expected = MessageStats {
+            originated: 1,
+            delivered: 1,
+            ..Default::default()
+        };
+        assert_that!(server.statistics()?.total).is_equal_to(expected);
+
+        Ok(())
+    }
+}
+
+mod config {
+    use ibcmail::{MessageStatus, IBCMAIL_CLIENT_ID};
+    use server::{msg::ServerExecuteMsgFns, ServerQueryMsgFns};
+
+    use super::*;
+    use crate::receiver::{ReceiverQueryMsgFns, RECEIVER_ID};
+
+    #[test]
+    fn only_admin_can_update_config() -> anyhow::Result<()> {
+        let mock = MockBech32::new("mock");
+        let env = TestEnv::setup(mock)?;
+        let server = ServerInterface::new(IBCMAIL_SERVER_ID, env.env.clone());
+        let admin = env
+            .abs
+            .fetch_or_build_account(Namespace::new(IBCMAIL_NAMESPACE)?, |builder| builder)?;
+
+        let res = server
+            .call_as(&env.client1.account().address()?)
+            .update_config(None, None, Some(1), None, None);
+        assert_that!(res).is_err().matches(|e| {
+            e.root()
+                .to_string()
+                .contains("Sender is not the owner of the ibcmail namespace")
+        });
+
+        server
+            .call_as(&admin.address()?)
+            .update_config(None, None, Some(1), None, None)?;
+        assert_that!(server.config()?.config.max_hops).is_equal_to(1);
+
+        Ok(())
+    }
+
+    #[test]
+    fn mail_is_delivered_to_first_accepted_client() -> anyhow::Result<()> {
+        let mock = MockBech32::new("mock");
+        let env = TestEnv::setup(mock)?;
+        let server = ServerInterface::new(IBCMAIL_SERVER_ID, env.env.clone());
+        let admin = env
+            .abs
+            .fetch_or_build_account(Namespace::new(IBCMAIL_NAMESPACE)?, |builder| builder)?;
+
+        let account = env.client2.account();
+        let receiver = env.install_receiver(account)?;
+        server.call_as(&admin.address()?).update_config(
+            Some(vec![RECEIVER_ID.to_string(), IBCMAIL_CLIENT_ID.to_string()]),
+            None,
+            None,
+            None,
+            None,
+        )?;
+
+        let msg = Message::new(
+            Recipient::account(account.id()?, None),
+            "test-subject",
+            "test-body",
+        );
+        env.client1.send_message(msg, None)?;
+
+        assert_that!(receiver.received()?).has_length(1);
+        let messages = env
+            .client2
+            .list_messages(MessageStatus::Received, None, None, None)?;
+        assert_that!(messages.messages).is_empty();
+
+        Ok(())
+    }
+}
+
+mod routing {
+    use std::str::FromStr;
+
+    use abstract_app::objects::TruncatedChainId;
+    use cosmwasm_std::coin;
+    use ibcmail::server::{msg::ServerExecuteMsgFns, state::Ics20Route};
+    use server::ServerQueryMsgFns;
+
+    use super::*;
+
+    /// Connect the mock chain to juno and neutron, which both reach osmosis.
+    fn setup_routes(
+        env: &TestEnv<MockBech32>,
+    ) -> anyhow::Result<(ServerInterface<MockBech32>, Account<MockBech32>)> {
+        let server = ServerInterface::new(IBCMAIL_SERVER_ID, env.env.clone());
+        let admin = env
+            .abs
+            .fetch_or_build_account(Namespace::new(IBCMAIL_NAMESPACE)?, |builder| builder)?;
+        let osmosis = TruncatedChainId::from_str("osmosis")?;
+        for (chain, channel) in [("juno", "channel-0"), ("neutron", "channel-1")] {
+            let chain = TruncatedChainId::from_str(chain)?;
+            server.call_as(&admin.address()?).set_ics_20_route(
+                chain.clone(),
+                Some(Ics20Route {
+                    channel: channel.to_string(),
+                    funds: coin(1, "ucosm"),
+                    remote_server: env.env.addr_make("remote-server").to_string(),
+                    relay_account: env.env.addr_make("relay-account").to_string(),
+                }),
+            )?;
+            server
+                .call_as(&admin.address()?)
+                .update_route_table(vec![(chain, vec![osmosis.clone()])], vec![])?;
+        }
+
+        Ok((server, admin))
+    }
+
+    /// Route that the server picks for mail to osmosis.
+    fn osmosis_route(
+        env: &TestEnv<MockBech32>,
+        server: &ServerInterface<MockBech32>,
+    ) -> anyhow::Result<Option<AccountTrace>> {
+        let msg = Message::new(
+            Recipient::account(
+                env.client2.account().id()?,
+                Some(TruncatedChainId::from_str("osmosis")?),
+            ),
+            "test-subject",
+            "test-body",
+        );
+        let sender = Sender::account(env.client1.account().id()?, None);
+        Ok(server.dry_run(msg, None, sender)?.route)
+    }
+
+    fn route_through(
+        env: &TestEnv<MockBech32>,
+        next_hop: &str,
+    ) -> anyhow::Result<Option<AccountTrace>> {
+        Ok(Some(AccountTrace::Remote(vec![
+            TruncatedChainId::from_chain_id(&env.env.block_info()?.chain_id),
+            TruncatedChainId::from_str(next_hop)?,
+            TruncatedChainId::from_str("osmosis")?,
+        ])))
+    }
+
+    #[test]
+    fn cheapest_path_follows_link_costs() -> anyhow::Result<()> {
+        let mock = MockBech32::new("mock");
+        let env = TestEnv::setup(mock)?;
+        let (server, admin) = setup_routes(&env)?;
+        let local = TruncatedChainId::from_chain_id(&env.env.block_info()?.chain_id);
+
+        server.call_as(&admin.address()?).set_link_cost(
+            local.clone(),
+            TruncatedChainId::from_str("juno")?,
+            Some(10),
+        )?;
+        assert_that!(osmosis_route(&env, &server)?).is_equal_to(route_through(&env, "neutron")?);
+
+        // A costly link further along the route counts as well
+        server.call_as(&admin.address()?).set_link_cost(
+            TruncatedChainId::from_str("neutron")?,
+            TruncatedChainId::from_str("osmosis")?,
+            Some(20),
+        )?;
+        assert_that!(osmosis_route(&env, &server)?).is_equal_to(route_through(&env, "juno")?);
+
+        Ok(())
+    }
+
+    #[test]
+    fn preferred_hop_overrides_cheapest_path() -> anyhow::Result<()> {
+        let mock = MockBech32::new("mock");
+        let env = TestEnv::setup(mock)?;
+        let (server, admin) = setup_routes(&env)?;
+        let local = TruncatedChainId::from_chain_id(&env.env.block_info()?.chain_id);
+        let osmosis = TruncatedChainId::from_str("osmosis")?;
+
+        server.call_as(&admin.address()?).set_link_cost(
+            local,
+            TruncatedChainId::from_str("juno")?,
+            Some(10),
+        )?;
+        assert_that!(osmosis_route(&env, &server)?).is_equal_to(route_through(&env, "neutron")?);
+
+        server
+            .call_as(&admin.address()?)
+            .set_preferred_hop(osmosis.clone(), Some(TruncatedChainId::from_str("juno")?))?;
+        assert_that!(osmosis_route(&env, &server)?).is_equal_to(route_through(&env, "juno")?);
+
+        server
+            .call_as(&admin.address()?)
+            .set_preferred_hop(osmosis, None)?;
+        assert_that!(osmosis_route(&env, &server)?).is_equal_to(route_through(&env, "neutron")?);
+
+        Ok(())
+    }
+}
+
+mod limits {
+    use std::str::FromStr;
+
+    use abstract_app::{
+        objects::TruncatedChainId,
+        std::adapter::{self, AdapterBaseMsg, AdapterRequestMsg, BaseExecuteMsg},
+    };
+    use cosmwasm_std::{testing::MockApi, Timestamp};
+    use cw_orch_interchain::prelude::*;
+    use ibcmail::{
+        server::{
+            msg::{ServerExecuteMsg, ServerIbcMessage},
+            transport::ibc_hooks_sender,
+        },
+        MessageStatus,
+    };
+    use server::msg::ServerExecuteMsgFns;
+
+    use super::*;
+
+    /// Have juno receive mail from archway for neutron with `header`, as a transfer from the archway server.
+    /// Returns the mail that the archway sender received.
+    fn forward_over_juno(
+        header: impl FnOnce(Header) -> Header,
+    ) -> anyhow::Result<Vec<IbcMailMessage>> {
+        let interchain = MockBech32InterchainEnv::new(vec![
+            ("juno-1", "juno18k2uq7srsr8lwrae6zr0qahpn29rsp7tw83nyx"),
+            (
+                "archway-1",
+                "archway18k2uq7srsr8lwrae6zr0qahpn29rsp7td7wvfd",
+            ),
+        ]);
+
+        let arch_env = TestEnv::setup(interchain.get_chain("archway-1")?)?;
+        let juno_env = TestEnv::setup(interchain.get_chain("juno-1")?)?;
+
+        // Bounces return to archway over Abstract IBC
+        arch_env.abs.connect_to(&juno_env.abs, &interchain)?;
+
+        let archway = TruncatedChainId::from_str("archway")?;
+        let juno_server = ServerInterface::new(IBCMAIL_SERVER_ID, juno_env.env.clone());
+        let juno_admin = juno_env
+            .abs
+            .fetch_or_build_account(Namespace::new(IBCMAIL_NAMESPACE)?, |builder| builder)?;
+        let arch_server = arch_env.env.addr_make("arch-server");
+        let hook_sender = ibc_hooks_sender(
+            &MockApi::default().with_prefix("juno"),
+            "channel-0",
+            arch_server.as_str(),
+        )?;
+        juno_server
+            .call_as(&juno_admin.address()?)
+            .set_trusted_hook_sender(
+                "channel-0".to_string(),
+                arch_server.to_string(),
+                Some(archway.clone()),
+            )?;
+        let relay_account = juno_env.client1.account();
+        relay_account.as_ref().execute_on_module(
+            IBCMAIL_SERVER_ID,
+            adapter::ExecuteMsg::<Empty>::Base(BaseExecuteMsg {
+                account_address: None,
+                msg: AdapterBaseMsg::UpdateAuthorizedAddresses {
+                    to_add: vec![hook_sender.to_string()],
+                    to_remove: vec![],
+                },
+            }),
+            vec![],
+        )?;
+
+        let sender = arch_env.client1.account().id()?;
+        let msg = IbcMailMessage {
+            sender: Sender::account(sender.clone(), Some(archway.clone())),
+            ..create_test_message(sender, juno_env.client2.account().id()?)
+        };
+        let header = header(Header::new(AccountTrace::Remote(vec![
+            archway,
+            TruncatedChainId::from_str("juno")?,
+            TruncatedChainId::from_str("neutron")?,
+        ])));
+        let res = juno_env.env.call_as(&hook_sender).execute(
+            &adapter::ExecuteMsg::<ServerExecuteMsg>::Module(AdapterRequestMsg {
+                account_address: Some(relay_account.address()?.to_string()),
+                request: ServerExecuteMsg::ReceiveIcs20 {
+                    msg: encode_server_msg(&ServerIbcMessage::RouteMessage { msg, header })?,
+                },
+            }),
+            &[],
+            &juno_server.address()?,
+        )?;
+        interchain.await_and_check_packets("juno-1", res)?;
+
+        Ok(arch_env
+            .client1
+            .list_messages(MessageStatus::Received, None, None, None)?
+            .messages)
+    }
+
+    #[test]
+    fn mail_exceeding_max_hops_is_bounced() -> anyhow::Result<()> {
+        let received = forward_over_juno(|header| Header {
+            max_hops: Some(1),
+            ..header
+        })?;
+
+        assert_that!(received).has_length(1);
+        assert_that!(received[0].message.subject)
+            .is_equal_to("Undeliverable: test-subject".to_string());
+        assert_that!(received[0].message.body).contains("hop limit exceeded");
+
+        Ok(())
+    }
+
+    #[test]
+    fn expired_mail_is_bounced() -> anyhow::Result<()> {
+        let received = forward_over_juno(|header| Header {
+            expires_at: Some(Timestamp::from_seconds(1)),
+            ..header
+        })?;
+
+        assert_that!(received).has_length(1);
+        assert_that!(received[0].message.subject)
+            .is_equal_to("Undeliverable: test-subject".to_string());
+        assert_that!(received[0].message.body).contains("message expired in transit");
+
+        Ok(())
+    }
 }
@@ -1,4 +1,6 @@
 #[cfg(test)]
 mod client;
+#[cfg(test)]
+mod receiver;
 
 pub const TEST_NAMESPACE: &str = "ibcmail-demo";
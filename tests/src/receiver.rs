@@ -0,0 +1,127 @@
+//! A minimal mail receiver app, used to test delivery to modules other than the ibcmail client.
+use abstract_app::{
+    sdk::AbstractSdkError, std::AbstractError, traits::AbstractResponse, AppContract,
+    AppError as AbstractAppError,
+};
+use cosmwasm_schema::QueryResponses;
+use cosmwasm_std::{
+    ensure, to_json_binary, Binary, Deps, DepsMut, Env, MessageInfo, Order, Response, StdError,
+    StdResult,
+};
+use cw_storage_plus::{Item, Map};
+use ibcmail::{receiver::is_mail_server, Header, IbcMailMessage, MessageHash};
+use thiserror::Error;
+
+/// Published under [`crate::TEST_NAMESPACE`]
+pub const RECEIVER_ID: &str = "ibcmail-demo:receiver";
+pub const RECEIVER_VERSION: &str = "0.0.1";
+
+pub type ReceiverApp = AppContract<
+    ReceiverError,
+    ReceiverInstantiateMsg,
+    ReceiverExecuteMsg,
+    ReceiverQueryMsg,
+    ReceiverMigrateMsg,
+>;
+
+pub type ReceiverResult<T = Response> = Result<T, ReceiverError>;
+
+#[derive(Error, Debug, PartialEq)]
+pub enum ReceiverError {
+    #[error("{0}")]
+    Std(#[from] StdError),
+
+    #[error("{0}")]
+    Abstract(#[from] AbstractError),
+
+    #[error("{0}")]
+    AbstractSdk(#[from] AbstractSdkError),
+
+    #[error("{0}")]
+    DappError(#[from] AbstractAppError),
+
+    #[error("Sender is not mail server")]
+    NotMailServer {},
+
+    #[error("Receiver rejects mail")]
+    Rejecting {},
+}
+
+abstract_app::app_msg_types!(ReceiverApp, ReceiverExecuteMsg, ReceiverQueryMsg);
+
+#[cosmwasm_schema::cw_serde]
+pub struct ReceiverInstantiateMsg {}
+
+#[cosmwasm_schema::cw_serde]
+pub struct ReceiverMigrateMsg {}
+
+#[cosmwasm_schema::cw_serde]
+#[derive(cw_orch::ExecuteFns)]
+pub enum ReceiverExecuteMsg {
+    /// Receive a message from the server, see [`ibcmail::receiver::MailReceiverExecuteMsg`]
+    ReceiveMessage { msg: IbcMailMessage, header: Header },
+    /// Make the receiver fail (`true`) or accept (`false`) the messages it receives
+    SetRejecting { rejecting: bool },
+}
+
+#[cosmwasm_schema::cw_serde]
+#[derive(cw_orch::QueryFns, QueryResponses)]
+pub enum ReceiverQueryMsg {
+    #[returns(Vec<IbcMailMessage>)]
+    Received {},
+}
+
+const REJECTING: Item<bool> = Item::new("rejecting");
+const RECEIVED: Map<MessageHash, IbcMailMessage> = Map::new("received");
+
+const APP: ReceiverApp = ReceiverApp::new(RECEIVER_ID, RECEIVER_VERSION, None)
+    .with_execute(execute_handler)
+    .with_query(query_handler);
+
+abstract_app::cw_orch_interface!(APP, ReceiverApp, ReceiverInterface);
+
+fn execute_handler(
+    deps: DepsMut,
+    _env: Env,
+    info: MessageInfo,
+    app: ReceiverApp,
+    msg: ReceiverExecuteMsg,
+) -> ReceiverResult {
+    match msg {
+        ReceiverExecuteMsg::ReceiveMessage { msg, .. } => {
+            ensure!(
+                is_mail_server(&app, deps.as_ref(), info.sender),
+                ReceiverError::NotMailServer {}
+            );
+            ensure!(
+                !REJECTING.may_load(deps.storage)?.unwrap_or_default(),
+                ReceiverError::Rejecting {}
+            );
+            RECEIVED.save(deps.storage, msg.id.clone(), &msg)?;
+
+            Ok(app.response("received").add_attribute("message_id", msg.id))
+        }
+        ReceiverExecuteMsg::SetRejecting { rejecting } => {
+            REJECTING.save(deps.storage, &rejecting)?;
+            Ok(app.response("set_rejecting"))
+        }
+    }
+}
+
+fn query_handler(
+    deps: Deps,
+    _env: Env,
+    _app: &ReceiverApp,
+    msg: ReceiverQueryMsg,
+) -> ReceiverResult<Binary> {
+    match msg {
+        ReceiverQueryMsg::Received {} => {
+            let received = RECEIVED
+                .range(deps.storage, None, None, Order::Ascending)
+                .map(|item| item.map(|(_, msg)| msg))
+                .collect::<StdResult<Vec<_>>>()?;
+            to_json_binary(&received)
+        }
+    }
+    .map_err(Into::into)
+}